@@ -0,0 +1,84 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use sqlx::{SqliteConnection, SqliteExecutor};
+
+const ALIAS_ANIMALS: &[&str] = &[
+    "Otter", "Badger", "Falcon", "Heron", "Lynx", "Marten", "Newt", "Ocelot", "Panda", "Quokka",
+    "Raven", "Salamander", "Tapir", "Urchin", "Vole", "Walrus", "Yak", "Zebra", "Alpaca", "Beaver",
+    "Coyote", "Dingo", "Egret", "Ferret", "Gecko", "Hedgehog", "Ibis", "Jackal", "Koala", "Lemur",
+    "Mole", "Narwhal", "Owl", "Pelican", "Raccoon", "Seal", "Toucan", "Wombat", "Puffin", "Stoat",
+];
+
+/// Numbered rounds of animal names to try before giving up on finding a free alias.
+const MAX_ALIAS_ROUNDS: usize = 20;
+
+/// Returns the pseudonym `user_id` writes under in the thread of `post_id`,
+/// assigning one the first time they post or comment anonymously there. Run it
+/// in the transaction that creates the anonymous post or comment.
+///
+/// Aliases are stable within a thread (the post and its comments) and unique
+/// among the thread's participants, but unrelated across threads, so they can't
+/// be used to link someone's anonymous activity together.
+pub async fn thread_alias(conn: &mut SqliteConnection, post_id: &str, user_id: &str) -> Result<String, sqlx::Error> {
+    if let Some(alias) = find_alias(&mut *conn, post_id, user_id).await? {
+        return Ok(alias);
+    }
+
+    let taken: Vec<String> = sqlx::query_scalar("SELECT alias FROM anonymous_aliases WHERE post_id = ?")
+        .bind(post_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+    let mut hasher = DefaultHasher::new();
+    (post_id, user_id).hash(&mut hasher);
+    let start = hasher.finish() as usize;
+
+    // Try every animal starting from a per-user offset, then numbered variants
+    for round in 1..=MAX_ALIAS_ROUNDS {
+        for i in 0..ALIAS_ANIMALS.len() {
+            let animal = ALIAS_ANIMALS[(start + i) % ALIAS_ANIMALS.len()];
+            let alias = if round == 1 {
+                format!("Anonymous {}", animal)
+            } else {
+                format!("Anonymous {} {}", animal, round)
+            };
+
+            if taken.contains(&alias) {
+                continue;
+            }
+
+            // The unique index settles races between two first-time posters
+            let inserted = sqlx::query(
+                "INSERT OR IGNORE INTO anonymous_aliases (post_id, user_id, alias) VALUES (?, ?, ?)"
+            )
+            .bind(post_id)
+            .bind(user_id)
+            .bind(&alias)
+            .execute(&mut *conn)
+            .await?
+            .rows_affected()
+                > 0;
+
+            if inserted {
+                return Ok(alias);
+            }
+
+            if let Some(existing) = find_alias(&mut *conn, post_id, user_id).await? {
+                return Ok(existing);
+            }
+        }
+    }
+
+    Err(sqlx::Error::Protocol(format!("no free anonymous alias left in thread {}", post_id)))
+}
+
+/// Looks up the pseudonym already assigned to `user_id` in the thread of `post_id`.
+/// Read paths use this so viewing a thread never assigns aliases.
+pub async fn find_alias<'e>(executor: impl SqliteExecutor<'e>, post_id: &str, user_id: &str) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT alias FROM anonymous_aliases WHERE post_id = ? AND user_id = ?")
+        .bind(post_id)
+        .bind(user_id)
+        .fetch_optional(executor)
+        .await
+}
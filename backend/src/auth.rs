@@ -44,13 +44,7 @@ pub fn extract_token(req: &HttpRequest) -> Option<String> {
     req.headers()
         .get("Authorization")
         .and_then(|h| h.to_str().ok())
-        .and_then(|s| {
-            if s.starts_with("Bearer ") {
-                Some(s[7..].to_string())
-            } else {
-                None
-            }
-        })
+        .and_then(|s| s.strip_prefix("Bearer ").map(|t| t.to_string()))
 }
 
 pub async fn get_current_user(req: &HttpRequest, state: &web::Data<AppState>) -> Option<User> {
@@ -9,6 +9,11 @@ pub async fn init_db() -> Result<SqlitePool, sqlx::Error> {
         .connect(&database_url)
         .await?;
 
+    create_schema(pool).await
+}
+
+/// Creates any missing tables, columns and indexes.
+pub async fn create_schema(pool: SqlitePool) -> Result<SqlitePool, sqlx::Error> {
     // Create tables
     sqlx::query(
         r#"
@@ -161,6 +166,490 @@ pub async fn init_db() -> Result<SqlitePool, sqlx::Error> {
     .execute(&pool)
     .await?;
 
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS blocks (
+            id TEXT PRIMARY KEY,
+            blocker_id TEXT NOT NULL,
+            blocked_id TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (blocker_id) REFERENCES users(id),
+            FOREIGN KEY (blocked_id) REFERENCES users(id),
+            UNIQUE(blocker_id, blocked_id)
+        )
+        "#,
+    )
+    .execute(&pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS mutes (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            target_type TEXT NOT NULL,
+            target_id TEXT NOT NULL,
+            expires_at TEXT,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (user_id) REFERENCES users(id),
+            UNIQUE(user_id, target_type, target_id)
+        )
+        "#,
+    )
+    .execute(&pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS follows (
+            id TEXT PRIMARY KEY,
+            follower_id TEXT NOT NULL,
+            followee_id TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (follower_id) REFERENCES users(id),
+            FOREIGN KEY (followee_id) REFERENCES users(id),
+            UNIQUE(follower_id, followee_id)
+        )
+        "#,
+    )
+    .execute(&pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS friend_suggestions (
+            user_id TEXT NOT NULL,
+            suggested_id TEXT NOT NULL,
+            mutual_friends_count INTEGER NOT NULL DEFAULT 0,
+            shared_groups_count INTEGER NOT NULL DEFAULT 0,
+            interactions_count INTEGER NOT NULL DEFAULT 0,
+            score REAL NOT NULL DEFAULT 0,
+            computed_at TEXT NOT NULL,
+            PRIMARY KEY (user_id, suggested_id),
+            FOREIGN KEY (user_id) REFERENCES users(id),
+            FOREIGN KEY (suggested_id) REFERENCES users(id)
+        )
+        "#,
+    )
+    .execute(&pool)
+    .await?;
+
+    // Accepted friendships in both directions, so graph queries don't need to
+    // check user_id and friend_id separately
+    sqlx::query(
+        r#"
+        CREATE VIEW IF NOT EXISTS friend_edges AS
+        SELECT user_id, friend_id FROM friendships WHERE status = 'accepted'
+        UNION ALL
+        SELECT friend_id, user_id FROM friendships WHERE status = 'accepted'
+        "#,
+    )
+    .execute(&pool)
+    .await?;
+
+    // Columns added after the initial schema
+    add_column_if_missing(&pool, "users", "requires_follow_approval", "INTEGER NOT NULL DEFAULT 0").await?;
+    add_column_if_missing(&pool, "users", "email_visibility", "TEXT NOT NULL DEFAULT 'friends'").await?;
+    add_column_if_missing(&pool, "users", "bio_visibility", "TEXT NOT NULL DEFAULT 'everyone'").await?;
+    add_column_if_missing(&pool, "users", "friends_list_visibility", "TEXT NOT NULL DEFAULT 'everyone'").await?;
+    add_column_if_missing(&pool, "users", "searchable_by", "TEXT NOT NULL DEFAULT 'everyone'").await?;
+    add_column_if_missing(&pool, "users", "friend_requests_from", "TEXT NOT NULL DEFAULT 'everyone'").await?;
+    add_column_if_missing(&pool, "friendships", "responded_at", "TEXT").await?;
+    add_column_if_missing(&pool, "follows", "responded_at", "TEXT").await?;
+    add_column_if_missing(&pool, "groups", "category", "TEXT").await?;
+    add_column_if_missing(&pool, "groups", "posting_policy", "TEXT NOT NULL DEFAULT 'everyone'").await?;
+    add_column_if_missing(&pool, "groups", "require_post_approval", "INTEGER NOT NULL DEFAULT 0").await?;
+    add_column_if_missing(&pool, "groups", "allow_anonymous", "INTEGER NOT NULL DEFAULT 1").await?;
+    add_column_if_missing(&pool, "users", "is_admin", "INTEGER NOT NULL DEFAULT 0").await?;
+    add_column_if_missing(&pool, "posts", "status", "TEXT NOT NULL DEFAULT 'published'").await?;
+    add_column_if_missing(&pool, "posts", "is_pinned", "INTEGER NOT NULL DEFAULT 0").await?;
+    add_column_if_missing(&pool, "posts", "comments_locked", "INTEGER NOT NULL DEFAULT 0").await?;
+    add_column_if_missing(&pool, "posts", "publish_at", "TEXT").await?;
+    add_column_if_missing(&pool, "posts", "repost_of_id", "TEXT").await?;
+    add_column_if_missing(&pool, "posts", "is_plain_repost", "INTEGER NOT NULL DEFAULT 0").await?;
+    add_column_if_missing(&pool, "posts", "link_url", "TEXT").await?;
+    add_column_if_missing(&pool, "group_members", "show_in_feed", "INTEGER NOT NULL DEFAULT 1").await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS group_tags (
+            group_id TEXT NOT NULL,
+            tag TEXT NOT NULL,
+            PRIMARY KEY (group_id, tag),
+            FOREIGN KEY (group_id) REFERENCES groups(id)
+        )
+        "#,
+    )
+    .execute(&pool)
+    .await?;
+
+    // Private group membership workflow
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS group_join_requests (
+            id TEXT PRIMARY KEY,
+            group_id TEXT NOT NULL,
+            user_id TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (group_id) REFERENCES groups(id),
+            FOREIGN KEY (user_id) REFERENCES users(id),
+            UNIQUE(group_id, user_id)
+        )
+        "#,
+    )
+    .execute(&pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS group_invite_codes (
+            code TEXT PRIMARY KEY,
+            group_id TEXT NOT NULL,
+            created_by TEXT NOT NULL,
+            expires_at TEXT,
+            max_uses INTEGER,
+            uses INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (group_id) REFERENCES groups(id),
+            FOREIGN KEY (created_by) REFERENCES users(id)
+        )
+        "#,
+    )
+    .execute(&pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS group_invitations (
+            id TEXT PRIMARY KEY,
+            group_id TEXT NOT NULL,
+            user_id TEXT NOT NULL,
+            invited_by TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (group_id) REFERENCES groups(id),
+            FOREIGN KEY (user_id) REFERENCES users(id),
+            FOREIGN KEY (invited_by) REFERENCES users(id),
+            UNIQUE(group_id, user_id)
+        )
+        "#,
+    )
+    .execute(&pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS group_bans (
+            id TEXT PRIMARY KEY,
+            group_id TEXT NOT NULL,
+            user_id TEXT NOT NULL,
+            banned_by TEXT NOT NULL,
+            reason TEXT,
+            anonymous_alias TEXT,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (group_id) REFERENCES groups(id),
+            FOREIGN KEY (user_id) REFERENCES users(id),
+            FOREIGN KEY (banned_by) REFERENCES users(id)
+        )
+        "#,
+    )
+    .execute(&pool)
+    .await?;
+
+    // Per-thread pseudonyms for anonymous posts and comments
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS anonymous_aliases (
+            post_id TEXT NOT NULL,
+            user_id TEXT NOT NULL,
+            alias TEXT NOT NULL,
+            PRIMARY KEY (post_id, user_id),
+            FOREIGN KEY (post_id) REFERENCES posts(id),
+            FOREIGN KEY (user_id) REFERENCES users(id),
+            UNIQUE(post_id, alias)
+        )
+        "#,
+    )
+    .execute(&pool)
+    .await?;
+
+    // Audit trail of site admins unmasking anonymous authors
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS anonymity_reveals (
+            id TEXT PRIMARY KEY,
+            admin_id TEXT NOT NULL,
+            target_type TEXT NOT NULL,
+            target_id TEXT NOT NULL,
+            revealed_user_id TEXT NOT NULL,
+            reason TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (admin_id) REFERENCES users(id),
+            FOREIGN KEY (revealed_user_id) REFERENCES users(id)
+        )
+        "#,
+    )
+    .execute(&pool)
+    .await?;
+
+    // Friend lists ("close friends", "family") that custom-audience posts can target
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS friend_lists (
+            id TEXT PRIMARY KEY,
+            owner_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (owner_id) REFERENCES users(id),
+            UNIQUE(owner_id, name)
+        )
+        "#,
+    )
+    .execute(&pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS friend_list_members (
+            list_id TEXT NOT NULL,
+            user_id TEXT NOT NULL,
+            added_at TEXT NOT NULL,
+            PRIMARY KEY (list_id, user_id),
+            FOREIGN KEY (list_id) REFERENCES friend_lists(id),
+            FOREIGN KEY (user_id) REFERENCES users(id)
+        )
+        "#,
+    )
+    .execute(&pool)
+    .await?;
+
+    // Who a personal post is shown to beyond its visibility: kind is 'list' or 'user'
+    // for a custom audience, or 'except' for friends left out
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS post_audience (
+            post_id TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            target_id TEXT NOT NULL,
+            PRIMARY KEY (post_id, kind, target_id),
+            FOREIGN KEY (post_id) REFERENCES posts(id)
+        )
+        "#,
+    )
+    .execute(&pool)
+    .await?;
+
+    // Stories expire and are hard-deleted by the sweeper, views included
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS stories (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            content TEXT NOT NULL,
+            media_url TEXT,
+            visibility TEXT NOT NULL DEFAULT 'friends_only',
+            created_at TEXT NOT NULL,
+            expires_at TEXT NOT NULL,
+            FOREIGN KEY (user_id) REFERENCES users(id)
+        )
+        "#,
+    )
+    .execute(&pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS story_views (
+            story_id TEXT NOT NULL,
+            viewer_id TEXT NOT NULL,
+            viewed_at TEXT NOT NULL,
+            PRIMARY KEY (story_id, viewer_id),
+            FOREIGN KEY (story_id) REFERENCES stories(id),
+            FOREIGN KEY (viewer_id) REFERENCES users(id)
+        )
+        "#,
+    )
+    .execute(&pool)
+    .await?;
+
+    // Users mentioned in a post (comment_id NULL) or in one of its comments
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS mentions (
+            post_id TEXT NOT NULL,
+            comment_id TEXT,
+            user_id TEXT NOT NULL,
+            FOREIGN KEY (post_id) REFERENCES posts(id),
+            FOREIGN KEY (comment_id) REFERENCES comments(id),
+            FOREIGN KEY (user_id) REFERENCES users(id)
+        )
+        "#,
+    )
+    .execute(&pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS post_hashtags (
+            post_id TEXT NOT NULL,
+            tag TEXT NOT NULL,
+            PRIMARY KEY (post_id, tag),
+            FOREIGN KEY (post_id) REFERENCES posts(id)
+        )
+        "#,
+    )
+    .execute(&pool)
+    .await?;
+
+    // Polls attached to posts; a user's vote is one row per chosen option
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS polls (
+            post_id TEXT PRIMARY KEY,
+            multiple_choice INTEGER NOT NULL DEFAULT 0,
+            anonymous INTEGER NOT NULL DEFAULT 0,
+            results_visibility TEXT NOT NULL DEFAULT 'always',
+            closes_at TEXT,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (post_id) REFERENCES posts(id)
+        )
+        "#,
+    )
+    .execute(&pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS poll_options (
+            id TEXT PRIMARY KEY,
+            post_id TEXT NOT NULL,
+            text TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            FOREIGN KEY (post_id) REFERENCES polls(post_id)
+        )
+        "#,
+    )
+    .execute(&pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS poll_votes (
+            post_id TEXT NOT NULL,
+            option_id TEXT NOT NULL,
+            user_id TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            PRIMARY KEY (option_id, user_id),
+            FOREIGN KEY (option_id) REFERENCES poll_options(id),
+            FOREIGN KEY (user_id) REFERENCES users(id)
+        )
+        "#,
+    )
+    .execute(&pool)
+    .await?;
+
+    // Link previews cached by URL; status is 'pending', 'ready' or 'failed'
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS link_previews (
+            url TEXT PRIMARY KEY,
+            status TEXT NOT NULL DEFAULT 'pending',
+            title TEXT,
+            description TEXT,
+            image_url TEXT,
+            site_name TEXT,
+            error TEXT,
+            requested_at TEXT NOT NULL,
+            fetched_at TEXT
+        )
+        "#,
+    )
+    .execute(&pool)
+    .await?;
+
+    // Full-text search indexes, kept in sync with their source tables by triggers.
+    // Only fields everyone may search on are indexed (user bios can be private).
+    sqlx::query("CREATE VIRTUAL TABLE IF NOT EXISTS posts_fts USING fts5(post_id UNINDEXED, content)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("CREATE VIRTUAL TABLE IF NOT EXISTS users_fts USING fts5(user_id UNINDEXED, username, display_name)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("CREATE VIRTUAL TABLE IF NOT EXISTS groups_fts USING fts5(group_id UNINDEXED, name, description)")
+        .execute(&pool)
+        .await?;
+
+    let fts_triggers = [
+        r#"
+        CREATE TRIGGER IF NOT EXISTS posts_fts_insert AFTER INSERT ON posts BEGIN
+            INSERT INTO posts_fts (post_id, content) VALUES (new.id, new.content);
+        END
+        "#,
+        r#"
+        CREATE TRIGGER IF NOT EXISTS posts_fts_update AFTER UPDATE OF content ON posts BEGIN
+            UPDATE posts_fts SET content = new.content WHERE post_id = new.id;
+        END
+        "#,
+        r#"
+        CREATE TRIGGER IF NOT EXISTS posts_fts_delete AFTER DELETE ON posts BEGIN
+            DELETE FROM posts_fts WHERE post_id = old.id;
+        END
+        "#,
+        r#"
+        CREATE TRIGGER IF NOT EXISTS users_fts_insert AFTER INSERT ON users BEGIN
+            INSERT INTO users_fts (user_id, username, display_name) VALUES (new.id, new.username, new.display_name);
+        END
+        "#,
+        r#"
+        CREATE TRIGGER IF NOT EXISTS users_fts_update AFTER UPDATE OF username, display_name ON users BEGIN
+            UPDATE users_fts SET username = new.username, display_name = new.display_name WHERE user_id = new.id;
+        END
+        "#,
+        r#"
+        CREATE TRIGGER IF NOT EXISTS users_fts_delete AFTER DELETE ON users BEGIN
+            DELETE FROM users_fts WHERE user_id = old.id;
+        END
+        "#,
+        r#"
+        CREATE TRIGGER IF NOT EXISTS groups_fts_insert AFTER INSERT ON groups BEGIN
+            INSERT INTO groups_fts (group_id, name, description) VALUES (new.id, new.name, COALESCE(new.description, ''));
+        END
+        "#,
+        r#"
+        CREATE TRIGGER IF NOT EXISTS groups_fts_update AFTER UPDATE OF name, description ON groups BEGIN
+            UPDATE groups_fts SET name = new.name, description = COALESCE(new.description, '') WHERE group_id = new.id;
+        END
+        "#,
+        r#"
+        CREATE TRIGGER IF NOT EXISTS groups_fts_delete AFTER DELETE ON groups BEGIN
+            DELETE FROM groups_fts WHERE group_id = old.id;
+        END
+        "#,
+    ];
+    for trigger in fts_triggers {
+        sqlx::query(trigger).execute(&pool).await?;
+    }
+
+    // Index rows that existed before the search tables did
+    let posts_indexed: i32 = sqlx::query_scalar("SELECT COUNT(*) FROM posts_fts").fetch_one(&pool).await?;
+    if posts_indexed == 0 {
+        sqlx::query("INSERT INTO posts_fts (post_id, content) SELECT id, content FROM posts")
+            .execute(&pool)
+            .await?;
+    }
+    let users_indexed: i32 = sqlx::query_scalar("SELECT COUNT(*) FROM users_fts").fetch_one(&pool).await?;
+    if users_indexed == 0 {
+        sqlx::query("INSERT INTO users_fts (user_id, username, display_name) SELECT id, username, display_name FROM users")
+            .execute(&pool)
+            .await?;
+    }
+    let groups_indexed: i32 = sqlx::query_scalar("SELECT COUNT(*) FROM groups_fts").fetch_one(&pool).await?;
+    if groups_indexed == 0 {
+        sqlx::query("INSERT INTO groups_fts (group_id, name, description) SELECT id, name, COALESCE(description, '') FROM groups")
+            .execute(&pool)
+            .await?;
+    }
+
     // Create indexes for better performance
     sqlx::query("CREATE INDEX IF NOT EXISTS idx_posts_user_id ON posts(user_id)")
         .execute(&pool)
@@ -181,6 +670,92 @@ pub async fn init_db() -> Result<SqlitePool, sqlx::Error> {
         .execute(&pool)
         .await?;
 
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_blocks_blocked_id ON blocks(blocked_id)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_follows_followee_id ON follows(followee_id)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_group_members_user_id ON group_members(user_id)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_group_tags_tag ON group_tags(tag)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_group_invitations_user_id ON group_invitations(user_id)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_likes_user_id ON likes(user_id)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_comments_user_id ON comments(user_id)")
+        .execute(&pool)
+        .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_posts_status_publish_at ON posts(status, publish_at)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_posts_repost_of_id ON posts(repost_of_id)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_stories_user_id_expires_at ON stories(user_id, expires_at)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_stories_expires_at ON stories(expires_at)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_friend_list_members_user_id ON friend_list_members(user_id)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_post_audience_target_id ON post_audience(target_id)")
+        .execute(&pool)
+        .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_mentions_post_id ON mentions(post_id)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_post_hashtags_tag ON post_hashtags(tag)")
+        .execute(&pool)
+        .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_poll_options_post ON poll_options(post_id, position)")
+        .execute(&pool)
+        .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_poll_votes_user ON poll_votes(post_id, user_id)")
+        .execute(&pool)
+        .await?;
+
+    // One named ban per member; anonymous bans sit alongside it so they can't be probed for
+    sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS idx_group_bans_named ON group_bans(group_id, user_id) WHERE anonymous_alias IS NULL")
+        .execute(&pool)
+        .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_link_previews_status ON link_previews(status, requested_at)")
+        .execute(&pool)
+        .await?;
+
     println!("Database initialized successfully");
     Ok(pool)
 }
+
+async fn add_column_if_missing(
+    pool: &SqlitePool,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<(), sqlx::Error> {
+    let exists: i32 = sqlx::query_scalar("SELECT COUNT(*) FROM pragma_table_info(?) WHERE name = ?")
+        .bind(table)
+        .bind(column)
+        .fetch_one(pool)
+        .await?;
+
+    if exists == 0 {
+        sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+            .execute(pool)
+            .await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,136 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::auth::require_auth;
+use crate::models::{AnonymityReveal, Comment, Post, RevealAuthorRequest, RevealAuthorResponse, User};
+use crate::AppState;
+
+/// Reveals must be justified with at least this many characters.
+const MIN_REVEAL_REASON_LENGTH: usize = 20;
+
+/// Break-glass unmasking of an anonymous post or comment author. Only site
+/// admins may do this, a reason is required, and every reveal is recorded.
+pub async fn reveal_anonymous_author(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<RevealAuthorRequest>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    if !current_user.is_admin {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Only site admins can reveal anonymous authors"
+        }));
+    }
+
+    if body.reason.trim().chars().count() < MIN_REVEAL_REASON_LENGTH {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("A reason of at least {} characters is required", MIN_REVEAL_REASON_LENGTH)
+        }));
+    }
+
+    let (target_type, target_id, author) = match (&body.post_id, &body.comment_id) {
+        (Some(post_id), None) => {
+            let post = sqlx::query_as::<_, Post>("SELECT * FROM posts WHERE id = ?")
+                .bind(post_id)
+                .fetch_optional(&state.db)
+                .await;
+            ("post", post_id, post.map(|p| p.map(|p| (p.user_id, p.is_anonymous))))
+        }
+        (None, Some(comment_id)) => {
+            let comment = sqlx::query_as::<_, Comment>("SELECT * FROM comments WHERE id = ?")
+                .bind(comment_id)
+                .fetch_optional(&state.db)
+                .await;
+            ("comment", comment_id, comment.map(|c| c.map(|c| (c.user_id, c.is_anonymous))))
+        }
+        _ => return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Provide exactly one of post_id or comment_id"
+        })),
+    };
+
+    let author_id = match author {
+        Ok(Some((author_id, true))) => author_id,
+        Ok(Some((_, false))) => return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("This {} is not anonymous", target_type)
+        })),
+        Ok(None) => return HttpResponse::NotFound().json(serde_json::json!({
+            "error": if target_type == "post" { "Post not found" } else { "Comment not found" }
+        })),
+        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        })),
+    };
+
+    let user = match sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(&author_id)
+        .fetch_optional(&state.db)
+        .await
+    {
+        Ok(Some(user)) => user,
+        Ok(None) => return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Author no longer exists"
+        })),
+        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        })),
+    };
+
+    // Nothing is revealed unless the audit record was written
+    let reveal_id = Uuid::new_v4().to_string();
+    let result = sqlx::query(
+        "INSERT INTO anonymity_reveals (id, admin_id, target_type, target_id, revealed_user_id, reason, created_at) VALUES (?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(&reveal_id)
+    .bind(&current_user.id)
+    .bind(target_type)
+    .bind(target_id)
+    .bind(&author_id)
+    .bind(body.reason.trim())
+    .bind(Utc::now().to_rfc3339())
+    .execute(&state.db)
+    .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(RevealAuthorResponse {
+            reveal_id,
+            user: user.into(),
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to record reveal: {}", e)
+        })),
+    }
+}
+
+pub async fn get_reveal_log(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    if !current_user.is_admin {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Only site admins can view the reveal log"
+        }));
+    }
+
+    let reveals = sqlx::query_as::<_, AnonymityReveal>(
+        "SELECT * FROM anonymity_reveals ORDER BY created_at DESC"
+    )
+    .fetch_all(&state.db)
+    .await;
+
+    match reveals {
+        Ok(reveals) => HttpResponse::Ok().json(reveals),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to get reveal log: {}", e)
+        })),
+    }
+}
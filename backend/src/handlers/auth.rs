@@ -82,7 +82,7 @@ pub async fn register(
                 token,
                 user: UserResponse {
                     id: user_id,
-                    email: body.email.clone(),
+                    email: Some(body.email.clone()),
                     username: body.username.clone(),
                     display_name: body.display_name.clone(),
                     avatar_url: None,
@@ -112,7 +112,7 @@ pub async fn login(
                 
                 HttpResponse::Ok().json(AuthResponse {
                     token,
-                    user: user.into(),
+                    user: UserResponse::for_self(user),
                 })
             } else {
                 HttpResponse::Unauthorized().json(serde_json::json!({
@@ -134,7 +134,7 @@ pub async fn get_me(
     state: web::Data<AppState>,
 ) -> HttpResponse {
     match require_auth(&req, &state).await {
-        Ok(user) => HttpResponse::Ok().json(UserResponse::from(user)),
+        Ok(user) => HttpResponse::Ok().json(UserResponse::for_self(user)),
         Err(e) => HttpResponse::Unauthorized().json(serde_json::json!({
             "error": e.to_string()
         })),
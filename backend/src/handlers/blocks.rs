@@ -0,0 +1,176 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::auth::require_auth;
+use crate::handlers::friend_lists::remove_from_friend_lists;
+use crate::models::{Block, BlockedUser, User};
+use crate::AppState;
+
+pub async fn get_blocked_users(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let blocks = sqlx::query_as::<_, Block>(
+        "SELECT * FROM blocks WHERE blocker_id = ? ORDER BY created_at DESC"
+    )
+    .bind(&current_user.id)
+    .fetch_all(&state.db)
+    .await;
+
+    match blocks {
+        Ok(blocks) => {
+            let mut blocked_users: Vec<BlockedUser> = Vec::new();
+
+            for block in blocks {
+                if let Ok(Some(user)) = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+                    .bind(&block.blocked_id)
+                    .fetch_optional(&state.db)
+                    .await
+                {
+                    blocked_users.push(BlockedUser {
+                        block_id: block.id,
+                        user: user.into(),
+                        created_at: block.created_at,
+                    });
+                }
+            }
+
+            HttpResponse::Ok().json(blocked_users)
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to get blocked users: {}", e)
+        })),
+    }
+}
+
+pub async fn block_user(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let blocked_id = path.into_inner();
+
+    if current_user.id == blocked_id {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Cannot block yourself"
+        }));
+    }
+
+    let user_exists = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(&blocked_id)
+        .fetch_optional(&state.db)
+        .await;
+
+    if let Ok(None) = user_exists {
+        return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "User not found"
+        }));
+    }
+
+    let block_id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+
+    let result = sqlx::query(
+        "INSERT INTO blocks (id, blocker_id, blocked_id, created_at) VALUES (?, ?, ?, ?) ON CONFLICT(blocker_id, blocked_id) DO NOTHING"
+    )
+    .bind(&block_id)
+    .bind(&current_user.id)
+    .bind(&blocked_id)
+    .bind(&now)
+    .execute(&state.db)
+    .await;
+
+    if let Err(e) = result {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to block user: {}", e)
+        }));
+    }
+
+    // Blocking ends any friendship, follow or pending request in either direction
+    let _ = sqlx::query(
+        "DELETE FROM friendships WHERE (user_id = ? AND friend_id = ?) OR (user_id = ? AND friend_id = ?)"
+    )
+    .bind(&current_user.id)
+    .bind(&blocked_id)
+    .bind(&blocked_id)
+    .bind(&current_user.id)
+    .execute(&state.db)
+    .await;
+    remove_from_friend_lists(&state, &current_user.id, &blocked_id).await;
+    let _ = sqlx::query(
+        "DELETE FROM follows WHERE (follower_id = ? AND followee_id = ?) OR (follower_id = ? AND followee_id = ?)"
+    )
+    .bind(&current_user.id)
+    .bind(&blocked_id)
+    .bind(&blocked_id)
+    .bind(&current_user.id)
+    .execute(&state.db)
+    .await;
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "message": "User blocked"
+    }))
+}
+
+pub async fn unblock_user(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let blocked_id = path.into_inner();
+
+    let result = sqlx::query("DELETE FROM blocks WHERE blocker_id = ? AND blocked_id = ?")
+        .bind(&current_user.id)
+        .bind(&blocked_id)
+        .execute(&state.db)
+        .await;
+
+    match result {
+        Ok(r) => {
+            if r.rows_affected() > 0 {
+                HttpResponse::Ok().json(serde_json::json!({
+                    "message": "User unblocked"
+                }))
+            } else {
+                HttpResponse::NotFound().json(serde_json::json!({
+                    "error": "User is not blocked"
+                }))
+            }
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to unblock user: {}", e)
+        })),
+    }
+}
+
+// Helper functions
+
+/// Returns true if either user has blocked the other.
+pub async fn is_blocked(state: &web::Data<AppState>, user_a: &str, user_b: &str) -> bool {
+    sqlx::query_scalar::<_, i32>(
+        "SELECT COUNT(*) FROM blocks WHERE (blocker_id = ? AND blocked_id = ?) OR (blocker_id = ? AND blocked_id = ?)"
+    )
+    .bind(user_a)
+    .bind(user_b)
+    .bind(user_b)
+    .bind(user_a)
+    .fetch_one(&state.db)
+    .await
+    .unwrap_or(0) > 0
+}
@@ -0,0 +1,543 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::auth::require_auth;
+use crate::handlers::blocks::is_blocked;
+use crate::handlers::friends::{
+    check_prior_request, pending_requests_to, respond_to_request, PriorRequest, FOLLOW_REQUESTS,
+};
+use crate::models::{Follow, FollowCounts, FollowWithUser, User};
+use crate::notifications::{notify, Notification};
+use crate::AppState;
+
+pub async fn follow_user(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let followee_id = path.into_inner();
+
+    if current_user.id == followee_id {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Cannot follow yourself"
+        }));
+    }
+
+    let followee = match sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(&followee_id)
+        .fetch_optional(&state.db)
+        .await
+    {
+        Ok(Some(user)) => user,
+        Ok(None) => return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "User not found"
+        })),
+        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        })),
+    };
+
+    if is_blocked(&state, &current_user.id, &followee_id).await {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Cannot follow this user"
+        }));
+    }
+
+    // Follows are one-way, so unlike friend requests a follow request the other
+    // user sent us is a separate relationship and doesn't settle this one
+    match check_prior_request(&state, FOLLOW_REQUESTS, &current_user.id, &followee_id).await {
+        Ok(PriorRequest::Clear) => {}
+        Ok(PriorRequest::Open(existing)) => {
+            return HttpResponse::Conflict().json(serde_json::json!({
+                "error": "Already following this user",
+                "status": existing.status
+            }));
+        }
+        Ok(PriorRequest::CoolingDown(retry_after)) => {
+            return HttpResponse::TooManyRequests().json(serde_json::json!({
+                "error": "Follow request was rejected recently, try again later",
+                "retry_after": retry_after
+            }));
+        }
+        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        })),
+    }
+
+    // Private accounts go through the same pending -> accepted flow as friend requests
+    let status = if followee.requires_follow_approval { "pending" } else { "accepted" };
+    let follow_id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+
+    let result = sqlx::query(
+        "INSERT INTO follows (id, follower_id, followee_id, status, created_at) VALUES (?, ?, ?, ?, ?)"
+    )
+    .bind(&follow_id)
+    .bind(&current_user.id)
+    .bind(&followee_id)
+    .bind(status)
+    .bind(&now)
+    .execute(&state.db)
+    .await;
+
+    match result {
+        Ok(_) => {
+            notify(&state, &followee_id, &Notification {
+                kind: if status == "pending" { "follow_request" } else { "new_follower" },
+                actor_id: current_user.id.clone(),
+                post_id: None,
+                group_id: None,
+                is_anonymous: false,
+            }).await;
+
+            HttpResponse::Created().json(serde_json::json!({
+                "message": if status == "pending" { "Follow request sent" } else { "Now following user" },
+                "follow_id": follow_id,
+                "status": status
+            }))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to follow user: {}", e)
+        })),
+    }
+}
+
+pub async fn unfollow_user(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let followee_id = path.into_inner();
+
+    // Also withdraws a pending follow request, but leaves a rejection in place for its cooldown
+    let result = sqlx::query("DELETE FROM follows WHERE follower_id = ? AND followee_id = ? AND status IN ('pending', 'accepted')")
+        .bind(&current_user.id)
+        .bind(&followee_id)
+        .execute(&state.db)
+        .await;
+
+    match result {
+        Ok(r) => {
+            if r.rows_affected() > 0 {
+                HttpResponse::Ok().json(serde_json::json!({
+                    "message": "Unfollowed user"
+                }))
+            } else {
+                HttpResponse::NotFound().json(serde_json::json!({
+                    "error": "Not following this user"
+                }))
+            }
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to unfollow user: {}", e)
+        })),
+    }
+}
+
+pub async fn get_follow_requests(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    match pending_requests_to(&state, FOLLOW_REQUESTS, &current_user.id).await {
+        Ok(pending) => {
+            let follows = pending.into_iter().map(Follow::from).collect();
+            let requests = follows_with_users(&state, follows, |f| f.follower_id.clone()).await;
+            HttpResponse::Ok().json(requests)
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to get follow requests: {}", e)
+        })),
+    }
+}
+
+pub async fn accept_follow_request(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let follower_id = path.into_inner();
+
+    match respond_to_request(&state, FOLLOW_REQUESTS, &follower_id, &current_user.id, true).await {
+        Ok(Some(_)) => {
+            notify(&state, &follower_id, &Notification {
+                kind: "follow_accepted",
+                actor_id: current_user.id.clone(),
+                post_id: None,
+                group_id: None,
+                is_anonymous: false,
+            }).await;
+
+            HttpResponse::Ok().json(serde_json::json!({
+                "message": "Follow request accepted"
+            }))
+        }
+        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Follow request not found"
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to accept follow request: {}", e)
+        })),
+    }
+}
+
+pub async fn reject_follow_request(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let follower_id = path.into_inner();
+
+    // Kept as 'rejected' so the follower waits out the same cooldown as a rejected friend request
+    match respond_to_request(&state, FOLLOW_REQUESTS, &follower_id, &current_user.id, false).await {
+        Ok(Some(_)) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Follow request rejected"
+        })),
+        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Follow request not found"
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to reject follow request: {}", e)
+        })),
+    }
+}
+
+pub async fn get_followers(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let user_id = path.into_inner();
+
+    if let Err(response) = check_follow_lists_visible(&state, &user_id, &current_user.id).await {
+        return response;
+    }
+
+    let follows = sqlx::query_as::<_, Follow>(
+        r#"
+        SELECT * FROM follows
+        WHERE followee_id = ? AND status = 'accepted'
+          AND follower_id NOT IN (SELECT blocked_id FROM blocks WHERE blocker_id = ?)
+          AND follower_id NOT IN (SELECT blocker_id FROM blocks WHERE blocked_id = ?)
+        ORDER BY created_at DESC
+        "#
+    )
+    .bind(&user_id)
+    .bind(&current_user.id)
+    .bind(&current_user.id)
+    .fetch_all(&state.db)
+    .await;
+
+    match follows {
+        Ok(follows) => {
+            let followers = follows_with_users(&state, follows, |f| f.follower_id.clone()).await;
+            HttpResponse::Ok().json(followers)
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to get followers: {}", e)
+        })),
+    }
+}
+
+pub async fn get_following(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let user_id = path.into_inner();
+
+    if let Err(response) = check_follow_lists_visible(&state, &user_id, &current_user.id).await {
+        return response;
+    }
+
+    let follows = sqlx::query_as::<_, Follow>(
+        r#"
+        SELECT * FROM follows
+        WHERE follower_id = ? AND status = 'accepted'
+          AND followee_id NOT IN (SELECT blocked_id FROM blocks WHERE blocker_id = ?)
+          AND followee_id NOT IN (SELECT blocker_id FROM blocks WHERE blocked_id = ?)
+        ORDER BY created_at DESC
+        "#
+    )
+    .bind(&user_id)
+    .bind(&current_user.id)
+    .bind(&current_user.id)
+    .fetch_all(&state.db)
+    .await;
+
+    match follows {
+        Ok(follows) => {
+            let following = follows_with_users(&state, follows, |f| f.followee_id.clone()).await;
+            HttpResponse::Ok().json(following)
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to get following: {}", e)
+        })),
+    }
+}
+
+pub async fn get_follow_counts(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let user_id = path.into_inner();
+
+    HttpResponse::Ok().json(build_follow_counts(&state, &user_id, &current_user.id).await)
+}
+
+// Helper functions
+
+pub async fn build_follow_counts(state: &web::Data<AppState>, user_id: &str, viewer_id: &str) -> FollowCounts {
+    let followers_count: i32 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM follows WHERE followee_id = ? AND status = 'accepted'"
+    )
+    .bind(user_id)
+    .fetch_one(&state.db)
+    .await
+    .unwrap_or(0);
+
+    let following_count: i32 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM follows WHERE follower_id = ? AND status = 'accepted'"
+    )
+    .bind(user_id)
+    .fetch_one(&state.db)
+    .await
+    .unwrap_or(0);
+
+    let follow_status: Option<String> = sqlx::query_scalar(
+        "SELECT status FROM follows WHERE follower_id = ? AND followee_id = ? AND status IN ('pending', 'accepted')"
+    )
+    .bind(viewer_id)
+    .bind(user_id)
+    .fetch_optional(&state.db)
+    .await
+    .ok()
+    .flatten();
+
+    FollowCounts {
+        followers_count,
+        following_count,
+        follow_status,
+    }
+}
+
+/// Returns true if `follower_id` has an accepted follow of `followee_id`.
+pub async fn is_following(state: &web::Data<AppState>, follower_id: &str, followee_id: &str) -> bool {
+    sqlx::query_scalar::<_, i32>(
+        "SELECT COUNT(*) FROM follows WHERE follower_id = ? AND followee_id = ? AND status = 'accepted'"
+    )
+    .bind(follower_id)
+    .bind(followee_id)
+    .fetch_one(&state.db)
+    .await
+    .unwrap_or(0) > 0
+}
+
+/// Follower and following lists are hidden across a block, and a private
+/// account's lists are only shown to the account and its accepted followers.
+async fn check_follow_lists_visible(state: &web::Data<AppState>, user_id: &str, viewer_id: &str) -> Result<(), HttpResponse> {
+    if user_id == viewer_id {
+        return Ok(());
+    }
+
+    let user = match sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(&state.db)
+        .await
+    {
+        Ok(Some(user)) => user,
+        Ok(None) => return Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "User not found"
+        }))),
+        Err(e) => return Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        }))),
+    };
+
+    if is_blocked(state, viewer_id, user_id).await || (user.requires_follow_approval && !is_following(state, viewer_id, user_id).await) {
+        return Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "This user's follows are not visible to you"
+        })));
+    }
+
+    Ok(())
+}
+
+async fn follows_with_users(
+    state: &web::Data<AppState>,
+    follows: Vec<Follow>,
+    other_user_id: impl Fn(&Follow) -> String,
+) -> Vec<FollowWithUser> {
+    let mut result: Vec<FollowWithUser> = Vec::new();
+
+    for follow in follows {
+        if let Ok(Some(user)) = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+            .bind(other_user_id(&follow))
+            .fetch_optional(&state.db)
+            .await
+        {
+            result.push(FollowWithUser {
+                follow_id: follow.id,
+                user: user.into(),
+                status: follow.status,
+                created_at: follow.created_at,
+            });
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{auth_request, block, create_user, response_json, test_state};
+
+    async fn make_private(state: &web::Data<AppState>, user_id: &str) {
+        sqlx::query("UPDATE users SET requires_follow_approval = 1 WHERE id = ?")
+            .bind(user_id)
+            .execute(&state.db)
+            .await
+            .unwrap();
+    }
+
+    async fn follow(state: &web::Data<AppState>, follower_id: &str, followee_id: &str) -> HttpResponse {
+        follow_user(auth_request(follower_id), state.clone(), web::Path::from(followee_id.to_string())).await
+    }
+
+    async fn follower_ids(state: &web::Data<AppState>, viewer_id: &str, user_id: &str) -> Result<Vec<String>, u16> {
+        let response = get_followers(auth_request(viewer_id), state.clone(), web::Path::from(user_id.to_string())).await;
+        if !response.status().is_success() {
+            return Err(response.status().as_u16());
+        }
+        let followers = response_json(response).await;
+        Ok(followers.as_array().unwrap().iter().map(|f| f["user"]["id"].as_str().unwrap().to_string()).collect())
+    }
+
+    #[actix_rt::test]
+    async fn private_accounts_approve_followers() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+        make_private(&state, &alice).await;
+
+        let response = follow(&state, &bob, &alice).await;
+        assert_eq!(response.status().as_u16(), 201);
+        assert_eq!(response_json(response).await["status"], "pending");
+        assert!(!is_following(&state, &bob, &alice).await);
+        assert_eq!(follow(&state, &bob, &alice).await.status().as_u16(), 409);
+
+        let requests = response_json(get_follow_requests(auth_request(&alice), state.clone()).await).await;
+        assert_eq!(requests[0]["user"]["id"], bob.as_str());
+
+        let response = accept_follow_request(auth_request(&alice), state.clone(), web::Path::from(bob.clone())).await;
+        assert_eq!(response.status().as_u16(), 200);
+        assert!(is_following(&state, &bob, &alice).await);
+        let requests = response_json(get_follow_requests(auth_request(&alice), state.clone()).await).await;
+        assert!(requests.as_array().unwrap().is_empty());
+
+        // Public accounts are followed straight away
+        let response = follow(&state, &alice, &bob).await;
+        assert_eq!(response_json(response).await["status"], "accepted");
+    }
+
+    #[actix_rt::test]
+    async fn rejected_followers_wait_out_the_cooldown() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+        make_private(&state, &alice).await;
+
+        follow(&state, &bob, &alice).await;
+        let response = reject_follow_request(auth_request(&alice), state.clone(), web::Path::from(bob.clone())).await;
+        assert_eq!(response.status().as_u16(), 200);
+
+        // Unfollowing doesn't clear the rejection
+        let response = unfollow_user(auth_request(&bob), state.clone(), web::Path::from(alice.clone())).await;
+        assert_eq!(response.status().as_u16(), 404);
+        let response = follow(&state, &bob, &alice).await;
+        assert_eq!(response.status().as_u16(), 429);
+        assert!(response_json(response).await["retry_after"].as_i64().unwrap() > 0);
+        let counts = build_follow_counts(&state, &alice, &bob).await;
+        assert_eq!(counts.follow_status, None);
+
+        sqlx::query("UPDATE follows SET responded_at = ?")
+            .bind((Utc::now() - chrono::Duration::days(8)).to_rfc3339())
+            .execute(&state.db)
+            .await
+            .unwrap();
+        let response = follow(&state, &bob, &alice).await;
+        assert_eq!(response.status().as_u16(), 201);
+        assert_eq!(response_json(response).await["status"], "pending");
+    }
+
+    #[actix_rt::test]
+    async fn follow_lists_respect_private_accounts_and_blocks() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+        let carol = create_user(&state, "carol").await;
+        let dave = create_user(&state, "dave").await;
+        for follower in [&bob, &carol] {
+            follow(&state, follower, &alice).await;
+        }
+
+        assert_eq!(follower_ids(&state, &dave, &alice).await.unwrap().len(), 2);
+
+        // Followers blocked by the viewer are left out, and blocked viewers see nothing
+        block(&state, &dave, &bob).await;
+        assert_eq!(follower_ids(&state, &dave, &alice).await, Ok(vec![carol.clone()]));
+        block(&state, &alice, &dave).await;
+        assert_eq!(follower_ids(&state, &dave, &alice).await, Err(403));
+
+        // Private accounts only show their lists to themselves and accepted followers
+        make_private(&state, &alice).await;
+        assert_eq!(follower_ids(&state, &alice, &alice).await.unwrap().len(), 2);
+        assert_eq!(follower_ids(&state, &bob, &alice).await.unwrap().len(), 2);
+        let eve = create_user(&state, "eve").await;
+        assert_eq!(follower_ids(&state, &eve, &alice).await, Err(403));
+        let response = get_following(auth_request(&eve), state.clone(), web::Path::from(alice.clone())).await;
+        assert_eq!(response.status().as_u16(), 403);
+    }
+}
@@ -0,0 +1,421 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::auth::require_auth;
+use crate::handlers::posts::are_friends;
+use crate::models::{CreateFriendListRequest, FriendList, FriendListResponse, UpdateFriendListRequest, User};
+use crate::AppState;
+
+pub async fn get_friend_lists(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let lists = sqlx::query_as::<_, FriendList>(
+        "SELECT * FROM friend_lists WHERE owner_id = ? ORDER BY name"
+    )
+    .bind(&current_user.id)
+    .fetch_all(&state.db)
+    .await;
+
+    match lists {
+        Ok(lists) => {
+            let mut responses: Vec<FriendListResponse> = Vec::new();
+
+            for list in lists {
+                responses.push(build_friend_list_response(&state, list).await);
+            }
+
+            HttpResponse::Ok().json(responses)
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to get friend lists: {}", e)
+        })),
+    }
+}
+
+pub async fn create_friend_list(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<CreateFriendListRequest>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let name = body.name.trim().to_string();
+    if name.is_empty() {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "List name cannot be empty"
+        }));
+    }
+
+    if list_name_taken(&state, &current_user.id, &name).await {
+        return HttpResponse::Conflict().json(serde_json::json!({
+            "error": "You already have a list with this name"
+        }));
+    }
+
+    let member_ids = body.member_ids.clone().unwrap_or_default();
+    for member_id in &member_ids {
+        if !are_friends(&state, &current_user.id, member_id).await {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "Friend lists can only contain your friends"
+            }));
+        }
+    }
+
+    let list = FriendList {
+        id: Uuid::new_v4().to_string(),
+        owner_id: current_user.id.clone(),
+        name,
+        created_at: Utc::now().to_rfc3339(),
+    };
+
+    let result = sqlx::query(
+        "INSERT INTO friend_lists (id, owner_id, name, created_at) VALUES (?, ?, ?, ?)"
+    )
+    .bind(&list.id)
+    .bind(&list.owner_id)
+    .bind(&list.name)
+    .bind(&list.created_at)
+    .execute(&state.db)
+    .await;
+
+    if let Err(e) = result {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to create friend list: {}", e)
+        }));
+    }
+
+    for member_id in &member_ids {
+        let _ = sqlx::query(
+            "INSERT OR IGNORE INTO friend_list_members (list_id, user_id, added_at) VALUES (?, ?, ?)"
+        )
+        .bind(&list.id)
+        .bind(member_id)
+        .bind(&list.created_at)
+        .execute(&state.db)
+        .await;
+    }
+
+    HttpResponse::Created().json(build_friend_list_response(&state, list).await)
+}
+
+pub async fn get_friend_list(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    match fetch_own_list(&state, &path.into_inner(), &current_user.id).await {
+        Ok(list) => HttpResponse::Ok().json(build_friend_list_response(&state, list).await),
+        Err(response) => response,
+    }
+}
+
+pub async fn rename_friend_list(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<UpdateFriendListRequest>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let mut list = match fetch_own_list(&state, &path.into_inner(), &current_user.id).await {
+        Ok(list) => list,
+        Err(response) => return response,
+    };
+
+    let name = body.name.trim().to_string();
+    if name.is_empty() {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "List name cannot be empty"
+        }));
+    }
+
+    if name != list.name && list_name_taken(&state, &current_user.id, &name).await {
+        return HttpResponse::Conflict().json(serde_json::json!({
+            "error": "You already have a list with this name"
+        }));
+    }
+
+    let result = sqlx::query("UPDATE friend_lists SET name = ? WHERE id = ?")
+        .bind(&name)
+        .bind(&list.id)
+        .execute(&state.db)
+        .await;
+
+    match result {
+        Ok(_) => {
+            list.name = name;
+            HttpResponse::Ok().json(build_friend_list_response(&state, list).await)
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to rename friend list: {}", e)
+        })),
+    }
+}
+
+pub async fn delete_friend_list(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let list = match fetch_own_list(&state, &path.into_inner(), &current_user.id).await {
+        Ok(list) => list,
+        Err(response) => return response,
+    };
+
+    // Posts that targeted the list lose that part of their audience
+    let mut tx = match state.db.begin().await {
+        Ok(tx) => tx,
+        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        })),
+    };
+
+    let statements = [
+        "DELETE FROM post_audience WHERE kind = 'list' AND target_id = ?",
+        "DELETE FROM friend_list_members WHERE list_id = ?",
+        "DELETE FROM friend_lists WHERE id = ?",
+    ];
+
+    for statement in statements {
+        if let Err(e) = sqlx::query(statement).bind(&list.id).execute(&mut *tx).await {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Failed to delete friend list: {}", e)
+            }));
+        }
+    }
+
+    match tx.commit().await {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Friend list deleted"
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to delete friend list: {}", e)
+        })),
+    }
+}
+
+pub async fn add_list_member(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let (list_id, user_id) = path.into_inner();
+
+    let list = match fetch_own_list(&state, &list_id, &current_user.id).await {
+        Ok(list) => list,
+        Err(response) => return response,
+    };
+
+    if !are_friends(&state, &current_user.id, &user_id).await {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Friend lists can only contain your friends"
+        }));
+    }
+
+    let result = sqlx::query(
+        "INSERT OR IGNORE INTO friend_list_members (list_id, user_id, added_at) VALUES (?, ?, ?)"
+    )
+    .bind(&list.id)
+    .bind(&user_id)
+    .bind(Utc::now().to_rfc3339())
+    .execute(&state.db)
+    .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(build_friend_list_response(&state, list).await),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to add to friend list: {}", e)
+        })),
+    }
+}
+
+pub async fn remove_list_member(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let (list_id, user_id) = path.into_inner();
+
+    let list = match fetch_own_list(&state, &list_id, &current_user.id).await {
+        Ok(list) => list,
+        Err(response) => return response,
+    };
+
+    let result = sqlx::query("DELETE FROM friend_list_members WHERE list_id = ? AND user_id = ?")
+        .bind(&list.id)
+        .bind(&user_id)
+        .execute(&state.db)
+        .await;
+
+    match result {
+        Ok(r) if r.rows_affected() > 0 => HttpResponse::Ok().json(build_friend_list_response(&state, list).await),
+        Ok(_) => HttpResponse::NotFound().json(serde_json::json!({
+            "error": "User is not on this list"
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to remove from friend list: {}", e)
+        })),
+    }
+}
+
+// Helper functions
+
+/// Takes each user off the other's friend lists, for when they stop being friends.
+pub async fn remove_from_friend_lists(state: &web::Data<AppState>, user_a: &str, user_b: &str) {
+    for (owner_id, member_id) in [(user_a, user_b), (user_b, user_a)] {
+        let _ = sqlx::query(
+            "DELETE FROM friend_list_members WHERE user_id = ? AND list_id IN (SELECT id FROM friend_lists WHERE owner_id = ?)"
+        )
+        .bind(member_id)
+        .bind(owner_id)
+        .execute(&state.db)
+        .await;
+    }
+}
+
+async fn list_name_taken(state: &web::Data<AppState>, owner_id: &str, name: &str) -> bool {
+    sqlx::query_scalar::<_, i32>("SELECT COUNT(*) FROM friend_lists WHERE owner_id = ? AND name = ?")
+        .bind(owner_id)
+        .bind(name)
+        .fetch_one(&state.db)
+        .await
+        .unwrap_or(0) > 0
+}
+
+async fn fetch_own_list(state: &web::Data<AppState>, list_id: &str, owner_id: &str) -> Result<FriendList, HttpResponse> {
+    match sqlx::query_as::<_, FriendList>("SELECT * FROM friend_lists WHERE id = ? AND owner_id = ?")
+        .bind(list_id)
+        .bind(owner_id)
+        .fetch_optional(&state.db)
+        .await
+    {
+        Ok(Some(list)) => Ok(list),
+        Ok(None) => Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Friend list not found"
+        }))),
+        Err(e) => Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        }))),
+    }
+}
+
+async fn build_friend_list_response(state: &web::Data<AppState>, list: FriendList) -> FriendListResponse {
+    let members = sqlx::query_as::<_, User>(
+        r#"
+        SELECT u.* FROM users u
+        INNER JOIN friend_list_members m ON m.user_id = u.id
+        WHERE m.list_id = ?
+        ORDER BY u.display_name
+        "#
+    )
+    .bind(&list.id)
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+
+    FriendListResponse {
+        id: list.id,
+        name: list.name,
+        members: members.into_iter().map(|u| u.into()).collect(),
+        created_at: list.created_at,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::friends::remove_friend;
+    use crate::handlers::posts::{create_post, get_post};
+    use crate::test_support::{auth_request, befriend, create_user, json, response_json, test_state};
+
+    async fn can_see(state: &web::Data<AppState>, viewer: &str, post_id: &str) -> bool {
+        get_post(auth_request(viewer), state.clone(), web::Path::from(post_id.to_string())).await.status().is_success()
+    }
+
+    #[actix_rt::test]
+    async fn custom_audiences_follow_lists_and_exclusions() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+        let carol = create_user(&state, "carol").await;
+        let dave = create_user(&state, "dave").await;
+        let erin = create_user(&state, "erin").await;
+        for friend in [&bob, &carol, &dave] {
+            befriend(&state, &alice, friend).await;
+        }
+
+        let create_list = |body: serde_json::Value| create_friend_list(auth_request(&alice), state.clone(), json(body));
+        assert_eq!(create_list(serde_json::json!({"name": "Close", "member_ids": [erin]})).await.status().as_u16(), 400);
+        let response = create_list(serde_json::json!({"name": "Close", "member_ids": [bob]})).await;
+        assert_eq!(response.status().as_u16(), 201);
+        let list_id = response_json(response).await["id"].as_str().unwrap().to_string();
+        assert_eq!(create_list(serde_json::json!({"name": "Close"})).await.status().as_u16(), 409);
+
+        let post = |body: serde_json::Value| {
+            let state = state.clone();
+            let alice = alice.clone();
+            async move {
+                let response = create_post(auth_request(&alice), state, json(body)).await;
+                assert_eq!(response.status().as_u16(), 201);
+                response_json(response).await["id"].as_str().unwrap().to_string()
+            }
+        };
+        let custom = post(serde_json::json!({
+            "content": "Just for some",
+            "visibility": "custom",
+            "audience": {"list_ids": [list_id], "user_ids": [carol]}
+        }))
+        .await;
+        let all_but_dave = post(serde_json::json!({
+            "content": "Not for dave",
+            "visibility": "friends_only",
+            "audience": {"except_user_ids": [dave]}
+        }))
+        .await;
+
+        assert!(can_see(&state, &bob, &custom).await);
+        assert!(can_see(&state, &carol, &custom).await);
+        assert!(!can_see(&state, &dave, &custom).await);
+        assert!(can_see(&state, &carol, &all_but_dave).await);
+        assert!(!can_see(&state, &dave, &all_but_dave).await);
+
+        // Unfriending takes someone off the lists they were on
+        remove_friend(auth_request(&alice), state.clone(), web::Path::from(bob.clone())).await;
+        assert!(!can_see(&state, &bob, &custom).await);
+        let response = get_friend_list(auth_request(&alice), state.clone(), web::Path::from(list_id)).await;
+        assert!(response_json(response).await["members"].as_array().unwrap().is_empty());
+    }
+}
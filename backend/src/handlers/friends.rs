@@ -1,11 +1,25 @@
 use actix_web::{web, HttpRequest, HttpResponse};
-use chrono::Utc;
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
 use uuid::Uuid;
 
 use crate::auth::require_auth;
-use crate::models::{Friendship, FriendWithUser, User};
+use crate::handlers::blocks::is_blocked;
+use crate::handlers::friend_lists::remove_from_friend_lists;
+use crate::handlers::users::{audience_allows, user_response_for};
+use crate::models::{FriendSuggestion, FriendSuggestionResponse, Friendship, FriendWithUser, User, UserRequest, UserResponse};
+use crate::notifications::{notify, Notification};
+use crate::suggestions::{refresh_for_user, SUGGESTIONS_TTL_MINUTES};
 use crate::AppState;
 
+/// How long a rejected sender has to wait before sending another friend or follow request.
+const REQUEST_COOLDOWN_DAYS: i64 = 7;
+
+#[derive(Deserialize)]
+pub struct SuggestionsQuery {
+    pub limit: Option<i64>,
+}
+
 pub async fn get_friends(
     req: HttpRequest,
     state: web::Data<AppState>,
@@ -45,7 +59,7 @@ pub async fn get_friends(
                 {
                     friends.push(FriendWithUser {
                         friendship_id: friendship.id,
-                        user: friend.into(),
+                        user: user_response_for(&state, friend, &current_user.id).await,
                         status: friendship.status,
                         created_at: friendship.created_at,
                     });
@@ -60,38 +74,93 @@ pub async fn get_friends(
     }
 }
 
-pub async fn get_friend_requests(
+pub async fn get_user_friends(
     req: HttpRequest,
     state: web::Data<AppState>,
+    path: web::Path<String>,
 ) -> HttpResponse {
     let current_user = match require_auth(&req, &state).await {
         Ok(user) => user,
         Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
     };
 
-    // Get pending friendships where current user is the friend (receiving the request)
-    let friendships = sqlx::query_as::<_, Friendship>(
-        "SELECT * FROM friendships WHERE friend_id = ? AND status = 'pending'"
+    let user_id = path.into_inner();
+
+    let user = match sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(&user_id)
+        .fetch_optional(&state.db)
+        .await
+    {
+        Ok(Some(user)) => user,
+        Ok(None) => return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "User not found"
+        })),
+        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        })),
+    };
+
+    if is_blocked(&state, &current_user.id, &user.id).await
+        || !audience_allows(&state, &user.friends_list_visibility, &user.id, &current_user.id).await
+    {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "This user's friend list is not visible to you"
+        }));
+    }
+
+    let friends = sqlx::query_as::<_, User>(
+        r#"
+        SELECT * FROM users
+        WHERE id IN (SELECT friend_id FROM friend_edges WHERE user_id = ?)
+          AND id NOT IN (SELECT blocked_id FROM blocks WHERE blocker_id = ?)
+          AND id NOT IN (SELECT blocker_id FROM blocks WHERE blocked_id = ?)
+        ORDER BY display_name
+        "#
     )
+    .bind(&user.id)
+    .bind(&current_user.id)
     .bind(&current_user.id)
     .fetch_all(&state.db)
     .await;
 
-    match friendships {
-        Ok(friendships) => {
+    match friends {
+        Ok(friends) => {
+            let mut responses: Vec<UserResponse> = Vec::new();
+            for friend in friends {
+                responses.push(user_response_for(&state, friend, &current_user.id).await);
+            }
+            HttpResponse::Ok().json(responses)
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to get friends: {}", e)
+        })),
+    }
+}
+
+pub async fn get_friend_requests(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    match pending_requests_to(&state, FRIEND_REQUESTS, &current_user.id).await {
+        Ok(pending) => {
             let mut requests: Vec<FriendWithUser> = Vec::new();
             
-            for friendship in friendships {
+            for request in pending {
                 if let Ok(Some(user)) = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
-                    .bind(&friendship.user_id)
+                    .bind(&request.sender_id)
                     .fetch_optional(&state.db)
                     .await
                 {
                     requests.push(FriendWithUser {
-                        friendship_id: friendship.id,
-                        user: user.into(),
-                        status: friendship.status,
-                        created_at: friendship.created_at,
+                        friendship_id: request.id,
+                        user: user_response_for(&state, user, &current_user.id).await,
+                        status: request.status,
+                        created_at: request.created_at,
                     });
                 }
             }
@@ -123,32 +192,74 @@ pub async fn send_friend_request(
     }
 
     // Check if friend exists
-    let friend_exists = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+    let friend = match sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
         .bind(&friend_id)
         .fetch_optional(&state.db)
-        .await;
-
-    if let Ok(None) = friend_exists {
-        return HttpResponse::NotFound().json(serde_json::json!({
+        .await
+    {
+        Ok(Some(friend)) => friend,
+        Ok(None) => return HttpResponse::NotFound().json(serde_json::json!({
             "error": "User not found"
+        })),
+        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        })),
+    };
+
+    if is_blocked(&state, &current_user.id, &friend_id).await {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Cannot send friend request to this user"
         }));
     }
 
-    // Check if friendship already exists
-    let existing = sqlx::query_as::<_, Friendship>(
-        "SELECT * FROM friendships WHERE (user_id = ? AND friend_id = ?) OR (user_id = ? AND friend_id = ?)"
-    )
-    .bind(&current_user.id)
-    .bind(&friend_id)
-    .bind(&friend_id)
-    .bind(&current_user.id)
-    .fetch_optional(&state.db)
-    .await;
+    // A request the other user already sent us is settled first
+    match find_request(&state, FRIEND_REQUESTS, &friend_id, &current_user.id).await {
+        Ok(Some(incoming)) if incoming.status == "pending" => {
+            // They already asked us, so this request accepts theirs
+            return accept_pending_request(&state, &friend_id, &current_user.id).await;
+        }
+        Ok(Some(incoming)) if incoming.status == "rejected" => {
+            // We rejected them earlier and changed our mind
+            let _ = sqlx::query("DELETE FROM friendships WHERE id = ?")
+                .bind(&incoming.id)
+                .execute(&state.db)
+                .await;
+        }
+        Ok(Some(incoming)) => {
+            return HttpResponse::Conflict().json(serde_json::json!({
+                "error": "Friendship already exists",
+                "status": incoming.status
+            }));
+        }
+        Ok(None) => {}
+        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        })),
+    }
 
-    if let Ok(Some(friendship)) = existing {
-        return HttpResponse::Conflict().json(serde_json::json!({
-            "error": "Friendship already exists",
-            "status": friendship.status
+    match check_prior_request(&state, FRIEND_REQUESTS, &current_user.id, &friend_id).await {
+        Ok(PriorRequest::Clear) => {}
+        Ok(PriorRequest::Open(existing)) => {
+            return HttpResponse::Conflict().json(serde_json::json!({
+                "error": "Friendship already exists",
+                "status": existing.status
+            }));
+        }
+        Ok(PriorRequest::CoolingDown(retry_after)) => {
+            return HttpResponse::TooManyRequests().json(serde_json::json!({
+                "error": "Friend request was rejected recently, try again later",
+                "retry_after": retry_after
+            }));
+        }
+        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        })),
+    }
+
+    // Checked after the reverse-request case so an incoming request can always be accepted
+    if !audience_allows(&state, &friend.friend_requests_from, &friend.id, &current_user.id).await {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "This user is not accepting friend requests from you"
         }));
     }
 
@@ -166,10 +277,20 @@ pub async fn send_friend_request(
     .await;
 
     match result {
-        Ok(_) => HttpResponse::Created().json(serde_json::json!({
-            "message": "Friend request sent",
-            "friendship_id": friendship_id
-        })),
+        Ok(_) => {
+            notify(&state, &friend_id, &Notification {
+                kind: "friend_request",
+                actor_id: current_user.id.clone(),
+                post_id: None,
+                group_id: None,
+                is_anonymous: false,
+            }).await;
+
+            HttpResponse::Created().json(serde_json::json!({
+                "message": "Friend request sent",
+                "friendship_id": friendship_id
+            }))
+        }
         Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
             "error": format!("Failed to send friend request: {}", e)
         })),
@@ -188,41 +309,80 @@ pub async fn accept_friend_request(
 
     let user_id = path.into_inner();
 
-    // Find the pending friend request
-    let friendship = sqlx::query_as::<_, Friendship>(
-        "SELECT * FROM friendships WHERE user_id = ? AND friend_id = ? AND status = 'pending'"
+    accept_pending_request(&state, &user_id, &current_user.id).await
+}
+
+pub async fn reject_friend_request(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let user_id = path.into_inner();
+
+    // The row is kept as 'rejected' so the sender has to wait out the cooldown before asking again
+    match respond_to_request(&state, FRIEND_REQUESTS, &user_id, &current_user.id, false).await {
+        Ok(Some(_)) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Friend request rejected"
+        })),
+        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Friend request not found"
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to reject friend request: {}", e)
+        })),
+    }
+}
+
+pub async fn get_sent_friend_requests(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    // Get pending friendships where current user is the sender
+    let friendships = sqlx::query_as::<_, Friendship>(
+        "SELECT * FROM friendships WHERE user_id = ? AND status = 'pending' ORDER BY created_at DESC"
     )
-    .bind(&user_id)
     .bind(&current_user.id)
-    .fetch_optional(&state.db)
+    .fetch_all(&state.db)
     .await;
 
-    match friendship {
-        Ok(Some(friendship)) => {
-            let result = sqlx::query("UPDATE friendships SET status = 'accepted' WHERE id = ?")
-                .bind(&friendship.id)
-                .execute(&state.db)
-                .await;
+    match friendships {
+        Ok(friendships) => {
+            let mut requests: Vec<FriendWithUser> = Vec::new();
 
-            match result {
-                Ok(_) => HttpResponse::Ok().json(serde_json::json!({
-                    "message": "Friend request accepted"
-                })),
-                Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
-                    "error": format!("Failed to accept friend request: {}", e)
-                })),
+            for friendship in friendships {
+                if let Ok(Some(user)) = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+                    .bind(&friendship.friend_id)
+                    .fetch_optional(&state.db)
+                    .await
+                {
+                    requests.push(FriendWithUser {
+                        friendship_id: friendship.id,
+                        user: user_response_for(&state, user, &current_user.id).await,
+                        status: friendship.status,
+                        created_at: friendship.created_at,
+                    });
+                }
             }
+
+            HttpResponse::Ok().json(requests)
         }
-        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Friend request not found"
-        })),
         Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": format!("Database error: {}", e)
+            "error": format!("Failed to get sent friend requests: {}", e)
         })),
     }
 }
 
-pub async fn reject_friend_request(
+pub async fn cancel_friend_request(
     req: HttpRequest,
     state: web::Data<AppState>,
     path: web::Path<String>,
@@ -232,13 +392,13 @@ pub async fn reject_friend_request(
         Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
     };
 
-    let user_id = path.into_inner();
+    let friend_id = path.into_inner();
 
     let result = sqlx::query(
         "DELETE FROM friendships WHERE user_id = ? AND friend_id = ? AND status = 'pending'"
     )
-    .bind(&user_id)
     .bind(&current_user.id)
+    .bind(&friend_id)
     .execute(&state.db)
     .await;
 
@@ -246,7 +406,7 @@ pub async fn reject_friend_request(
         Ok(r) => {
             if r.rows_affected() > 0 {
                 HttpResponse::Ok().json(serde_json::json!({
-                    "message": "Friend request rejected"
+                    "message": "Friend request cancelled"
                 }))
             } else {
                 HttpResponse::NotFound().json(serde_json::json!({
@@ -255,7 +415,447 @@ pub async fn reject_friend_request(
             }
         }
         Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": format!("Failed to reject friend request: {}", e)
+            "error": format!("Failed to cancel friend request: {}", e)
         })),
     }
 }
+
+pub async fn remove_friend(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let friend_id = path.into_inner();
+
+    let result = sqlx::query(
+        "DELETE FROM friendships WHERE ((user_id = ? AND friend_id = ?) OR (user_id = ? AND friend_id = ?)) AND status = 'accepted'"
+    )
+    .bind(&current_user.id)
+    .bind(&friend_id)
+    .bind(&friend_id)
+    .bind(&current_user.id)
+    .execute(&state.db)
+    .await;
+
+    match result {
+        Ok(r) => {
+            if r.rows_affected() > 0 {
+                remove_from_friend_lists(&state, &current_user.id, &friend_id).await;
+
+                HttpResponse::Ok().json(serde_json::json!({
+                    "message": "Friend removed"
+                }))
+            } else {
+                HttpResponse::NotFound().json(serde_json::json!({
+                    "error": "Not friends with this user"
+                }))
+            }
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to remove friend: {}", e)
+        })),
+    }
+}
+
+pub async fn get_friend_suggestions(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<SuggestionsQuery>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let limit = query.limit.unwrap_or(20).clamp(1, 50);
+
+    // Serve from the precomputed table unless it is missing or stale
+    let computed_at: Option<String> = sqlx::query_scalar(
+        "SELECT MAX(computed_at) FROM friend_suggestions WHERE user_id = ?"
+    )
+    .bind(&current_user.id)
+    .fetch_one(&state.db)
+    .await
+    .unwrap_or(None);
+
+    let stale_before = (Utc::now() - Duration::minutes(SUGGESTIONS_TTL_MINUTES)).to_rfc3339();
+    if computed_at.is_none_or(|t| t < stale_before) {
+        if let Err(e) = refresh_for_user(&state.db, &current_user.id).await {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Failed to compute friend suggestions: {}", e)
+            }));
+        }
+    }
+
+    // Friendships, pending requests and blocks may have changed since the cache was built
+    let suggestions = sqlx::query_as::<_, FriendSuggestion>(
+        r#"
+        SELECT * FROM friend_suggestions
+        WHERE user_id = ?
+          AND suggested_id NOT IN (SELECT friend_id FROM friendships WHERE user_id = ?)
+          AND suggested_id NOT IN (SELECT user_id FROM friendships WHERE friend_id = ?)
+          AND suggested_id NOT IN (SELECT blocked_id FROM blocks WHERE blocker_id = ?)
+          AND suggested_id NOT IN (SELECT blocker_id FROM blocks WHERE blocked_id = ?)
+          AND suggested_id NOT IN (SELECT id FROM users WHERE friend_requests_from = 'nobody')
+        ORDER BY score DESC, mutual_friends_count DESC
+        LIMIT ?
+        "#
+    )
+    .bind(&current_user.id)
+    .bind(&current_user.id)
+    .bind(&current_user.id)
+    .bind(&current_user.id)
+    .bind(&current_user.id)
+    .bind(limit)
+    .fetch_all(&state.db)
+    .await;
+
+    match suggestions {
+        Ok(suggestions) => {
+            let mut responses: Vec<FriendSuggestionResponse> = Vec::new();
+
+            for suggestion in suggestions {
+                let user = match sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+                    .bind(&suggestion.suggested_id)
+                    .fetch_optional(&state.db)
+                    .await
+                {
+                    Ok(Some(user)) => user,
+                    _ => continue,
+                };
+
+                // Mutual friends are part of the suggested user's friend list
+                let friends_visible = audience_allows(&state, &user.friends_list_visibility, &user.id, &current_user.id).await;
+
+                let mutual_friends = if friends_visible {
+                    sqlx::query_as::<_, User>(
+                        r#"
+                        SELECT * FROM users
+                        WHERE id IN (SELECT friend_id FROM friend_edges WHERE user_id = ?)
+                          AND id IN (SELECT friend_id FROM friend_edges WHERE user_id = ?)
+                        ORDER BY display_name
+                        LIMIT 3
+                        "#
+                    )
+                    .bind(&current_user.id)
+                    .bind(&suggestion.suggested_id)
+                    .fetch_all(&state.db)
+                    .await
+                    .unwrap_or_default()
+                } else {
+                    Vec::new()
+                };
+
+                responses.push(FriendSuggestionResponse {
+                    user: user.into(),
+                    mutual_friends_count: if friends_visible { suggestion.mutual_friends_count } else { 0 },
+                    mutual_friends: mutual_friends.into_iter().map(UserResponse::from).collect(),
+                    shared_groups_count: suggestion.shared_groups_count,
+                    score: suggestion.score,
+                });
+            }
+
+            HttpResponse::Ok().json(responses)
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to get friend suggestions: {}", e)
+        })),
+    }
+}
+
+// Helper functions
+
+/// Accepts the pending request `requester_id` sent to the current user.
+async fn accept_pending_request(
+    state: &web::Data<AppState>,
+    requester_id: &str,
+    current_user_id: &str,
+) -> HttpResponse {
+    match respond_to_request(state, FRIEND_REQUESTS, requester_id, current_user_id, true).await {
+        Ok(Some(friendship_id)) => {
+            notify(state, requester_id, &Notification {
+                kind: "friend_accepted",
+                actor_id: current_user_id.to_string(),
+                post_id: None,
+                group_id: None,
+                is_anonymous: false,
+            }).await;
+
+            HttpResponse::Ok().json(serde_json::json!({
+                "message": "Friend request accepted",
+                "friendship_id": friendship_id
+            }))
+        }
+        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Friend request not found"
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to accept friend request: {}", e)
+        })),
+    }
+}
+
+/// A table of requests between two users that share one state machine: a
+/// request starts out pending and is either accepted or rejected, and a
+/// rejected sender has to wait out a cooldown before asking again.
+#[derive(Clone, Copy)]
+pub struct RequestKind {
+    table: &'static str,
+    sender_column: &'static str,
+    recipient_column: &'static str,
+}
+
+pub const FRIEND_REQUESTS: RequestKind = RequestKind {
+    table: "friendships",
+    sender_column: "user_id",
+    recipient_column: "friend_id",
+};
+
+pub const FOLLOW_REQUESTS: RequestKind = RequestKind {
+    table: "follows",
+    sender_column: "follower_id",
+    recipient_column: "followee_id",
+};
+
+impl RequestKind {
+    fn select(&self) -> String {
+        format!(
+            "SELECT id, {} AS sender_id, {} AS recipient_id, status, created_at, responded_at FROM {}",
+            self.sender_column, self.recipient_column, self.table
+        )
+    }
+}
+
+/// Where an earlier request from the same sender stands when they ask again.
+pub enum PriorRequest {
+    /// Nothing in the way; a rejection whose cooldown has passed is cleared
+    Clear,
+    /// Still pending or already accepted
+    Open(UserRequest),
+    /// Rejected recently, with the seconds left until the sender may ask again
+    CoolingDown(i64),
+}
+
+pub async fn find_request(
+    state: &web::Data<AppState>,
+    kind: RequestKind,
+    sender_id: &str,
+    recipient_id: &str,
+) -> Result<Option<UserRequest>, sqlx::Error> {
+    sqlx::query_as::<_, UserRequest>(&format!(
+        "{} WHERE {} = ? AND {} = ?",
+        kind.select(), kind.sender_column, kind.recipient_column
+    ))
+    .bind(sender_id)
+    .bind(recipient_id)
+    .fetch_optional(&state.db)
+    .await
+}
+
+pub async fn check_prior_request(
+    state: &web::Data<AppState>,
+    kind: RequestKind,
+    sender_id: &str,
+    recipient_id: &str,
+) -> Result<PriorRequest, sqlx::Error> {
+    let existing = match find_request(state, kind, sender_id, recipient_id).await? {
+        Some(existing) => existing,
+        None => return Ok(PriorRequest::Clear),
+    };
+
+    if existing.status != "rejected" {
+        return Ok(PriorRequest::Open(existing));
+    }
+
+    if let Some(retry_after) = cooldown_remaining(&existing) {
+        return Ok(PriorRequest::CoolingDown(retry_after));
+    }
+
+    sqlx::query(&format!("DELETE FROM {} WHERE id = ?", kind.table))
+        .bind(&existing.id)
+        .execute(&state.db)
+        .await?;
+
+    Ok(PriorRequest::Clear)
+}
+
+/// Pending requests sent to the user, newest first.
+pub async fn pending_requests_to(
+    state: &web::Data<AppState>,
+    kind: RequestKind,
+    recipient_id: &str,
+) -> Result<Vec<UserRequest>, sqlx::Error> {
+    sqlx::query_as::<_, UserRequest>(&format!(
+        "{} WHERE {} = ? AND status = 'pending' ORDER BY created_at DESC",
+        kind.select(), kind.recipient_column
+    ))
+    .bind(recipient_id)
+    .fetch_all(&state.db)
+    .await
+}
+
+/// Accepts or rejects a pending request, returning its id, or None if there
+/// was no pending request to answer.
+pub async fn respond_to_request(
+    state: &web::Data<AppState>,
+    kind: RequestKind,
+    sender_id: &str,
+    recipient_id: &str,
+    accept: bool,
+) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar(&format!(
+        "UPDATE {} SET status = ?, responded_at = ? WHERE {} = ? AND {} = ? AND status = 'pending' RETURNING id",
+        kind.table, kind.sender_column, kind.recipient_column
+    ))
+    .bind(if accept { "accepted" } else { "rejected" })
+    .bind(Utc::now().to_rfc3339())
+    .bind(sender_id)
+    .bind(recipient_id)
+    .fetch_optional(&state.db)
+    .await
+}
+
+/// Seconds until a rejected sender may ask again, or None once the cooldown is over.
+fn cooldown_remaining(request: &UserRequest) -> Option<i64> {
+    let responded_at = request
+        .responded_at
+        .as_deref()
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())?;
+
+    let retry_at = responded_at.with_timezone(&Utc) + Duration::days(REQUEST_COOLDOWN_DAYS);
+    let remaining = (retry_at - Utc::now()).num_seconds();
+
+    (remaining > 0).then_some(remaining)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::posts::get_friend_ids;
+    use crate::test_support::{auth_request, befriend, block, create_user, response_json, test_state};
+
+    async fn request(state: &web::Data<AppState>, sender_id: &str, recipient_id: &str) -> HttpResponse {
+        send_friend_request(auth_request(sender_id), state.clone(), web::Path::from(recipient_id.to_string())).await
+    }
+
+    #[actix_rt::test]
+    async fn asking_someone_who_already_asked_accepts_their_request() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+
+        assert_eq!(request(&state, &alice, &bob).await.status().as_u16(), 201);
+        assert_eq!(request(&state, &alice, &bob).await.status().as_u16(), 409);
+
+        let response = request(&state, &bob, &alice).await;
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(response_json(response).await["message"], "Friend request accepted");
+        assert_eq!(get_friend_ids(&state, &alice).await, vec![bob.clone()]);
+        assert_eq!(request(&state, &bob, &alice).await.status().as_u16(), 409);
+    }
+
+    #[actix_rt::test]
+    async fn rejected_senders_wait_out_the_cooldown() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+
+        request(&state, &alice, &bob).await;
+        let requests = response_json(get_friend_requests(auth_request(&bob), state.clone()).await).await;
+        assert_eq!(requests[0]["user"]["id"], alice.as_str());
+        let response = reject_friend_request(auth_request(&bob), state.clone(), web::Path::from(alice.clone())).await;
+        assert_eq!(response.status().as_u16(), 200);
+
+        assert_eq!(request(&state, &alice, &bob).await.status().as_u16(), 429);
+
+        sqlx::query("UPDATE friendships SET responded_at = ?")
+            .bind((Utc::now() - Duration::days(REQUEST_COOLDOWN_DAYS + 1)).to_rfc3339())
+            .execute(&state.db)
+            .await
+            .unwrap();
+        assert_eq!(request(&state, &alice, &bob).await.status().as_u16(), 201);
+
+        // The recipient can change their mind at any time
+        reject_friend_request(auth_request(&bob), state.clone(), web::Path::from(alice.clone())).await;
+        assert_eq!(request(&state, &bob, &alice).await.status().as_u16(), 201);
+    }
+
+    #[actix_rt::test]
+    async fn senders_can_cancel_requests_and_either_side_can_unfriend() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+
+        request(&state, &alice, &bob).await;
+        let sent = response_json(get_sent_friend_requests(auth_request(&alice), state.clone()).await).await;
+        assert_eq!(sent[0]["user"]["id"], bob.as_str());
+
+        // Only the sender can cancel, and only while the request is pending
+        let cancel = |user: &str, other: &str| cancel_friend_request(auth_request(user), state.clone(), web::Path::from(other.to_string()));
+        assert_eq!(cancel(&bob, &alice).await.status().as_u16(), 404);
+        assert_eq!(cancel(&alice, &bob).await.status().as_u16(), 200);
+        assert!(response_json(get_friend_requests(auth_request(&bob), state.clone()).await).await.as_array().unwrap().is_empty());
+
+        request(&state, &alice, &bob).await;
+        accept_friend_request(auth_request(&bob), state.clone(), web::Path::from(alice.clone())).await;
+        assert_eq!(cancel(&alice, &bob).await.status().as_u16(), 404);
+
+        let unfriend = |user: &str, other: &str| remove_friend(auth_request(user), state.clone(), web::Path::from(other.to_string()));
+        assert_eq!(unfriend(&bob, &alice).await.status().as_u16(), 200);
+        assert!(get_friend_ids(&state, &alice).await.is_empty());
+        assert_eq!(unfriend(&alice, &bob).await.status().as_u16(), 404);
+
+        // Unfriending isn't a rejection, so there's no cooldown
+        assert_eq!(request(&state, &alice, &bob).await.status().as_u16(), 201);
+    }
+
+    async fn suggested_ids(state: &web::Data<AppState>, user_id: &str) -> Vec<String> {
+        let response = get_friend_suggestions(auth_request(user_id), state.clone(), web::Query::from_query("").unwrap()).await;
+        assert_eq!(response.status().as_u16(), 200);
+        response_json(response)
+            .await
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["user"]["id"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[actix_rt::test]
+    async fn suggestions_are_cached_until_stale_but_filtered_on_every_read() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+        let carol = create_user(&state, "carol").await;
+        let dave = create_user(&state, "dave").await;
+        befriend(&state, &alice, &bob).await;
+        befriend(&state, &bob, &carol).await;
+
+        assert_eq!(suggested_ids(&state, &alice).await, vec![carol.clone()]);
+
+        // A new friend of a friend waits for the cache to go stale
+        befriend(&state, &bob, &dave).await;
+        assert_eq!(suggested_ids(&state, &alice).await, vec![carol.clone()]);
+
+        sqlx::query("UPDATE friend_suggestions SET computed_at = ?")
+            .bind((Utc::now() - Duration::minutes(SUGGESTIONS_TTL_MINUTES + 1)).to_rfc3339())
+            .execute(&state.db)
+            .await
+            .unwrap();
+        let mut ids = suggested_ids(&state, &alice).await;
+        ids.sort();
+        let mut expected = vec![carol.clone(), dave.clone()];
+        expected.sort();
+        assert_eq!(ids, expected);
+
+        // Blocks and requests apply straight away
+        block(&state, &dave, &alice).await;
+        assert_eq!(request(&state, &alice, &carol).await.status().as_u16(), 201);
+        assert!(suggested_ids(&state, &alice).await.is_empty());
+    }
+}
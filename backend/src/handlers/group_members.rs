@@ -0,0 +1,538 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::auth::require_auth;
+use crate::handlers::groups::get_member_role;
+use crate::models::{
+    BanMemberRequest, Group, GroupBan, GroupBanWithUser, GroupMember, GroupMemberWithUser,
+    UpdateMemberRoleRequest, User, UserResponse,
+};
+use crate::policy::{group_allows, load_membership, outranks, GroupAction, Membership};
+use crate::AppState;
+
+pub async fn get_members(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let group_id = path.into_inner();
+
+    let group = match fetch_group(&state, &group_id).await {
+        Ok(group) => group,
+        Err(response) => return response,
+    };
+
+    if !group_allows(GroupAction::ViewDetails, &group, load_membership(&state, &group_id, &current_user.id).await) {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "You must be a member to view this group's members"
+        }));
+    }
+
+    // Admins first, then moderators, then members in the order they joined
+    let members = sqlx::query_as::<_, GroupMember>(
+        r#"
+        SELECT * FROM group_members WHERE group_id = ?
+        ORDER BY CASE role WHEN 'admin' THEN 0 WHEN 'moderator' THEN 1 ELSE 2 END, joined_at ASC
+        "#
+    )
+    .bind(&group_id)
+    .fetch_all(&state.db)
+    .await;
+
+    match members {
+        Ok(members) => {
+            let mut members_with_users: Vec<GroupMemberWithUser> = Vec::new();
+
+            for member in members {
+                if let Ok(Some(user)) = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+                    .bind(&member.user_id)
+                    .fetch_optional(&state.db)
+                    .await
+                {
+                    members_with_users.push(GroupMemberWithUser {
+                        is_owner: member.user_id == group.creator_id,
+                        user: user.into(),
+                        role: member.role,
+                        joined_at: member.joined_at,
+                    });
+                }
+            }
+
+            HttpResponse::Ok().json(members_with_users)
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to get members: {}", e)
+        })),
+    }
+}
+
+pub async fn update_member_role(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+    body: web::Json<UpdateMemberRoleRequest>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let (group_id, user_id) = path.into_inner();
+
+    // Admins are only created by transferring ownership
+    if !["moderator", "member"].contains(&body.role.as_str()) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Role must be moderator or member"
+        }));
+    }
+
+    let group = match fetch_group(&state, &group_id).await {
+        Ok(group) => group,
+        Err(response) => return response,
+    };
+
+    if !group_allows(GroupAction::Administer, &group, load_membership(&state, &group_id, &current_user.id).await) {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Only group admins can change member roles"
+        }));
+    }
+
+    let target_role = match get_member_role(&state, &group_id, &user_id).await {
+        Some(role) => role,
+        None => return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "User is not a member of this group"
+        })),
+    };
+
+    if user_id == group.creator_id {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "The group owner's role cannot be changed; transfer ownership instead"
+        }));
+    }
+
+    if target_role == "admin" && current_user.id != group.creator_id {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Only the group owner can change another admin's role"
+        }));
+    }
+
+    let result = sqlx::query("UPDATE group_members SET role = ? WHERE group_id = ? AND user_id = ?")
+        .bind(&body.role)
+        .bind(&group_id)
+        .bind(&user_id)
+        .execute(&state.db)
+        .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Member role updated",
+            "role": body.role
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to update member role: {}", e)
+        })),
+    }
+}
+
+pub async fn kick_member(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let (group_id, user_id) = path.into_inner();
+
+    let target_role = match get_member_role(&state, &group_id, &user_id).await {
+        Some(role) => role,
+        None => return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "User is not a member of this group"
+        })),
+    };
+
+    if !outranks(load_membership(&state, &group_id, &current_user.id).await, Membership::from_role(Some(&target_role))) {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "You cannot remove this member"
+        }));
+    }
+
+    let result = sqlx::query("DELETE FROM group_members WHERE group_id = ? AND user_id = ?")
+        .bind(&group_id)
+        .bind(&user_id)
+        .execute(&state.db)
+        .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Member removed"
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to remove member: {}", e)
+        })),
+    }
+}
+
+pub async fn get_bans(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let group_id = path.into_inner();
+
+    if !outranks(load_membership(&state, &group_id, &current_user.id).await, Membership::None) {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Only group admins and moderators can view bans"
+        }));
+    }
+
+    let bans = sqlx::query_as::<_, GroupBan>(
+        "SELECT * FROM group_bans WHERE group_id = ? ORDER BY created_at DESC"
+    )
+    .bind(&group_id)
+    .fetch_all(&state.db)
+    .await;
+
+    match bans {
+        Ok(bans) => {
+            let mut bans_with_users: Vec<GroupBanWithUser> = Vec::new();
+
+            for ban in bans {
+                // Bans issued through anonymous content keep the author hidden
+                let user = if ban.anonymous_alias.is_some() {
+                    None
+                } else {
+                    sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+                        .bind(&ban.user_id)
+                        .fetch_optional(&state.db)
+                        .await
+                        .ok()
+                        .flatten()
+                        .map(UserResponse::from)
+                };
+
+                bans_with_users.push(GroupBanWithUser {
+                    ban_id: ban.id,
+                    user,
+                    anonymous_alias: ban.anonymous_alias,
+                    banned_by: ban.banned_by,
+                    reason: ban.reason,
+                    created_at: ban.created_at,
+                });
+            }
+
+            HttpResponse::Ok().json(bans_with_users)
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to get bans: {}", e)
+        })),
+    }
+}
+
+pub async fn ban_member(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+    body: Option<web::Json<BanMemberRequest>>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let (group_id, user_id) = path.into_inner();
+
+    if let Err(response) = fetch_group(&state, &group_id).await {
+        return response;
+    }
+
+    let user_exists = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(&user_id)
+        .fetch_optional(&state.db)
+        .await;
+
+    if let Ok(None) = user_exists {
+        return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "User not found"
+        }));
+    }
+
+    // Non-members can be banned pre-emptively, e.g. to stop a join request spammer.
+    // The target's role is read directly, as an anonymous ban would hide it from load_membership.
+    let target = Membership::from_role(get_member_role(&state, &group_id, &user_id).await.as_deref());
+    if !outranks(load_membership(&state, &group_id, &current_user.id).await, target) {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "You cannot ban this user"
+        }));
+    }
+
+    let reason = body.and_then(|b| b.reason.clone());
+
+    match apply_ban(&state, &group_id, &user_id, &current_user.id, reason, None).await {
+        Ok(true) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "User banned from group"
+        })),
+        Ok(false) => HttpResponse::Conflict().json(serde_json::json!({
+            "error": "User is already banned from this group"
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to ban user: {}", e)
+        })),
+    }
+}
+
+pub async fn unban_member(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let (group_id, user_id) = path.into_inner();
+
+    if !outranks(load_membership(&state, &group_id, &current_user.id).await, Membership::None) {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Only group admins and moderators can lift bans"
+        }));
+    }
+
+    // Named bans are addressed by user id and anonymous ones only by their ban id,
+    // so a user id can't be matched against a hidden author
+    let result = sqlx::query(
+        r#"
+        DELETE FROM group_bans
+        WHERE group_id = ?
+        AND ((anonymous_alias IS NULL AND user_id = ?) OR (anonymous_alias IS NOT NULL AND id = ?))
+        "#
+    )
+    .bind(&group_id)
+    .bind(&user_id)
+    .bind(&user_id)
+    .execute(&state.db)
+    .await;
+
+    match result {
+        Ok(r) => {
+            if r.rows_affected() > 0 {
+                HttpResponse::Ok().json(serde_json::json!({
+                    "message": "Ban lifted"
+                }))
+            } else {
+                HttpResponse::NotFound().json(serde_json::json!({
+                    "error": "User is not banned from this group"
+                }))
+            }
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to lift ban: {}", e)
+        })),
+    }
+}
+
+pub async fn transfer_ownership(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let (group_id, user_id) = path.into_inner();
+
+    let group = match fetch_group(&state, &group_id).await {
+        Ok(group) => group,
+        Err(response) => return response,
+    };
+
+    if group.creator_id != current_user.id {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Only the group owner can transfer ownership"
+        }));
+    }
+
+    if user_id == current_user.id {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "You already own this group"
+        }));
+    }
+
+    if get_member_role(&state, &group_id, &user_id).await.is_none() {
+        return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "User is not a member of this group"
+        }));
+    }
+
+    // The previous owner stays on as an admin
+    let result: Result<(), sqlx::Error> = async {
+        let mut tx = state.db.begin().await?;
+
+        sqlx::query("UPDATE group_members SET role = 'admin' WHERE group_id = ? AND user_id = ?")
+            .bind(&group_id)
+            .bind(&user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("UPDATE groups SET creator_id = ? WHERE id = ?")
+            .bind(&user_id)
+            .bind(&group_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await
+    }
+    .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Ownership transferred"
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to transfer ownership: {}", e)
+        })),
+    }
+}
+
+// Helper functions
+
+/// Returns true if the user is banned from the group.
+pub async fn is_banned(state: &web::Data<AppState>, group_id: &str, user_id: &str) -> bool {
+    sqlx::query_scalar::<_, i32>("SELECT COUNT(*) FROM group_bans WHERE group_id = ? AND user_id = ?")
+        .bind(group_id)
+        .bind(user_id)
+        .fetch_one(&state.db)
+        .await
+        .unwrap_or(0) > 0
+}
+
+/// Bans the user. Returns false if they were already banned the same way.
+///
+/// A named ban ends any membership, pending join request or invitation. An
+/// anonymous ban leaves the author on the member list, so comparing member
+/// lists can't tie them to their alias; `load_membership` treats them as an
+/// outsider instead. Named bans never see anonymous ones, so banning a suspect
+/// by name answers the same whether or not they are the hidden author.
+pub async fn apply_ban(
+    state: &web::Data<AppState>,
+    group_id: &str,
+    user_id: &str,
+    banned_by: &str,
+    reason: Option<String>,
+    anonymous_alias: Option<String>,
+) -> Result<bool, sqlx::Error> {
+    let reason = reason.filter(|r| !r.trim().is_empty());
+
+    let mut tx = state.db.begin().await?;
+
+    let result = sqlx::query(
+        r#"
+        INSERT INTO group_bans (id, group_id, user_id, banned_by, reason, anonymous_alias, created_at)
+        SELECT ?1, ?2, ?3, ?4, ?5, ?6, ?7
+        WHERE NOT EXISTS (
+            SELECT 1 FROM group_bans WHERE group_id = ?2 AND user_id = ?3 AND anonymous_alias IS ?6
+        )
+        "#
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(group_id)
+    .bind(user_id)
+    .bind(banned_by)
+    .bind(&reason)
+    .bind(&anonymous_alias)
+    .bind(Utc::now().to_rfc3339())
+    .execute(&mut *tx)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    if anonymous_alias.is_none() {
+        for table in ["group_members", "group_join_requests", "group_invitations"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE group_id = ? AND user_id = ?", table))
+                .bind(group_id)
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+
+    tx.commit().await?;
+    Ok(true)
+}
+
+async fn fetch_group(state: &web::Data<AppState>, group_id: &str) -> Result<Group, HttpResponse> {
+    match sqlx::query_as::<_, Group>("SELECT * FROM groups WHERE id = ?")
+        .bind(group_id)
+        .fetch_optional(&state.db)
+        .await
+    {
+        Ok(Some(group)) => Ok(group),
+        Ok(None) => Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Group not found"
+        }))),
+        Err(e) => Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        }))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::groups::join_group;
+    use crate::test_support::{auth_request, create_group, create_user, json, test_state};
+
+    #[actix_rt::test]
+    async fn members_can_only_be_managed_by_someone_who_outranks_them() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+        let carol = create_user(&state, "carol").await;
+        let dave = create_user(&state, "dave").await;
+        let group_id = create_group(&state, &alice, &[&bob, &carol, &dave]).await;
+        let path = |user_id: &str| web::Path::from((group_id.clone(), user_id.to_string()));
+        let set_role = |actor: &str, user_id: &str, role: &str| {
+            update_member_role(auth_request(actor), state.clone(), path(user_id), json(serde_json::json!({"role": role})))
+        };
+
+        // Admins are only made by transferring ownership
+        assert_eq!(set_role(&alice, &bob, "admin").await.status().as_u16(), 400);
+        assert_eq!(set_role(&alice, &bob, "moderator").await.status().as_u16(), 200);
+        assert_eq!(set_role(&bob, &carol, "moderator").await.status().as_u16(), 403);
+
+        assert_eq!(kick_member(auth_request(&bob), state.clone(), path(&alice)).await.status().as_u16(), 403);
+        assert_eq!(kick_member(auth_request(&bob), state.clone(), path(&dave)).await.status().as_u16(), 200);
+        assert_eq!(get_member_role(&state, &group_id, &dave).await, None);
+
+        assert_eq!(ban_member(auth_request(&bob), state.clone(), path(&carol), None).await.status().as_u16(), 200);
+        assert_eq!(ban_member(auth_request(&bob), state.clone(), path(&carol), None).await.status().as_u16(), 409);
+        let rejoin = || join_group(auth_request(&carol), state.clone(), web::Path::from(group_id.clone()));
+        assert_eq!(rejoin().await.status().as_u16(), 403);
+        assert_eq!(unban_member(auth_request(&bob), state.clone(), path(&carol)).await.status().as_u16(), 200);
+        assert_eq!(rejoin().await.status().as_u16(), 200);
+
+        // The previous owner stays on as an admin the new owner can demote
+        assert_eq!(transfer_ownership(auth_request(&bob), state.clone(), path(&alice)).await.status().as_u16(), 403);
+        assert_eq!(transfer_ownership(auth_request(&alice), state.clone(), path(&bob)).await.status().as_u16(), 200);
+        assert_eq!(set_role(&alice, &bob, "member").await.status().as_u16(), 400);
+        assert_eq!(set_role(&bob, &alice, "member").await.status().as_u16(), 200);
+        assert_eq!(kick_member(auth_request(&alice), state.clone(), path(&carol)).await.status().as_u16(), 403);
+    }
+}
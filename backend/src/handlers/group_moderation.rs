@@ -0,0 +1,622 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::Utc;
+
+use crate::anonymity::thread_alias;
+use crate::auth::require_auth;
+use crate::handlers::group_members::apply_ban;
+use crate::handlers::posts::{build_post_response, delete_post_rows};
+use crate::models::{BanMemberRequest, Comment, Post, PostResponse};
+use crate::notifications::{notify, notify_group_members, Notification};
+use crate::policy::{is_group_moderator, load_membership, outranks};
+use crate::tagging::notify_post_mentions;
+use crate::AppState;
+
+pub async fn get_pending_posts(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let group_id = path.into_inner();
+
+    if !is_group_moderator(&state, &group_id, &current_user.id).await {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Only group admins and moderators can review pending posts"
+        }));
+    }
+
+    let posts = sqlx::query_as::<_, Post>(
+        "SELECT * FROM posts WHERE group_id = ? AND status = 'pending' ORDER BY created_at ASC"
+    )
+    .bind(&group_id)
+    .fetch_all(&state.db)
+    .await;
+
+    match posts {
+        Ok(posts) => {
+            let mut post_responses: Vec<PostResponse> = Vec::new();
+
+            for post in posts {
+                let post_response = build_post_response(&state, &post, &current_user.id).await;
+                post_responses.push(post_response);
+            }
+
+            HttpResponse::Ok().json(post_responses)
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to get pending posts: {}", e)
+        })),
+    }
+}
+
+pub async fn approve_post(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let (group_id, post_id) = path.into_inner();
+
+    let mut post = match fetch_moderated_post(&state, &group_id, &post_id, &current_user.id).await {
+        Ok(post) => post,
+        Err(response) => return response,
+    };
+
+    let result = sqlx::query(
+        "UPDATE posts SET status = 'published', updated_at = ? WHERE id = ? AND status = 'pending'"
+    )
+    .bind(Utc::now().to_rfc3339())
+    .bind(&post_id)
+    .execute(&state.db)
+    .await;
+
+    match result {
+        Ok(r) => {
+            if r.rows_affected() == 0 {
+                return HttpResponse::NotFound().json(serde_json::json!({
+                    "error": "Post is not pending approval"
+                }));
+            }
+
+            notify(&state, &post.user_id, &Notification {
+                kind: "group_post_approved",
+                actor_id: current_user.id.clone(),
+                post_id: Some(post.id.clone()),
+                group_id: Some(group_id.clone()),
+                is_anonymous: false,
+            }).await;
+            notify_group_members(&state, &group_id, &Notification {
+                kind: "group_post",
+                actor_id: post.user_id.clone(),
+                post_id: Some(post.id.clone()),
+                group_id: Some(group_id.clone()),
+                is_anonymous: post.is_anonymous,
+            }).await;
+
+            post.status = "published".to_string();
+            notify_post_mentions(&state, &post).await;
+
+            HttpResponse::Ok().json(serde_json::json!({
+                "message": "Post approved"
+            }))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to approve post: {}", e)
+        })),
+    }
+}
+
+pub async fn reject_post(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let (group_id, post_id) = path.into_inner();
+
+    let post = match fetch_moderated_post(&state, &group_id, &post_id, &current_user.id).await {
+        Ok(post) => post,
+        Err(response) => return response,
+    };
+
+    if post.status != "pending" {
+        return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Post is not pending approval"
+        }));
+    }
+
+    let result: Result<(), sqlx::Error> = async {
+        let mut tx = state.db.begin().await?;
+        delete_post_rows(&mut tx, &post_id).await?;
+        tx.commit().await
+    }
+    .await;
+
+    match result {
+        Ok(_) => {
+            notify(&state, &post.user_id, &Notification {
+                kind: "group_post_rejected",
+                actor_id: current_user.id.clone(),
+                post_id: None,
+                group_id: Some(group_id.clone()),
+                is_anonymous: false,
+            }).await;
+
+            HttpResponse::Ok().json(serde_json::json!({
+                "message": "Post rejected"
+            }))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to reject post: {}", e)
+        })),
+    }
+}
+
+pub async fn pin_post(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    set_post_flag(req, state, path, "is_pinned", true, "Post pinned").await
+}
+
+pub async fn unpin_post(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    set_post_flag(req, state, path, "is_pinned", false, "Post unpinned").await
+}
+
+pub async fn lock_comments(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    set_post_flag(req, state, path, "comments_locked", true, "Comments locked").await
+}
+
+pub async fn unlock_comments(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    set_post_flag(req, state, path, "comments_locked", false, "Comments unlocked").await
+}
+
+pub async fn delete_group_post(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let (group_id, post_id) = path.into_inner();
+
+    if let Err(response) = fetch_moderated_post(&state, &group_id, &post_id, &current_user.id).await {
+        return response;
+    }
+
+    let result: Result<(), sqlx::Error> = async {
+        let mut tx = state.db.begin().await?;
+        delete_post_rows(&mut tx, &post_id).await?;
+        tx.commit().await
+    }
+    .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Post deleted"
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to delete post: {}", e)
+        })),
+    }
+}
+
+pub async fn delete_group_comment(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let (group_id, comment_id) = path.into_inner();
+
+    if !is_group_moderator(&state, &group_id, &current_user.id).await {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Only group admins and moderators can moderate this group"
+        }));
+    }
+
+    // Only comments on this group's posts can be removed through it
+    let comment = sqlx::query_as::<_, Comment>(
+        r#"
+        SELECT c.* FROM comments c
+        INNER JOIN posts p ON p.id = c.post_id
+        WHERE c.id = ? AND p.group_id = ?
+        "#
+    )
+    .bind(&comment_id)
+    .bind(&group_id)
+    .fetch_optional(&state.db)
+    .await;
+
+    if let Ok(None) = comment {
+        return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Comment not found"
+        }));
+    }
+
+    let _ = sqlx::query("DELETE FROM mentions WHERE comment_id = ?")
+        .bind(&comment_id)
+        .execute(&state.db)
+        .await;
+
+    let result = sqlx::query("DELETE FROM comments WHERE id = ?")
+        .bind(&comment_id)
+        .execute(&state.db)
+        .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Comment deleted"
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to delete comment: {}", e)
+        })),
+    }
+}
+
+pub async fn ban_post_author(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+    body: Option<web::Json<BanMemberRequest>>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let (group_id, post_id) = path.into_inner();
+
+    let post = match fetch_moderated_post(&state, &group_id, &post_id, &current_user.id).await {
+        Ok(post) => post,
+        Err(response) => return response,
+    };
+
+    let reason = body.and_then(|b| b.reason.clone());
+    ban_author(&state, &group_id, &post.id, &post.user_id, post.is_anonymous, &current_user.id, reason).await
+}
+
+pub async fn ban_comment_author(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+    body: Option<web::Json<BanMemberRequest>>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let (group_id, comment_id) = path.into_inner();
+
+    if !is_group_moderator(&state, &group_id, &current_user.id).await {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Only group admins and moderators can moderate this group"
+        }));
+    }
+
+    let comment = match sqlx::query_as::<_, Comment>(
+        r#"
+        SELECT c.* FROM comments c
+        INNER JOIN posts p ON p.id = c.post_id
+        WHERE c.id = ? AND p.group_id = ?
+        "#
+    )
+    .bind(&comment_id)
+    .bind(&group_id)
+    .fetch_optional(&state.db)
+    .await
+    {
+        Ok(Some(comment)) => comment,
+        Ok(None) => return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Comment not found"
+        })),
+        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        })),
+    };
+
+    let reason = body.and_then(|b| b.reason.clone());
+    ban_author(&state, &group_id, &comment.post_id, &comment.user_id, comment.is_anonymous, &current_user.id, reason).await
+}
+
+// Helper functions
+
+/// Loads a post of the group after checking the user may moderate the group.
+async fn fetch_moderated_post(
+    state: &web::Data<AppState>,
+    group_id: &str,
+    post_id: &str,
+    moderator_id: &str,
+) -> Result<Post, HttpResponse> {
+    if !is_group_moderator(state, group_id, moderator_id).await {
+        return Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Only group admins and moderators can moderate this group"
+        })));
+    }
+
+    match sqlx::query_as::<_, Post>("SELECT * FROM posts WHERE id = ? AND group_id = ?")
+        .bind(post_id)
+        .bind(group_id)
+        .fetch_optional(&state.db)
+        .await
+    {
+        Ok(Some(post)) => Ok(post),
+        Ok(None) => Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Post not found"
+        }))),
+        Err(e) => Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        }))),
+    }
+}
+
+/// Sets one of the moderator-controlled boolean columns on a group post.
+async fn set_post_flag(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+    column: &'static str,
+    value: bool,
+    message: &str,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let (group_id, post_id) = path.into_inner();
+
+    if let Err(response) = fetch_moderated_post(&state, &group_id, &post_id, &current_user.id).await {
+        return response;
+    }
+
+    let result = sqlx::query(&format!("UPDATE posts SET {} = ? WHERE id = ?", column))
+        .bind(value)
+        .bind(&post_id)
+        .execute(&state.db)
+        .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "message": message
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to update post: {}", e)
+        })),
+    }
+}
+
+/// Bans the author of a post or comment. For anonymous content the ban is
+/// recorded under the author's alias in that thread, and the response never
+/// includes who they are or why they can't be banned.
+async fn ban_author(
+    state: &web::Data<AppState>,
+    group_id: &str,
+    post_id: &str,
+    author_id: &str,
+    is_anonymous: bool,
+    moderator_id: &str,
+    reason: Option<String>,
+) -> HttpResponse {
+    let anonymous_refusal = || HttpResponse::Forbidden().json(serde_json::json!({
+        "error": "The author of this anonymous content can't be banned"
+    }));
+
+    if !outranks(load_membership(state, group_id, moderator_id).await, load_membership(state, group_id, author_id).await) {
+        if is_anonymous {
+            return anonymous_refusal();
+        }
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "You cannot ban this author"
+        }));
+    }
+
+    let anonymous_alias = if is_anonymous {
+        // Normally already assigned when the content was written
+        let alias = async { thread_alias(&mut *state.db.acquire().await?, post_id, author_id).await }.await;
+        match alias {
+            Ok(alias) => Some(alias),
+            Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Failed to ban author: {}", e)
+            })),
+        }
+    } else {
+        None
+    };
+
+    match apply_ban(state, group_id, author_id, moderator_id, reason, anonymous_alias).await {
+        Ok(true) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Author banned from group"
+        })),
+        Ok(false) if is_anonymous => anonymous_refusal(),
+        Ok(false) => HttpResponse::Conflict().json(serde_json::json!({
+            "error": "Author is already banned from this group"
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to ban author: {}", e)
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::group_members::{ban_member, get_bans, get_members, unban_member};
+    use crate::handlers::groups::create_group_post;
+    use crate::handlers::posts::add_comment;
+    use crate::test_support::{auth_request, count, create_group, create_user, json, post_in_group, response_json, test_state};
+
+    /// Everything that hangs off posts, which must be gone once they are.
+    const POST_ROWS: &[&str] = &[
+        "SELECT COUNT(*) FROM posts",
+        "SELECT COUNT(*) FROM comments",
+        "SELECT COUNT(*) FROM mentions",
+        "SELECT COUNT(*) FROM post_hashtags",
+        "SELECT COUNT(*) FROM polls",
+        "SELECT COUNT(*) FROM poll_options",
+        "SELECT COUNT(*) FROM anonymous_aliases",
+    ];
+
+    async fn tagged_post_with_poll(state: &web::Data<AppState>, user_id: &str, group_id: &str, is_anonymous: bool) -> String {
+        let post = post_in_group(state, user_id, group_id, serde_json::json!({
+            "content": "Thoughts on #rust, @alice?",
+            "is_anonymous": is_anonymous,
+            "poll": {"options": ["Yes", "No"]}
+        }))
+        .await;
+        post["id"].as_str().unwrap().to_string()
+    }
+
+    #[actix_rt::test]
+    async fn rejecting_a_pending_post_removes_its_tags_and_poll() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+        let group_id = create_group(&state, &alice, &[&bob]).await;
+        sqlx::query("UPDATE groups SET require_post_approval = 1").execute(&state.db).await.unwrap();
+
+        let post_id = tagged_post_with_poll(&state, &bob, &group_id, false).await;
+        assert_eq!(count(&state, "SELECT COUNT(*) FROM posts WHERE status = 'pending'").await, 1);
+        assert_eq!(count(&state, "SELECT COUNT(*) FROM post_hashtags").await, 1);
+
+        let response = reject_post(auth_request(&alice), state.clone(), web::Path::from((group_id, post_id))).await;
+        assert_eq!(response.status().as_u16(), 200);
+        for sql in POST_ROWS {
+            assert_eq!(count(&state, sql).await, 0, "{}", sql);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn moderators_delete_posts_with_everything_attached() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+        let group_id = create_group(&state, &alice, &[&bob]).await;
+        sqlx::query("UPDATE groups SET allow_anonymous = 1").execute(&state.db).await.unwrap();
+
+        let post_id = tagged_post_with_poll(&state, &bob, &group_id, true).await;
+        let response = add_comment(
+            auth_request(&alice),
+            state.clone(),
+            web::Path::from(post_id.clone()),
+            json(serde_json::json!({"content": "Sure, @bob", "is_anonymous": true})),
+        )
+        .await;
+        assert_eq!(response.status().as_u16(), 201);
+        assert_eq!(count(&state, "SELECT COUNT(*) FROM anonymous_aliases").await, 2);
+
+        let response = delete_group_post(auth_request(&alice), state.clone(), web::Path::from((group_id, post_id))).await;
+        assert_eq!(response.status().as_u16(), 200);
+        for sql in POST_ROWS {
+            assert_eq!(count(&state, sql).await, 0, "{}", sql);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn refusing_to_ban_an_anonymous_author_does_not_say_why() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+        let carol = create_user(&state, "carol").await;
+        let group_id = create_group(&state, &alice, &[&bob, &carol]).await;
+        sqlx::query("UPDATE groups SET allow_anonymous = 1").execute(&state.db).await.unwrap();
+        sqlx::query("UPDATE group_members SET role = 'moderator' WHERE user_id IN (?, ?)")
+            .bind(&bob)
+            .bind(&carol)
+            .execute(&state.db)
+            .await
+            .unwrap();
+
+        // A moderator can't ban another moderator, but mustn't learn that's who it is
+        let post_id = tagged_post_with_poll(&state, &bob, &group_id, true).await;
+        let response = ban_post_author(auth_request(&carol), state.clone(), web::Path::from((group_id, post_id)), None).await;
+        assert_eq!(response.status().as_u16(), 403);
+        assert_eq!(
+            response_json(response).await["error"],
+            "The author of this anonymous content can't be banned"
+        );
+    }
+
+    async fn member_ids(state: &web::Data<AppState>, viewer: &str, group_id: &str) -> Vec<String> {
+        let members = response_json(get_members(auth_request(viewer), state.clone(), web::Path::from(group_id.to_string())).await).await;
+        members.as_array().unwrap().iter().map(|m| m["user"]["id"].as_str().unwrap().to_string()).collect()
+    }
+
+    #[actix_rt::test]
+    async fn anonymous_bans_cannot_be_traced_back_to_the_author() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+        let carol = create_user(&state, "carol").await;
+        let group_id = create_group(&state, &alice, &[&bob, &carol]).await;
+        sqlx::query("UPDATE groups SET allow_anonymous = 1").execute(&state.db).await.unwrap();
+
+        let post_id = tagged_post_with_poll(&state, &bob, &group_id, true).await;
+        let members_before = member_ids(&state, &alice, &group_id).await;
+        let response = ban_post_author(auth_request(&alice), state.clone(), web::Path::from((group_id.clone(), post_id)), None).await;
+        assert_eq!(response.status().as_u16(), 200);
+
+        // The member list doesn't change, but the author has lost their membership rights
+        assert_eq!(member_ids(&state, &alice, &group_id).await, members_before);
+        let response = create_group_post(
+            auth_request(&bob),
+            state.clone(),
+            web::Path::from(group_id.clone()),
+            json(serde_json::json!({"content": "Still here"})),
+        )
+        .await;
+        assert_eq!(response.status().as_u16(), 403);
+
+        // Lifting by user id never matches the anonymous ban
+        let response = unban_member(auth_request(&alice), state.clone(), web::Path::from((group_id.clone(), bob.clone()))).await;
+        assert_eq!(response.status().as_u16(), 404);
+
+        // Banning the author by name looks like banning anyone else
+        for suspect in [&carol, &bob] {
+            let response = ban_member(auth_request(&alice), state.clone(), web::Path::from((group_id.clone(), suspect.clone())), None).await;
+            assert_eq!(response.status().as_u16(), 200);
+            assert_eq!(response_json(response).await["message"], "User banned from group");
+        }
+        assert_eq!(member_ids(&state, &alice, &group_id).await, vec![alice.clone()]);
+
+        let bans = response_json(get_bans(auth_request(&alice), state.clone(), web::Path::from(group_id.clone())).await).await;
+        let anonymous_ban = bans.as_array().unwrap().iter().find(|b| b["anonymous_alias"].is_string()).unwrap();
+        assert_eq!(anonymous_ban["user"], serde_json::Value::Null);
+        let ban_id = anonymous_ban["ban_id"].as_str().unwrap().to_string();
+        let response = unban_member(auth_request(&alice), state.clone(), web::Path::from((group_id, ban_id))).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+}
@@ -1,11 +1,42 @@
 use actix_web::{web, HttpRequest, HttpResponse};
-use chrono::Utc;
+use chrono::{Duration, Utc};
+use serde::Deserialize;
 use uuid::Uuid;
 
+use crate::anonymity::thread_alias;
 use crate::auth::require_auth;
-use crate::models::{CreateGroupPostRequest, CreateGroupRequest, Group, GroupMember, GroupResponse, Post, PostResponse, User, UserResponse};
+use crate::handlers::blocks::is_blocked;
+use crate::handlers::group_members::is_banned;
+use crate::handlers::polls::{insert_poll, validate_poll};
+use crate::handlers::posts::{build_post_response, parse_schedule};
+use crate::handlers::search::build_match_query;
+use crate::link_previews::{first_url, queue_link_preview};
+use crate::models::{
+    CreateGroupPostRequest, CreateGroupRequest, CreateInviteCodeRequest, Group, GroupInvitation,
+    GroupInvitationResponse, GroupInviteCode, GroupJoinRequest, GroupJoinRequestWithUser,
+    GroupResponse, Post, PostResponse, UpdateGroupRequest, User, UserResponse,
+};
+use crate::notifications::{notify, notify_group_members, notify_group_moderators, Notification};
+use crate::policy::{group_allows, is_group_moderator, load_membership, GroupAction, Membership};
+use crate::tagging::{notify_post_mentions, sync_post_tags};
 use crate::AppState;
 
+/// Posts newer than this count as recent activity when sorting the directory.
+const GROUP_ACTIVITY_WINDOW_DAYS: i64 = 7;
+const MAX_GROUP_TAGS: usize = 10;
+const MAX_TAG_LENGTH: usize = 30;
+const MAX_INVITE_CODE_HOURS: i64 = 24 * 90;
+
+#[derive(Deserialize)]
+pub struct DiscoverQuery {
+    pub q: Option<String>,
+    pub sort: Option<String>, // members (default), activity, newest
+    pub category: Option<String>,
+    pub tag: Option<String>,
+    pub page: Option<i64>,
+    pub limit: Option<i64>,
+}
+
 pub async fn get_groups(
     req: HttpRequest,
     state: web::Data<AppState>,
@@ -45,6 +76,78 @@ pub async fn get_groups(
     }
 }
 
+pub async fn discover_groups(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<DiscoverQuery>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let order_by = match query.sort.as_deref().unwrap_or("members") {
+        "members" => "COALESCE(m.members_count, 0) DESC, COALESCE(a.recent_posts, 0) DESC",
+        "activity" => "COALESCE(a.recent_posts, 0) DESC, a.last_post_at DESC",
+        "newest" => "g.created_at DESC",
+        other => return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("Unknown sort: {} (expected members, activity or newest)", other)
+        })),
+    };
+
+    let match_query = query.q.as_deref().and_then(build_match_query);
+    let category = query.category.as_deref().map(|c| c.trim().to_lowercase());
+    let tag = query.tag.as_deref().map(normalize_tag);
+    let limit = query.limit.unwrap_or(20).clamp(1, 50);
+    let offset = query.page.unwrap_or(0).max(0) * limit;
+    let activity_since = (Utc::now() - Duration::days(GROUP_ACTIVITY_WINDOW_DAYS)).to_rfc3339();
+
+    // Private groups are never listed; they can only be found by invitation
+    let groups = sqlx::query_as::<_, Group>(&format!(
+        r#"
+        SELECT g.* FROM groups g
+        LEFT JOIN (
+            SELECT group_id, COUNT(*) AS members_count FROM group_members GROUP BY group_id
+        ) m ON m.group_id = g.id
+        LEFT JOIN (
+            SELECT group_id, SUM(created_at > ?1) AS recent_posts, MAX(created_at) AS last_post_at
+            FROM posts WHERE group_id IS NOT NULL AND status = 'published' GROUP BY group_id
+        ) a ON a.group_id = g.id
+        WHERE g.is_private = 0
+          AND (?2 IS NULL OR g.id IN (SELECT group_id FROM groups_fts WHERE groups_fts MATCH ?2))
+          AND (?3 IS NULL OR g.category = ?3)
+          AND (?4 IS NULL OR g.id IN (SELECT group_id FROM group_tags WHERE tag = ?4))
+        ORDER BY {}
+        LIMIT ?5 OFFSET ?6
+        "#,
+        order_by
+    ))
+    .bind(&activity_since)
+    .bind(&match_query)
+    .bind(&category)
+    .bind(&tag)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(&state.db)
+    .await;
+
+    match groups {
+        Ok(groups) => {
+            let mut group_responses: Vec<GroupResponse> = Vec::new();
+
+            for group in groups {
+                let group_response = build_group_response(&state, &group, &current_user.id).await;
+                group_responses.push(group_response);
+            }
+
+            HttpResponse::Ok().json(group_responses)
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to discover groups: {}", e)
+        })),
+    }
+}
+
 pub async fn create_group(
     req: HttpRequest,
     state: web::Data<AppState>,
@@ -65,10 +168,15 @@ pub async fn create_group(
     let member_id = Uuid::new_v4().to_string();
     let now = Utc::now().to_rfc3339();
     let is_private = body.is_private.unwrap_or(false);
+    let category = body
+        .category
+        .as_deref()
+        .map(|c| c.trim().to_lowercase())
+        .filter(|c| !c.is_empty());
 
     // Create group
     let result = sqlx::query(
-        "INSERT INTO groups (id, name, description, creator_id, is_private, created_at) VALUES (?, ?, ?, ?, ?, ?)"
+        "INSERT INTO groups (id, name, description, creator_id, is_private, created_at, category) VALUES (?, ?, ?, ?, ?, ?, ?)"
     )
     .bind(&group_id)
     .bind(&body.name)
@@ -76,6 +184,7 @@ pub async fn create_group(
     .bind(&current_user.id)
     .bind(is_private)
     .bind(&now)
+    .bind(&category)
     .execute(&state.db)
     .await;
 
@@ -96,6 +205,10 @@ pub async fn create_group(
     .execute(&state.db)
     .await;
 
+    if let Some(ref tags) = body.tags {
+        set_group_tags(&state, &group_id, tags).await;
+    }
+
     match member_result {
         Ok(_) => {
             let group = Group {
@@ -106,6 +219,10 @@ pub async fn create_group(
                 creator_id: current_user.id.clone(),
                 is_private,
                 created_at: now,
+                category,
+                posting_policy: "everyone".to_string(),
+                require_post_approval: false,
+                allow_anonymous: true,
             };
             
             let group_response = build_group_response(&state, &group, &current_user.id).await;
@@ -148,10 +265,11 @@ pub async fn get_group(
     }
 }
 
-pub async fn join_group(
+pub async fn update_group(
     req: HttpRequest,
     state: web::Data<AppState>,
     path: web::Path<String>,
+    body: web::Json<UpdateGroupRequest>,
 ) -> HttpResponse {
     let current_user = match require_auth(&req, &state).await {
         Ok(user) => user,
@@ -160,47 +278,241 @@ pub async fn join_group(
 
     let group_id = path.into_inner();
 
-    // Check if group exists
-    let group = sqlx::query_as::<_, Group>("SELECT * FROM groups WHERE id = ?")
+    let mut group = match sqlx::query_as::<_, Group>("SELECT * FROM groups WHERE id = ?")
         .bind(&group_id)
         .fetch_optional(&state.db)
-        .await;
-
-    if let Ok(None) = group {
-        return HttpResponse::NotFound().json(serde_json::json!({
+        .await
+    {
+        Ok(Some(group)) => group,
+        Ok(None) => return HttpResponse::NotFound().json(serde_json::json!({
             "error": "Group not found"
+        })),
+        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        })),
+    };
+
+    if !group_allows(GroupAction::Administer, &group, load_membership(&state, &group_id, &current_user.id).await) {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Only group admins can edit group settings"
         }));
     }
 
-    // Check if already a member
-    let existing = sqlx::query_as::<_, GroupMember>(
-        "SELECT * FROM group_members WHERE group_id = ? AND user_id = ?"
-    )
-    .bind(&group_id)
-    .bind(&current_user.id)
-    .fetch_optional(&state.db)
-    .await;
+    if let Some(ref name) = body.name {
+        if name.trim().is_empty() {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "Group name cannot be empty"
+            }));
+        }
+        group.name = name.clone();
+    }
 
-    if let Ok(Some(_)) = existing {
-        return HttpResponse::Conflict().json(serde_json::json!({
-            "error": "Already a member of this group"
-        }));
+    if let Some(ref posting_policy) = body.posting_policy {
+        if !["everyone", "admins"].contains(&posting_policy.as_str()) {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "posting_policy must be everyone or admins"
+            }));
+        }
+        group.posting_policy = posting_policy.clone();
     }
 
-    let member_id = Uuid::new_v4().to_string();
-    let now = Utc::now().to_rfc3339();
+    // Empty strings clear the optional fields
+    if let Some(ref description) = body.description {
+        group.description = Some(description.clone()).filter(|d| !d.trim().is_empty());
+    }
+    if let Some(ref cover_image) = body.cover_image {
+        group.cover_image = Some(cover_image.clone()).filter(|c| !c.trim().is_empty());
+    }
+    if let Some(ref category) = body.category {
+        group.category = Some(category.trim().to_lowercase()).filter(|c| !c.is_empty());
+    }
+
+    let was_private = group.is_private;
+    group.is_private = body.is_private.unwrap_or(group.is_private);
+    group.require_post_approval = body.require_post_approval.unwrap_or(group.require_post_approval);
+    group.allow_anonymous = body.allow_anonymous.unwrap_or(group.allow_anonymous);
 
     let result = sqlx::query(
-        "INSERT INTO group_members (id, group_id, user_id, role, joined_at) VALUES (?, ?, ?, 'member', ?)"
+        r#"
+        UPDATE groups
+        SET name = ?, description = ?, cover_image = ?, is_private = ?, category = ?,
+            posting_policy = ?, require_post_approval = ?, allow_anonymous = ?
+        WHERE id = ?
+        "#
     )
-    .bind(&member_id)
+    .bind(&group.name)
+    .bind(&group.description)
+    .bind(&group.cover_image)
+    .bind(group.is_private)
+    .bind(&group.category)
+    .bind(&group.posting_policy)
+    .bind(group.require_post_approval)
+    .bind(group.allow_anonymous)
     .bind(&group_id)
-    .bind(&current_user.id)
-    .bind(&now)
     .execute(&state.db)
     .await;
 
+    if let Err(e) = result {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to update group: {}", e)
+        }));
+    }
+
+    if let Some(ref tags) = body.tags {
+        set_group_tags(&state, &group_id, tags).await;
+    }
+
+    // Opening up a private group lets everyone who was waiting in
+    if was_private && !group.is_private {
+        let pending: Vec<String> = sqlx::query_scalar("SELECT user_id FROM group_join_requests WHERE group_id = ?")
+            .bind(&group_id)
+            .fetch_all(&state.db)
+            .await
+            .unwrap_or_default();
+
+        for user_id in pending {
+            let _ = add_member(&state, &group_id, &user_id).await;
+        }
+    }
+
+    let group_response = build_group_response(&state, &group, &current_user.id).await;
+    HttpResponse::Ok().json(group_response)
+}
+
+pub async fn delete_group(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let group_id = path.into_inner();
+
+    let group = match sqlx::query_as::<_, Group>("SELECT * FROM groups WHERE id = ?")
+        .bind(&group_id)
+        .fetch_optional(&state.db)
+        .await
+    {
+        Ok(Some(group)) => group,
+        Ok(None) => return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Group not found"
+        })),
+        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        })),
+    };
+
+    if !group_allows(GroupAction::Administer, &group, load_membership(&state, &group_id, &current_user.id).await) {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Only group admins can delete the group"
+        }));
+    }
+
+    // Children before parents, all or nothing
+    let statements = [
+        "DELETE FROM likes WHERE post_id IN (SELECT id FROM posts WHERE group_id = ?)",
+        "DELETE FROM mentions WHERE post_id IN (SELECT id FROM posts WHERE group_id = ?)",
+        "DELETE FROM post_hashtags WHERE post_id IN (SELECT id FROM posts WHERE group_id = ?)",
+        "DELETE FROM poll_votes WHERE post_id IN (SELECT id FROM posts WHERE group_id = ?)",
+        "DELETE FROM poll_options WHERE post_id IN (SELECT id FROM posts WHERE group_id = ?)",
+        "DELETE FROM polls WHERE post_id IN (SELECT id FROM posts WHERE group_id = ?)",
+        "DELETE FROM anonymous_aliases WHERE post_id IN (SELECT id FROM posts WHERE group_id = ?)",
+        "DELETE FROM comments WHERE post_id IN (SELECT id FROM posts WHERE group_id = ?)",
+        "DELETE FROM posts WHERE group_id = ?",
+        "DELETE FROM group_members WHERE group_id = ?",
+        "DELETE FROM group_join_requests WHERE group_id = ?",
+        "DELETE FROM group_invite_codes WHERE group_id = ?",
+        "DELETE FROM group_invitations WHERE group_id = ?",
+        "DELETE FROM group_bans WHERE group_id = ?",
+        "DELETE FROM group_tags WHERE group_id = ?",
+        "DELETE FROM mutes WHERE target_type = 'group' AND target_id = ?",
+        "DELETE FROM groups WHERE id = ?",
+    ];
+
+    let result: Result<(), sqlx::Error> = async {
+        let mut tx = state.db.begin().await?;
+
+        for statement in statements {
+            sqlx::query(statement)
+                .bind(&group_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await
+    }
+    .await;
+
     match result {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Group deleted"
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to delete group: {}", e)
+        })),
+    }
+}
+
+pub async fn join_group(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let group_id = path.into_inner();
+
+    // Check if group exists
+    let group = match sqlx::query_as::<_, Group>("SELECT * FROM groups WHERE id = ?")
+        .bind(&group_id)
+        .fetch_optional(&state.db)
+        .await
+    {
+        Ok(Some(group)) => group,
+        Ok(None) => return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Group not found"
+        })),
+        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        })),
+    };
+
+    // Check if already a member
+    if get_member_role(&state, &group_id, &current_user.id).await.is_some() {
+        return HttpResponse::Conflict().json(serde_json::json!({
+            "error": "Already a member of this group"
+        }));
+    }
+
+    if is_banned(&state, &group_id, &current_user.id).await {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "You are banned from this group"
+        }));
+    }
+
+    // Private groups need approval unless the user was invited
+    if group.is_private {
+        let invited = sqlx::query_scalar::<_, i32>(
+            "SELECT COUNT(*) FROM group_invitations WHERE group_id = ? AND user_id = ?"
+        )
+        .bind(&group_id)
+        .bind(&current_user.id)
+        .fetch_one(&state.db)
+        .await
+        .unwrap_or(0) > 0;
+
+        if !invited {
+            return request_to_join(&state, &group_id, &current_user.id).await;
+        }
+    }
+
+    match add_member(&state, &group_id, &current_user.id).await {
         Ok(_) => HttpResponse::Ok().json(serde_json::json!({
             "message": "Joined group successfully"
         })),
@@ -222,6 +534,29 @@ pub async fn leave_group(
 
     let group_id = path.into_inner();
 
+    let role = match get_member_role(&state, &group_id, &current_user.id).await {
+        Some(role) => role,
+        None => return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Not a member of this group"
+        })),
+    };
+
+    if role == "admin" {
+        let admins_count: i32 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM group_members WHERE group_id = ? AND role = 'admin'"
+        )
+        .bind(&group_id)
+        .fetch_one(&state.db)
+        .await
+        .unwrap_or(0);
+
+        if admins_count <= 1 {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "You are the last admin; transfer ownership before leaving"
+            }));
+        }
+    }
+
     let result = sqlx::query(
         "DELETE FROM group_members WHERE group_id = ? AND user_id = ?"
     )
@@ -231,16 +566,24 @@ pub async fn leave_group(
     .await;
 
     match result {
-        Ok(r) => {
-            if r.rows_affected() > 0 {
-                HttpResponse::Ok().json(serde_json::json!({
-                    "message": "Left group successfully"
-                }))
-            } else {
-                HttpResponse::NotFound().json(serde_json::json!({
-                    "error": "Not a member of this group"
-                }))
-            }
+        Ok(_) => {
+            // An owner leaving hands the group to the longest-serving remaining admin
+            let _ = sqlx::query(
+                r#"
+                UPDATE groups SET creator_id = (
+                    SELECT user_id FROM group_members WHERE group_id = ?1 AND role = 'admin' ORDER BY joined_at ASC LIMIT 1
+                )
+                WHERE id = ?1 AND creator_id = ?2
+                "#
+            )
+            .bind(&group_id)
+            .bind(&current_user.id)
+            .execute(&state.db)
+            .await;
+
+            HttpResponse::Ok().json(serde_json::json!({
+                "message": "Left group successfully"
+            }))
         }
         Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
             "error": format!("Failed to leave group: {}", e)
@@ -248,6 +591,24 @@ pub async fn leave_group(
     }
 }
 
+/// Shows the group's posts in the member's home feed again.
+pub async fn show_in_feed(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    set_show_in_feed(req, state, path, true, "Group posts will show in your feed").await
+}
+
+/// Keeps the group's posts out of the member's home feed.
+pub async fn hide_from_feed(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    set_show_in_feed(req, state, path, false, "Group posts will no longer show in your feed").await
+}
+
 pub async fn get_group_posts(
     req: HttpRequest,
     state: web::Data<AppState>,
@@ -260,28 +621,48 @@ pub async fn get_group_posts(
 
     let group_id = path.into_inner();
 
-    // Check if user is a member
-    let is_member = sqlx::query_as::<_, GroupMember>(
-        "SELECT * FROM group_members WHERE group_id = ? AND user_id = ?"
-    )
-    .bind(&group_id)
-    .bind(&current_user.id)
-    .fetch_optional(&state.db)
-    .await
-    .ok()
-    .flatten()
-    .is_some();
+    let group = match sqlx::query_as::<_, Group>("SELECT * FROM groups WHERE id = ?")
+        .bind(&group_id)
+        .fetch_optional(&state.db)
+        .await
+    {
+        Ok(Some(group)) => group,
+        Ok(None) => return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Group not found"
+        })),
+        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        })),
+    };
 
-    if !is_member {
+    let membership = load_membership(&state, &group_id, &current_user.id).await;
+
+    if !group_allows(GroupAction::ViewPosts, &group, membership) {
         return HttpResponse::Forbidden().json(serde_json::json!({
             "error": "You must be a member to view group posts"
         }));
     }
 
+    // Same rule as PostAction::View: named posts from users on either side of a
+    // block are hidden from everyone but moderators
     let posts = sqlx::query_as::<_, Post>(
-        "SELECT * FROM posts WHERE group_id = ? ORDER BY created_at DESC LIMIT 50"
+        r#"
+        SELECT * FROM posts
+        WHERE group_id = ? AND status = 'published' AND (
+            ? OR is_anonymous = 1 OR user_id = ? OR (
+                user_id NOT IN (SELECT blocked_id FROM blocks WHERE blocker_id = ?)
+                AND user_id NOT IN (SELECT blocker_id FROM blocks WHERE blocked_id = ?)
+            )
+        )
+        ORDER BY is_pinned DESC, created_at DESC
+        LIMIT 50
+        "#
     )
     .bind(&group_id)
+    .bind(membership.is_moderator())
+    .bind(&current_user.id)
+    .bind(&current_user.id)
+    .bind(&current_user.id)
     .fetch_all(&state.db)
     .await;
 
@@ -321,21 +702,29 @@ pub async fn create_group_post(
         }));
     }
 
-    // Check if user is a member
-    let is_member = sqlx::query_as::<_, GroupMember>(
-        "SELECT * FROM group_members WHERE group_id = ? AND user_id = ?"
-    )
-    .bind(&group_id)
-    .bind(&current_user.id)
-    .fetch_optional(&state.db)
-    .await
-    .ok()
-    .flatten()
-    .is_some();
+    let group = match sqlx::query_as::<_, Group>("SELECT * FROM groups WHERE id = ?")
+        .bind(&group_id)
+        .fetch_optional(&state.db)
+        .await
+    {
+        Ok(Some(group)) => group,
+        Ok(None) => return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Group not found"
+        })),
+        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        })),
+    };
+
+    let membership = load_membership(&state, &group_id, &current_user.id).await;
 
-    if !is_member {
+    if !group_allows(GroupAction::Post, &group, membership) {
         return HttpResponse::Forbidden().json(serde_json::json!({
-            "error": "You must be a member to post in this group"
+            "error": if membership == Membership::None {
+                "You must be a member to post in this group"
+            } else {
+                "Only admins can post in this group"
+            }
         }));
     }
 
@@ -343,53 +732,718 @@ pub async fn create_group_post(
     let now = Utc::now().to_rfc3339();
     let is_anonymous = body.is_anonymous.unwrap_or(false);
 
-    let result = sqlx::query(
-        "INSERT INTO posts (id, user_id, content, visibility, group_id, is_anonymous, created_at, updated_at) VALUES (?, ?, ?, 'group', ?, ?, ?, ?)"
-    )
-    .bind(&post_id)
-    .bind(&current_user.id)
-    .bind(&body.content)
-    .bind(&group_id)
-    .bind(is_anonymous)
-    .bind(&now)
-    .bind(&now)
-    .execute(&state.db)
-    .await;
+    if is_anonymous && !group_allows(GroupAction::PostAnonymously, &group, membership) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Anonymous posting is disabled in this group"
+        }));
+    }
 
-    match result {
-        Ok(_) => {
-            let post = Post {
-                id: post_id,
-                user_id: current_user.id.clone(),
-                content: body.content.clone(),
-                visibility: "group".to_string(),
-                group_id: Some(group_id),
-                is_anonymous,
-                created_at: now.clone(),
-                updated_at: now,
-            };
+    // Drafts and scheduled posts go through the approval queue when they're released
+    let (status, publish_at) = match parse_schedule(body.draft.unwrap_or(false), body.publish_at.as_deref()) {
+        Ok(schedule) => schedule,
+        Err(message) => return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": message
+        })),
+    };
+    let releasing = status == "published";
+
+    // Moderators skip the approval queue
+    let needs_approval = releasing && group.require_post_approval && !group_allows(GroupAction::Moderate, &group, membership);
+    let status = if needs_approval { "pending" } else { status };
+
+    if let Some(Err(message)) = body.poll.as_ref().map(validate_poll) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": message
+        }));
+    }
+
+    let link_url = first_url(&body.content);
+
+    let result = async {
+        let mut tx = state.db.begin().await?;
+
+        sqlx::query(
+            "INSERT INTO posts (id, user_id, content, visibility, group_id, is_anonymous, created_at, updated_at, status, publish_at, link_url) VALUES (?, ?, ?, 'group', ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&post_id)
+        .bind(&current_user.id)
+        .bind(&body.content)
+        .bind(&group_id)
+        .bind(is_anonymous)
+        .bind(&now)
+        .bind(&now)
+        .bind(status)
+        .bind(&publish_at)
+        .bind(&link_url)
+        .execute(&mut *tx)
+        .await?;
+
+        if let Some(ref poll) = body.poll {
+            insert_poll(&mut tx, &post_id, poll).await?;
+        }
+
+        // Assigned with the post so reading the thread never has to
+        if is_anonymous {
+            thread_alias(&mut tx, &post_id, &current_user.id).await?;
+        }
+
+        tx.commit().await
+    }
+    .await;
+
+    match result {
+        Ok(_) => {
+            let post = Post {
+                id: post_id,
+                user_id: current_user.id.clone(),
+                content: body.content.clone(),
+                visibility: "group".to_string(),
+                group_id: Some(group_id.clone()),
+                is_anonymous,
+                created_at: now.clone(),
+                updated_at: now,
+                status: status.to_string(),
+                is_pinned: false,
+                comments_locked: false,
+                publish_at,
+                repost_of_id: None,
+                is_plain_repost: false,
+                link_url,
+            };
+
+            let notification = Notification {
+                kind: if needs_approval { "group_post_pending" } else { "group_post" },
+                actor_id: current_user.id.clone(),
+                post_id: Some(post.id.clone()),
+                group_id: Some(group_id.clone()),
+                is_anonymous,
+            };
+            if needs_approval {
+                notify_group_moderators(&state, &group_id, &notification).await;
+            } else if releasing {
+                notify_group_members(&state, &group_id, &notification).await;
+            }
+
+            let _ = sync_post_tags(&state, &post).await;
+            if post.status == "published" {
+                notify_post_mentions(&state, &post).await;
+            }
+            if let Some(ref url) = post.link_url {
+                queue_link_preview(&state, url).await;
+            }
             
             let post_response = build_post_response(&state, &post, &current_user.id).await;
             HttpResponse::Created().json(post_response)
         }
         Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": format!("Failed to create post: {}", e)
+            "error": format!("Failed to create post: {}", e)
+        })),
+    }
+}
+
+pub async fn get_join_requests(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let group_id = path.into_inner();
+
+    if !is_group_moderator(&state, &group_id, &current_user.id).await {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Only group admins and moderators can view join requests"
+        }));
+    }
+
+    let requests = sqlx::query_as::<_, GroupJoinRequest>(
+        "SELECT * FROM group_join_requests WHERE group_id = ? ORDER BY created_at ASC"
+    )
+    .bind(&group_id)
+    .fetch_all(&state.db)
+    .await;
+
+    match requests {
+        Ok(requests) => {
+            let mut requests_with_users: Vec<GroupJoinRequestWithUser> = Vec::new();
+
+            for request in requests {
+                if let Ok(Some(user)) = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+                    .bind(&request.user_id)
+                    .fetch_optional(&state.db)
+                    .await
+                {
+                    requests_with_users.push(GroupJoinRequestWithUser {
+                        request_id: request.id,
+                        user: user.into(),
+                        created_at: request.created_at,
+                    });
+                }
+            }
+
+            HttpResponse::Ok().json(requests_with_users)
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to get join requests: {}", e)
+        })),
+    }
+}
+
+pub async fn approve_join_request(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let (group_id, user_id) = path.into_inner();
+
+    if !is_group_moderator(&state, &group_id, &current_user.id).await {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Only group admins and moderators can approve join requests"
+        }));
+    }
+
+    let request = sqlx::query_as::<_, GroupJoinRequest>(
+        "SELECT * FROM group_join_requests WHERE group_id = ? AND user_id = ?"
+    )
+    .bind(&group_id)
+    .bind(&user_id)
+    .fetch_optional(&state.db)
+    .await;
+
+    if let Ok(None) = request {
+        return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Join request not found"
+        }));
+    }
+
+    if is_banned(&state, &group_id, &user_id).await {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "User is banned from this group"
+        }));
+    }
+
+    match add_member(&state, &group_id, &user_id).await {
+        Ok(_) => {
+            notify(&state, &user_id, &Notification {
+                kind: "group_join_approved",
+                actor_id: current_user.id.clone(),
+                post_id: None,
+                group_id: Some(group_id.clone()),
+                is_anonymous: false,
+            }).await;
+
+            HttpResponse::Ok().json(serde_json::json!({
+                "message": "Join request approved"
+            }))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to approve join request: {}", e)
+        })),
+    }
+}
+
+pub async fn deny_join_request(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let (group_id, user_id) = path.into_inner();
+
+    if !is_group_moderator(&state, &group_id, &current_user.id).await {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Only group admins and moderators can deny join requests"
+        }));
+    }
+
+    let result = sqlx::query("DELETE FROM group_join_requests WHERE group_id = ? AND user_id = ?")
+        .bind(&group_id)
+        .bind(&user_id)
+        .execute(&state.db)
+        .await;
+
+    match result {
+        Ok(r) => {
+            if r.rows_affected() > 0 {
+                HttpResponse::Ok().json(serde_json::json!({
+                    "message": "Join request denied"
+                }))
+            } else {
+                HttpResponse::NotFound().json(serde_json::json!({
+                    "error": "Join request not found"
+                }))
+            }
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to deny join request: {}", e)
+        })),
+    }
+}
+
+pub async fn get_invite_codes(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let group_id = path.into_inner();
+
+    if !is_group_moderator(&state, &group_id, &current_user.id).await {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Only group admins and moderators can view invite codes"
+        }));
+    }
+
+    let codes = sqlx::query_as::<_, GroupInviteCode>(
+        "SELECT * FROM group_invite_codes WHERE group_id = ? ORDER BY created_at DESC"
+    )
+    .bind(&group_id)
+    .fetch_all(&state.db)
+    .await;
+
+    match codes {
+        Ok(codes) => HttpResponse::Ok().json(codes),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to get invite codes: {}", e)
+        })),
+    }
+}
+
+pub async fn create_invite_code(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: Option<web::Json<CreateInviteCodeRequest>>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let group_id = path.into_inner();
+
+    if !is_group_moderator(&state, &group_id, &current_user.id).await {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Only group admins and moderators can create invite codes"
+        }));
+    }
+
+    let (expires_in_hours, max_uses) = body
+        .map(|b| (b.expires_in_hours, b.max_uses))
+        .unwrap_or((None, None));
+
+    if expires_in_hours.is_some_and(|h| !(1..=MAX_INVITE_CODE_HOURS).contains(&h)) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("expires_in_hours must be between 1 and {}", MAX_INVITE_CODE_HOURS)
+        }));
+    }
+
+    if max_uses.is_some_and(|m| m <= 0) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "max_uses must be positive"
+        }));
+    }
+
+    let now = Utc::now();
+    let invite_code = GroupInviteCode {
+        code: Uuid::new_v4().simple().to_string(),
+        group_id,
+        created_by: current_user.id.clone(),
+        expires_at: expires_in_hours.map(|h| (now + Duration::hours(h)).to_rfc3339()),
+        max_uses,
+        uses: 0,
+        created_at: now.to_rfc3339(),
+    };
+
+    let result = sqlx::query(
+        "INSERT INTO group_invite_codes (code, group_id, created_by, expires_at, max_uses, uses, created_at) VALUES (?, ?, ?, ?, ?, 0, ?)"
+    )
+    .bind(&invite_code.code)
+    .bind(&invite_code.group_id)
+    .bind(&invite_code.created_by)
+    .bind(&invite_code.expires_at)
+    .bind(invite_code.max_uses)
+    .bind(&invite_code.created_at)
+    .execute(&state.db)
+    .await;
+
+    match result {
+        Ok(_) => HttpResponse::Created().json(invite_code),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to create invite code: {}", e)
+        })),
+    }
+}
+
+pub async fn revoke_invite_code(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let (group_id, code) = path.into_inner();
+
+    if !is_group_moderator(&state, &group_id, &current_user.id).await {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Only group admins and moderators can revoke invite codes"
+        }));
+    }
+
+    let result = sqlx::query("DELETE FROM group_invite_codes WHERE code = ? AND group_id = ?")
+        .bind(&code)
+        .bind(&group_id)
+        .execute(&state.db)
+        .await;
+
+    match result {
+        Ok(r) => {
+            if r.rows_affected() > 0 {
+                HttpResponse::Ok().json(serde_json::json!({
+                    "message": "Invite code revoked"
+                }))
+            } else {
+                HttpResponse::NotFound().json(serde_json::json!({
+                    "error": "Invite code not found"
+                }))
+            }
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to revoke invite code: {}", e)
+        })),
+    }
+}
+
+pub async fn join_with_invite_code(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let code = path.into_inner();
+
+    let invite_code = match sqlx::query_as::<_, GroupInviteCode>("SELECT * FROM group_invite_codes WHERE code = ?")
+        .bind(&code)
+        .fetch_optional(&state.db)
+        .await
+    {
+        Ok(Some(invite_code)) => invite_code,
+        Ok(None) => return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Invite code not found"
+        })),
+        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        })),
+    };
+
+    if get_member_role(&state, &invite_code.group_id, &current_user.id).await.is_some() {
+        return HttpResponse::Conflict().json(serde_json::json!({
+            "error": "Already a member of this group"
+        }));
+    }
+
+    if is_banned(&state, &invite_code.group_id, &current_user.id).await {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "You are banned from this group"
+        }));
+    }
+
+    // Claim a use atomically so concurrent redemptions can't exceed the limit
+    let claimed = sqlx::query(
+        r#"
+        UPDATE group_invite_codes SET uses = uses + 1
+        WHERE code = ?
+          AND (max_uses IS NULL OR uses < max_uses)
+          AND (expires_at IS NULL OR expires_at > ?)
+        "#
+    )
+    .bind(&code)
+    .bind(Utc::now().to_rfc3339())
+    .execute(&state.db)
+    .await;
+
+    match claimed {
+        Ok(r) if r.rows_affected() == 0 => {
+            return HttpResponse::Gone().json(serde_json::json!({
+                "error": "Invite code has expired or reached its use limit"
+            }));
+        }
+        Ok(_) => {}
+        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to redeem invite code: {}", e)
+        })),
+    }
+
+    if let Err(e) = add_member(&state, &invite_code.group_id, &current_user.id).await {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to join group: {}", e)
+        }));
+    }
+
+    match sqlx::query_as::<_, Group>("SELECT * FROM groups WHERE id = ?")
+        .bind(&invite_code.group_id)
+        .fetch_optional(&state.db)
+        .await
+    {
+        Ok(Some(group)) => {
+            let group_response = build_group_response(&state, &group, &current_user.id).await;
+            HttpResponse::Ok().json(group_response)
+        }
+        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Group not found"
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        })),
+    }
+}
+
+pub async fn invite_member(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let (group_id, user_id) = path.into_inner();
+
+    let group = match sqlx::query_as::<_, Group>("SELECT * FROM groups WHERE id = ?")
+        .bind(&group_id)
+        .fetch_optional(&state.db)
+        .await
+    {
+        Ok(Some(group)) => group,
+        Ok(None) => return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Group not found"
+        })),
+        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        })),
+    };
+
+    if !group_allows(GroupAction::Invite, &group, load_membership(&state, &group_id, &current_user.id).await) {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "You cannot invite members to this group"
+        }));
+    }
+
+    let user_exists = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(&user_id)
+        .fetch_optional(&state.db)
+        .await;
+
+    if let Ok(None) = user_exists {
+        return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "User not found"
+        }));
+    }
+
+    // Anonymously banned authors are still members, so membership is checked first
+    if get_member_role(&state, &group_id, &user_id).await.is_some() {
+        return HttpResponse::Conflict().json(serde_json::json!({
+            "error": "User is already a member of this group"
+        }));
+    }
+
+    if is_blocked(&state, &current_user.id, &user_id).await || is_banned(&state, &group_id, &user_id).await {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Cannot invite this user"
+        }));
+    }
+
+    let invitation_id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+
+    let result = sqlx::query(
+        "INSERT INTO group_invitations (id, group_id, user_id, invited_by, created_at) VALUES (?, ?, ?, ?, ?) ON CONFLICT(group_id, user_id) DO NOTHING"
+    )
+    .bind(&invitation_id)
+    .bind(&group_id)
+    .bind(&user_id)
+    .bind(&current_user.id)
+    .bind(&now)
+    .execute(&state.db)
+    .await;
+
+    match result {
+        Ok(r) if r.rows_affected() == 0 => HttpResponse::Conflict().json(serde_json::json!({
+            "error": "User has already been invited"
+        })),
+        Ok(_) => {
+            notify(&state, &user_id, &Notification {
+                kind: "group_invitation",
+                actor_id: current_user.id.clone(),
+                post_id: None,
+                group_id: Some(group_id.clone()),
+                is_anonymous: false,
+            }).await;
+
+            HttpResponse::Created().json(serde_json::json!({
+                "message": "Invitation sent",
+                "invitation_id": invitation_id
+            }))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to invite user: {}", e)
+        })),
+    }
+}
+
+pub async fn get_my_invitations(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let invitations = sqlx::query_as::<_, GroupInvitation>(
+        "SELECT * FROM group_invitations WHERE user_id = ? ORDER BY created_at DESC"
+    )
+    .bind(&current_user.id)
+    .fetch_all(&state.db)
+    .await;
+
+    match invitations {
+        Ok(invitations) => {
+            let mut responses: Vec<GroupInvitationResponse> = Vec::new();
+
+            for invitation in invitations {
+                let group = sqlx::query_as::<_, Group>("SELECT * FROM groups WHERE id = ?")
+                    .bind(&invitation.group_id)
+                    .fetch_optional(&state.db)
+                    .await;
+                let inviter = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+                    .bind(&invitation.invited_by)
+                    .fetch_optional(&state.db)
+                    .await;
+
+                if let (Ok(Some(group)), Ok(Some(inviter))) = (group, inviter) {
+                    responses.push(GroupInvitationResponse {
+                        invitation_id: invitation.id,
+                        group: build_group_response(&state, &group, &current_user.id).await,
+                        invited_by: inviter.into(),
+                        created_at: invitation.created_at,
+                    });
+                }
+            }
+
+            HttpResponse::Ok().json(responses)
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to get invitations: {}", e)
+        })),
+    }
+}
+
+pub async fn decline_invitation(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let group_id = path.into_inner();
+
+    let result = sqlx::query("DELETE FROM group_invitations WHERE group_id = ? AND user_id = ?")
+        .bind(&group_id)
+        .bind(&current_user.id)
+        .execute(&state.db)
+        .await;
+
+    match result {
+        Ok(r) => {
+            if r.rows_affected() > 0 {
+                HttpResponse::Ok().json(serde_json::json!({
+                    "message": "Invitation declined"
+                }))
+            } else {
+                HttpResponse::NotFound().json(serde_json::json!({
+                    "error": "Invitation not found"
+                }))
+            }
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to decline invitation: {}", e)
         })),
     }
 }
 
 // Helper functions
-async fn build_group_response(state: &web::Data<AppState>, group: &Group, current_user_id: &str) -> GroupResponse {
+
+async fn set_show_in_feed(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    value: bool,
+    message: &str,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let result = sqlx::query("UPDATE group_members SET show_in_feed = ? WHERE group_id = ? AND user_id = ?")
+        .bind(value)
+        .bind(path.into_inner())
+        .bind(&current_user.id)
+        .execute(&state.db)
+        .await;
+
+    match result {
+        Ok(r) if r.rows_affected() > 0 => HttpResponse::Ok().json(serde_json::json!({
+            "message": message
+        })),
+        Ok(_) => HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Not a member of this group"
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to update feed preference: {}", e)
+        })),
+    }
+}
+
+pub async fn build_group_response(state: &web::Data<AppState>, group: &Group, current_user_id: &str) -> GroupResponse {
     let creator = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
         .bind(&group.creator_id)
         .fetch_optional(&state.db)
         .await
         .ok()
         .flatten()
-        .map(|u| UserResponse::from(u))
+        .map(UserResponse::from)
         .unwrap_or_else(|| UserResponse {
             id: group.creator_id.clone(),
-            email: String::new(),
+            email: None,
             username: "Unknown".to_string(),
             display_name: "Unknown User".to_string(),
             avatar_url: None,
@@ -402,73 +1456,400 @@ async fn build_group_response(state: &web::Data<AppState>, group: &Group, curren
         .await
         .unwrap_or(0);
 
-    let is_member = sqlx::query_as::<_, GroupMember>(
-        "SELECT * FROM group_members WHERE group_id = ? AND user_id = ?"
+    let membership = load_membership(state, &group.id, current_user_id).await;
+    let is_member = membership != Membership::None;
+
+    let tags: Vec<String> = sqlx::query_scalar("SELECT tag FROM group_tags WHERE group_id = ? ORDER BY tag")
+        .bind(&group.id)
+        .fetch_all(&state.db)
+        .await
+        .unwrap_or_default();
+
+    let has_pending_request = !is_member && sqlx::query_scalar::<_, i32>(
+        "SELECT COUNT(*) FROM group_join_requests WHERE group_id = ? AND user_id = ?"
     )
     .bind(&group.id)
     .bind(current_user_id)
-    .fetch_optional(&state.db)
+    .fetch_one(&state.db)
     .await
-    .ok()
-    .flatten()
-    .is_some();
+    .unwrap_or(0) > 0;
+
+    let show_in_feed = is_member && sqlx::query_scalar::<_, bool>(
+        "SELECT show_in_feed FROM group_members WHERE group_id = ? AND user_id = ?"
+    )
+    .bind(&group.id)
+    .bind(current_user_id)
+    .fetch_one(&state.db)
+    .await
+    .unwrap_or(true);
+
+    // Outsiders only see a private group's name, creator and size
+    let hide_content = !group_allows(GroupAction::ViewDetails, group, membership);
 
     GroupResponse {
         id: group.id.clone(),
         name: group.name.clone(),
-        description: group.description.clone(),
-        cover_image: group.cover_image.clone(),
+        description: if hide_content { None } else { group.description.clone() },
+        cover_image: if hide_content { None } else { group.cover_image.clone() },
         creator,
         is_private: group.is_private,
+        category: group.category.clone(),
+        posting_policy: group.posting_policy.clone(),
+        require_post_approval: group.require_post_approval,
+        allow_anonymous: group.allow_anonymous,
+        tags: if hide_content { Vec::new() } else { tags },
         members_count,
         is_member,
+        show_in_feed,
+        has_pending_request,
         created_at: group.created_at.clone(),
     }
 }
 
-async fn build_post_response(state: &web::Data<AppState>, post: &Post, current_user_id: &str) -> PostResponse {
-    let user = if post.is_anonymous {
-        None
-    } else {
-        sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
-            .bind(&post.user_id)
-            .fetch_optional(&state.db)
-            .await
-            .ok()
-            .flatten()
-            .map(|u| u.into())
-    };
+fn normalize_tag(tag: &str) -> String {
+    tag.trim().trim_start_matches('#').to_lowercase()
+}
 
-    let likes_count: i32 = sqlx::query_scalar("SELECT COUNT(*) FROM likes WHERE post_id = ?")
-        .bind(&post.id)
-        .fetch_one(&state.db)
-        .await
-        .unwrap_or(0);
+/// Replaces a group's tags with the given list, normalised and de-duplicated.
+async fn set_group_tags(state: &web::Data<AppState>, group_id: &str, tags: &[String]) {
+    let _ = sqlx::query("DELETE FROM group_tags WHERE group_id = ?")
+        .bind(group_id)
+        .execute(&state.db)
+        .await;
 
-    let comments_count: i32 = sqlx::query_scalar("SELECT COUNT(*) FROM comments WHERE post_id = ?")
-        .bind(&post.id)
-        .fetch_one(&state.db)
+    let tags = tags
+        .iter()
+        .map(|t| normalize_tag(t))
+        .filter(|t| !t.is_empty() && t.len() <= MAX_TAG_LENGTH)
+        .take(MAX_GROUP_TAGS);
+
+    for tag in tags {
+        let _ = sqlx::query("INSERT OR IGNORE INTO group_tags (group_id, tag) VALUES (?, ?)")
+            .bind(group_id)
+            .bind(&tag)
+            .execute(&state.db)
+            .await;
+    }
+}
+
+/// Returns the user's role in the group, or None if they aren't a member.
+pub async fn get_member_role(state: &web::Data<AppState>, group_id: &str, user_id: &str) -> Option<String> {
+    sqlx::query_scalar("SELECT role FROM group_members WHERE group_id = ? AND user_id = ?")
+        .bind(group_id)
+        .bind(user_id)
+        .fetch_optional(&state.db)
         .await
-        .unwrap_or(0);
+        .ok()
+        .flatten()
+}
 
-    let is_liked = sqlx::query_scalar::<_, i32>(
-        "SELECT COUNT(*) FROM likes WHERE post_id = ? AND user_id = ?"
+/// Adds the user as a plain member and clears any pending request or invitation.
+async fn add_member(state: &web::Data<AppState>, group_id: &str, user_id: &str) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO group_members (id, group_id, user_id, role, joined_at) VALUES (?, ?, ?, 'member', ?)"
     )
-    .bind(&post.id)
-    .bind(current_user_id)
-    .fetch_one(&state.db)
-    .await
-    .unwrap_or(0) > 0;
+    .bind(Uuid::new_v4().to_string())
+    .bind(group_id)
+    .bind(user_id)
+    .bind(Utc::now().to_rfc3339())
+    .execute(&state.db)
+    .await?;
+
+    sqlx::query("DELETE FROM group_join_requests WHERE group_id = ? AND user_id = ?")
+        .bind(group_id)
+        .bind(user_id)
+        .execute(&state.db)
+        .await?;
+    sqlx::query("DELETE FROM group_invitations WHERE group_id = ? AND user_id = ?")
+        .bind(group_id)
+        .bind(user_id)
+        .execute(&state.db)
+        .await?;
+
+    Ok(())
+}
+
+async fn request_to_join(state: &web::Data<AppState>, group_id: &str, user_id: &str) -> HttpResponse {
+    let result = sqlx::query(
+        "INSERT INTO group_join_requests (id, group_id, user_id, created_at) VALUES (?, ?, ?, ?) ON CONFLICT(group_id, user_id) DO NOTHING"
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(group_id)
+    .bind(user_id)
+    .bind(Utc::now().to_rfc3339())
+    .execute(&state.db)
+    .await;
+
+    match result {
+        Ok(r) if r.rows_affected() == 0 => HttpResponse::Conflict().json(serde_json::json!({
+            "error": "Join request already pending"
+        })),
+        Ok(_) => {
+            notify_group_moderators(state, group_id, &Notification {
+                kind: "group_join_request",
+                actor_id: user_id.to_string(),
+                post_id: None,
+                group_id: Some(group_id.to_string()),
+                is_anonymous: false,
+            }).await;
+
+            HttpResponse::Accepted().json(serde_json::json!({
+                "message": "Join request sent",
+                "status": "pending"
+            }))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to request to join group: {}", e)
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::posts::{add_comment, get_comments};
+    use crate::test_support::{self, auth_request, count, create_user, json, post_in_group, response_json, test_state};
+
+    #[actix_rt::test]
+    async fn deleting_a_group_removes_its_posts_and_everything_attached() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+        let group_id = test_support::create_group(&state, &alice, &[&bob]).await;
+        sqlx::query("UPDATE groups SET allow_anonymous = 1").execute(&state.db).await.unwrap();
+
+        let post = post_in_group(&state, &bob, &group_id, serde_json::json!({
+            "content": "Anyone else into #gardening, @alice?",
+            "is_anonymous": true,
+            "poll": {"options": ["Yes", "No"]}
+        }))
+        .await;
+        let response = add_comment(
+            auth_request(&alice),
+            state.clone(),
+            web::Path::from(post["id"].as_str().unwrap().to_string()),
+            json(serde_json::json!({"content": "Me", "is_anonymous": true})),
+        )
+        .await;
+        assert_eq!(response.status().as_u16(), 201);
+
+        let response = delete_group(auth_request(&alice), state.clone(), web::Path::from(group_id)).await;
+        assert_eq!(response.status().as_u16(), 200);
+        for table in ["groups", "group_members", "posts", "comments", "mentions", "post_hashtags", "polls", "poll_options", "anonymous_aliases"] {
+            assert_eq!(count(&state, &format!("SELECT COUNT(*) FROM {}", table)).await, 0, "{}", table);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn admins_edit_settings_and_opening_a_group_admits_pending_requests() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+        let carol = create_user(&state, "carol").await;
+        let group_id = test_support::create_group(&state, &alice, &[&bob]).await;
+        let update = |actor: &str, body: serde_json::Value| {
+            update_group(auth_request(actor), state.clone(), web::Path::from(group_id.clone()), json(body))
+        };
+
+        assert_eq!(update(&bob, serde_json::json!({"name": "Mine now"})).await.status().as_u16(), 403);
+        assert_eq!(update(&alice, serde_json::json!({"posting_policy": "moderators"})).await.status().as_u16(), 400);
+
+        let response = update(&alice, serde_json::json!({
+            "description": "About plants",
+            "category": " Hobbies ",
+            "posting_policy": "admins",
+            "is_private": true
+        }))
+        .await;
+        assert_eq!(response.status().as_u16(), 200);
+        let group = response_json(response).await;
+        assert_eq!(group["category"], "hobbies");
+        assert_eq!(group["is_private"], true);
+
+        let response = create_group_post(auth_request(&bob), state.clone(), web::Path::from(group_id.clone()), json(serde_json::json!({"content": "Hi"}))).await;
+        assert_eq!(response.status().as_u16(), 403);
+
+        join_group(auth_request(&carol), state.clone(), web::Path::from(group_id.clone())).await;
+        assert_eq!(get_member_role(&state, &group_id, &carol).await, None);
+
+        // Empty strings clear optional fields
+        let response = update(&alice, serde_json::json!({"description": "", "is_private": false})).await;
+        assert!(response_json(response).await["description"].is_null());
+        assert_eq!(get_member_role(&state, &group_id, &carol).await.as_deref(), Some("member"));
+    }
+
+    async fn discover(state: &web::Data<AppState>, viewer: &str, query: &str) -> Vec<String> {
+        let response = discover_groups(auth_request(viewer), state.clone(), web::Query::from_query(query).unwrap()).await;
+        assert_eq!(response.status().as_u16(), 200);
+        response_json(response)
+            .await
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|g| g["name"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[actix_rt::test]
+    async fn the_directory_lists_public_groups_by_search_category_and_tag() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+
+        for body in [
+            serde_json::json!({"name": "Garden Club", "category": "Hobbies", "tags": ["#Gardening", "plants"]}),
+            serde_json::json!({"name": "Chess Club", "category": " games ", "tags": ["chess"]}),
+            serde_json::json!({"name": "Secret Garden", "is_private": true, "tags": ["gardening"]}),
+        ] {
+            let response = create_group(auth_request(&alice), state.clone(), json(body)).await;
+            assert_eq!(response.status().as_u16(), 201);
+        }
+        let chess_id: String = sqlx::query_scalar("SELECT id FROM groups WHERE name = 'Chess Club'").fetch_one(&state.db).await.unwrap();
+        join_group(auth_request(&bob), state.clone(), web::Path::from(chess_id)).await;
+
+        assert_eq!(discover(&state, &bob, "").await, vec!["Chess Club", "Garden Club"]);
+        assert_eq!(discover(&state, &bob, "q=garden").await, vec!["Garden Club"]);
+        assert_eq!(discover(&state, &bob, "category=Games").await, vec!["Chess Club"]);
+        assert_eq!(discover(&state, &bob, "tag=%23gardening").await, vec!["Garden Club"]);
+        assert_eq!(discover(&state, &bob, "sort=newest&limit=1&page=1").await.len(), 1);
 
-    PostResponse {
-        id: post.id.clone(),
-        user,
-        content: post.content.clone(),
-        visibility: post.visibility.clone(),
-        is_anonymous: post.is_anonymous,
-        likes_count,
-        comments_count,
-        is_liked,
-        created_at: post.created_at.clone(),
+        let response = discover_groups(auth_request(&bob), state.clone(), web::Query::from_query("sort=size").unwrap()).await;
+        assert_eq!(response.status().as_u16(), 400);
+    }
+
+    async fn comment_as(state: &web::Data<AppState>, user_id: &str, post_id: &str, is_anonymous: bool) -> serde_json::Value {
+        let response = add_comment(
+            auth_request(user_id),
+            state.clone(),
+            web::Path::from(post_id.to_string()),
+            json(serde_json::json!({"content": "Agreed", "is_anonymous": is_anonymous})),
+        )
+        .await;
+        assert_eq!(response.status().as_u16(), 201);
+        response_json(response).await
+    }
+
+    #[actix_rt::test]
+    async fn anonymous_aliases_are_stable_within_a_thread_and_assigned_on_write() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+        let group_id = test_support::create_group(&state, &alice, &[&bob]).await;
+        sqlx::query("UPDATE groups SET allow_anonymous = 1").execute(&state.db).await.unwrap();
+
+        let post = post_in_group(&state, &bob, &group_id, serde_json::json!({"content": "Psst", "is_anonymous": true})).await;
+        let post_id = post["id"].as_str().unwrap().to_string();
+        let alias = post["anonymous_alias"].as_str().unwrap().to_string();
+        assert_eq!(count(&state, "SELECT COUNT(*) FROM anonymous_aliases").await, 1);
+
+        // Reading the thread doesn't assign anything
+        let response = get_group_posts(auth_request(&alice), state.clone(), web::Path::from(group_id.clone())).await;
+        assert_eq!(response_json(response).await[0]["anonymous_alias"], alias.as_str());
+        assert_eq!(count(&state, "SELECT COUNT(*) FROM anonymous_aliases").await, 1);
+
+        // The author keeps their alias in the thread, other people get their own
+        assert_eq!(comment_as(&state, &bob, &post_id, true).await["anonymous_alias"], alias.as_str());
+        let other = comment_as(&state, &alice, &post_id, true).await;
+        assert_ne!(other["anonymous_alias"], alias.as_str());
+        assert_eq!(comment_as(&state, &alice, &post_id, false).await["anonymous_alias"], serde_json::Value::Null);
+
+        let comments = response_json(get_comments(auth_request(&alice), state.clone(), web::Path::from(post_id)).await).await;
+        let aliases: Vec<&serde_json::Value> = comments.as_array().unwrap().iter().map(|c| &c["anonymous_alias"]).collect();
+        assert_eq!(aliases, vec![&serde_json::json!(alias), &other["anonymous_alias"], &serde_json::Value::Null]);
+        assert!(comments.as_array().unwrap()[..2].iter().all(|c| c["user"].is_null()));
+        assert_eq!(count(&state, "SELECT COUNT(*) FROM anonymous_aliases").await, 2);
+
+        // Even a post without an alias doesn't get one from being read
+        sqlx::query("DELETE FROM anonymous_aliases").execute(&state.db).await.unwrap();
+        get_group_posts(auth_request(&alice), state.clone(), web::Path::from(group_id)).await;
+        assert_eq!(count(&state, "SELECT COUNT(*) FROM anonymous_aliases").await, 0);
+    }
+
+    async fn create_private_group(state: &web::Data<AppState>, admin_id: &str) -> String {
+        let group_id = test_support::create_group(state, admin_id, &[]).await;
+        sqlx::query("UPDATE groups SET is_private = 1").execute(&state.db).await.unwrap();
+        group_id
+    }
+
+    async fn create_code(state: &web::Data<AppState>, user_id: &str, group_id: &str, body: serde_json::Value) -> HttpResponse {
+        create_invite_code(auth_request(user_id), state.clone(), web::Path::from(group_id.to_string()), Some(json(body))).await
+    }
+
+    async fn redeem(state: &web::Data<AppState>, user_id: &str, code: &str) -> u16 {
+        join_with_invite_code(auth_request(user_id), state.clone(), web::Path::from(code.to_string()))
+            .await
+            .status()
+            .as_u16()
+    }
+
+    #[actix_rt::test]
+    async fn private_groups_take_join_requests() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+        let carol = create_user(&state, "carol").await;
+        let group_id = create_private_group(&state, &alice).await;
+
+        for user in [&bob, &carol] {
+            let response = join_group(auth_request(user), state.clone(), web::Path::from(group_id.clone())).await;
+            assert_eq!(response.status().as_u16(), 202);
+        }
+        let response = join_group(auth_request(&bob), state.clone(), web::Path::from(group_id.clone())).await;
+        assert_eq!(response.status().as_u16(), 409);
+        assert!(get_member_role(&state, &group_id, &bob).await.is_none());
+
+        // Only moderators see and answer requests
+        let response = get_join_requests(auth_request(&bob), state.clone(), web::Path::from(group_id.clone())).await;
+        assert_eq!(response.status().as_u16(), 403);
+        let requests = response_json(get_join_requests(auth_request(&alice), state.clone(), web::Path::from(group_id.clone())).await).await;
+        assert_eq!(requests.as_array().unwrap().len(), 2);
+
+        let response = approve_join_request(auth_request(&alice), state.clone(), web::Path::from((group_id.clone(), bob.clone()))).await;
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(get_member_role(&state, &group_id, &bob).await.as_deref(), Some("member"));
+
+        let response = deny_join_request(auth_request(&alice), state.clone(), web::Path::from((group_id.clone(), carol.clone()))).await;
+        assert_eq!(response.status().as_u16(), 200);
+        assert!(get_member_role(&state, &group_id, &carol).await.is_none());
+        assert_eq!(count(&state, "SELECT COUNT(*) FROM group_join_requests").await, 0);
+    }
+
+    #[actix_rt::test]
+    async fn invite_codes_stop_at_their_limits() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+        let carol = create_user(&state, "carol").await;
+        let group_id = create_private_group(&state, &alice).await;
+
+        for body in [
+            serde_json::json!({"expires_in_hours": 0}),
+            serde_json::json!({"expires_in_hours": MAX_INVITE_CODE_HOURS + 1}),
+            serde_json::json!({"expires_in_hours": i64::MAX}),
+            serde_json::json!({"max_uses": 0}),
+        ] {
+            assert_eq!(create_code(&state, &alice, &group_id, body).await.status().as_u16(), 400);
+        }
+
+        let response = create_code(&state, &alice, &group_id, serde_json::json!({"max_uses": 1})).await;
+        assert_eq!(response.status().as_u16(), 201);
+        let code = response_json(response).await["code"].as_str().unwrap().to_string();
+
+        // Private groups are joined straight away with a code, but only as often as it allows
+        assert_eq!(redeem(&state, &bob, &code).await, 200);
+        assert_eq!(get_member_role(&state, &group_id, &bob).await.as_deref(), Some("member"));
+        assert_eq!(redeem(&state, &bob, &code).await, 409);
+        assert_eq!(redeem(&state, &carol, &code).await, 410);
+
+        let response = create_code(&state, &alice, &group_id, serde_json::json!({"expires_in_hours": 1})).await;
+        let code = response_json(response).await["code"].as_str().unwrap().to_string();
+        sqlx::query("UPDATE group_invite_codes SET expires_at = ? WHERE code = ?")
+            .bind((Utc::now() - Duration::minutes(1)).to_rfc3339())
+            .bind(&code)
+            .execute(&state.db)
+            .await
+            .unwrap();
+        assert_eq!(redeem(&state, &carol, &code).await, 410);
+        assert!(get_member_role(&state, &group_id, &carol).await.is_none());
     }
 }
@@ -0,0 +1,162 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+
+use crate::auth::require_auth;
+use crate::handlers::posts::build_post_response;
+use crate::models::{Post, PostResponse, TrendingHashtag};
+use crate::policy::{load_post_context, PostAction};
+use crate::tagging::normalize_hashtag;
+use crate::AppState;
+
+/// Trending hashtags are counted over this many past hours by default.
+const TRENDING_WINDOW_HOURS: i64 = 24;
+const MAX_TRENDING_WINDOW_HOURS: i64 = 168;
+
+/// How many of the most recent tagged posts are checked for visibility.
+const HASHTAG_CANDIDATES: i64 = 200;
+const HASHTAG_PAGE_LIMIT: usize = 50;
+
+#[derive(Deserialize)]
+pub struct TrendingQuery {
+    pub window_hours: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+/// Hashtags used by the most people over a sliding window. Only public posts from
+/// public accounts are counted, so trends never reveal anything private.
+pub async fn get_trending_hashtags(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<TrendingQuery>,
+) -> HttpResponse {
+    if let Err(e) = require_auth(&req, &state).await {
+        return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()}));
+    }
+
+    let window_hours = query.window_hours.unwrap_or(TRENDING_WINDOW_HOURS).clamp(1, MAX_TRENDING_WINDOW_HOURS);
+    let limit = query.limit.unwrap_or(10).clamp(1, 50);
+    let since = (Utc::now() - Duration::hours(window_hours)).to_rfc3339();
+
+    let trending = sqlx::query_as::<_, TrendingHashtag>(
+        r#"
+        SELECT h.tag, COUNT(DISTINCT p.id) AS posts_count, COUNT(DISTINCT p.user_id) AS authors_count
+        FROM post_hashtags h
+        INNER JOIN posts p ON p.id = h.post_id
+        WHERE p.status = 'published' AND p.visibility = 'public' AND p.group_id IS NULL
+          AND p.created_at > ?
+          AND p.user_id NOT IN (SELECT id FROM users WHERE requires_follow_approval = 1)
+        GROUP BY h.tag
+        ORDER BY authors_count DESC, posts_count DESC, h.tag
+        LIMIT ?
+        "#
+    )
+    .bind(&since)
+    .bind(limit)
+    .fetch_all(&state.db)
+    .await;
+
+    match trending {
+        Ok(trending) => HttpResponse::Ok().json(trending),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to get trending hashtags: {}", e)
+        })),
+    }
+}
+
+/// The most recent posts with a hashtag that the user is allowed to see.
+pub async fn get_hashtag_posts(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let tag = normalize_hashtag(&path.into_inner());
+
+    let posts = sqlx::query_as::<_, Post>(
+        r#"
+        SELECT p.* FROM posts p
+        INNER JOIN post_hashtags h ON h.post_id = p.id
+        WHERE h.tag = ? AND p.status = 'published'
+        ORDER BY p.created_at DESC
+        LIMIT ?
+        "#
+    )
+    .bind(&tag)
+    .bind(HASHTAG_CANDIDATES)
+    .fetch_all(&state.db)
+    .await;
+
+    match posts {
+        Ok(posts) => {
+            let mut post_responses: Vec<PostResponse> = Vec::new();
+
+            for post in posts {
+                if !load_post_context(&state, &post, &current_user.id).await.allows(PostAction::View) {
+                    continue;
+                }
+
+                post_responses.push(build_post_response(&state, &post, &current_user.id).await);
+                if post_responses.len() >= HASHTAG_PAGE_LIMIT {
+                    break;
+                }
+            }
+
+            HttpResponse::Ok().json(post_responses)
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to get hashtag posts: {}", e)
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::posts::create_post;
+    use crate::test_support::{auth_request, create_user, json, response_json, test_state};
+    use tokio::sync::mpsc;
+
+    async fn post_as(state: &web::Data<AppState>, user_id: &str, content: &str, visibility: &str) -> serde_json::Value {
+        let response = create_post(auth_request(user_id), state.clone(), json(serde_json::json!({"content": content, "visibility": visibility}))).await;
+        assert_eq!(response.status().as_u16(), 201);
+        response_json(response).await
+    }
+
+    #[actix_rt::test]
+    async fn tags_are_indexed_and_only_public_posts_trend() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+        let carol = create_user(&state, "carol").await;
+        let dave = create_user(&state, "dave").await;
+        sqlx::query("UPDATE users SET requires_follow_approval = 1 WHERE id = ?").bind(&dave).execute(&state.db).await.unwrap();
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        state.ws_connections.write().await.insert(bob.clone(), vec![sender]);
+
+        let post = post_as(&state, &alice, "Hi @bob and @nobody, see #Rust #rust #1 or mail a@b.com", "public").await;
+        assert_eq!(post["mentions"].as_array().unwrap().len(), 1);
+        assert_eq!(post["mentions"][0]["user_id"], bob.as_str());
+        let message: serde_json::Value = serde_json::from_str(&receiver.try_recv().unwrap()).unwrap();
+        assert_eq!(message["notification"]["kind"], "mentioned");
+
+        post_as(&state, &carol, "Quietly into #rust", "friends_only").await;
+        post_as(&state, &dave, "Privately into #rust", "public").await;
+
+        let response = get_hashtag_posts(auth_request(&bob), state.clone(), web::Path::from("#RUST".to_string())).await;
+        let posts = response_json(response).await;
+        assert_eq!(posts.as_array().unwrap().len(), 1);
+        assert_eq!(posts[0]["id"], post["id"]);
+
+        let response = get_trending_hashtags(auth_request(&bob), state.clone(), web::Query::from_query("").unwrap()).await;
+        let trending = response_json(response).await;
+        assert_eq!(trending.as_array().unwrap().len(), 1);
+        assert_eq!(trending[0]["tag"], "rust");
+        assert_eq!(trending[0]["authors_count"], 1);
+    }
+}
@@ -1,6 +1,17 @@
 pub mod auth;
 pub mod users;
 pub mod friends;
+pub mod friend_lists;
+pub mod follows;
+pub mod blocks;
+pub mod mutes;
 pub mod posts;
+pub mod polls;
+pub mod stories;
 pub mod groups;
+pub mod group_members;
+pub mod group_moderation;
 pub mod chat;
+pub mod search;
+pub mod hashtags;
+pub mod admin;
@@ -0,0 +1,266 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+
+use crate::auth::require_auth;
+use crate::models::{Mute, MuteRequest};
+use crate::AppState;
+
+/// Longest temporary mute; leave the duration out to mute indefinitely.
+const MAX_MUTE_DURATION_HOURS: i64 = 24 * 365;
+
+pub async fn get_mutes(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let now = Utc::now().to_rfc3339();
+
+    let mutes = sqlx::query_as::<_, Mute>(
+        "SELECT * FROM mutes WHERE user_id = ? AND (expires_at IS NULL OR expires_at > ?) ORDER BY created_at DESC"
+    )
+    .bind(&current_user.id)
+    .bind(&now)
+    .fetch_all(&state.db)
+    .await;
+
+    match mutes {
+        Ok(mutes) => HttpResponse::Ok().json(mutes),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to get mutes: {}", e)
+        })),
+    }
+}
+
+pub async fn mute_user(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: Option<web::Json<MuteRequest>>,
+) -> HttpResponse {
+    mute_target(req, state, "user", path.into_inner(), body).await
+}
+
+pub async fn unmute_user(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    unmute_target(req, state, "user", path.into_inner()).await
+}
+
+pub async fn mute_group(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: Option<web::Json<MuteRequest>>,
+) -> HttpResponse {
+    mute_target(req, state, "group", path.into_inner(), body).await
+}
+
+pub async fn unmute_group(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    unmute_target(req, state, "group", path.into_inner()).await
+}
+
+// Helper functions
+
+async fn mute_target(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    target_type: &str,
+    target_id: String,
+    body: Option<web::Json<MuteRequest>>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    if target_type == "user" && target_id == current_user.id {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Cannot mute yourself"
+        }));
+    }
+
+    let target_query = if target_type == "user" {
+        "SELECT COUNT(*) FROM users WHERE id = ?"
+    } else {
+        "SELECT COUNT(*) FROM groups WHERE id = ?"
+    };
+
+    let target_exists = sqlx::query_scalar::<_, i32>(target_query)
+        .bind(&target_id)
+        .fetch_one(&state.db)
+        .await
+        .unwrap_or(0) > 0;
+
+    if !target_exists {
+        return HttpResponse::NotFound().json(serde_json::json!({
+            "error": if target_type == "user" { "User not found" } else { "Group not found" }
+        }));
+    }
+
+    let duration_hours = body.and_then(|b| b.duration_hours);
+    if let Some(hours) = duration_hours {
+        if !(1..=MAX_MUTE_DURATION_HOURS).contains(&hours) {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": format!("duration_hours must be between 1 and {}", MAX_MUTE_DURATION_HOURS)
+            }));
+        }
+    }
+
+    let mute_id = Uuid::new_v4().to_string();
+    let now = Utc::now();
+    let expires_at = duration_hours.map(|hours| (now + Duration::hours(hours)).to_rfc3339());
+
+    // Re-muting replaces the previous expiry
+    let result = sqlx::query(
+        r#"
+        INSERT INTO mutes (id, user_id, target_type, target_id, expires_at, created_at)
+        VALUES (?, ?, ?, ?, ?, ?)
+        ON CONFLICT(user_id, target_type, target_id) DO UPDATE SET expires_at = excluded.expires_at
+        "#
+    )
+    .bind(&mute_id)
+    .bind(&current_user.id)
+    .bind(target_type)
+    .bind(&target_id)
+    .bind(&expires_at)
+    .bind(now.to_rfc3339())
+    .execute(&state.db)
+    .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "message": if target_type == "user" { "User muted" } else { "Group muted" },
+            "expires_at": expires_at
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to mute: {}", e)
+        })),
+    }
+}
+
+async fn unmute_target(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    target_type: &str,
+    target_id: String,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let result = sqlx::query(
+        "DELETE FROM mutes WHERE user_id = ? AND target_type = ? AND target_id = ?"
+    )
+    .bind(&current_user.id)
+    .bind(target_type)
+    .bind(&target_id)
+    .execute(&state.db)
+    .await;
+
+    match result {
+        Ok(r) => {
+            if r.rows_affected() > 0 {
+                HttpResponse::Ok().json(serde_json::json!({
+                    "message": if target_type == "user" { "User unmuted" } else { "Group unmuted" }
+                }))
+            } else {
+                HttpResponse::NotFound().json(serde_json::json!({
+                    "error": "Not muted"
+                }))
+            }
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to unmute: {}", e)
+        })),
+    }
+}
+
+/// Returns true if `user_id` has an unexpired mute on the given user or group.
+pub async fn is_muted(state: &web::Data<AppState>, user_id: &str, target_type: &str, target_id: &str) -> bool {
+    sqlx::query_scalar::<_, i32>(
+        "SELECT COUNT(*) FROM mutes WHERE user_id = ? AND target_type = ? AND target_id = ? AND (expires_at IS NULL OR expires_at > ?)"
+    )
+    .bind(user_id)
+    .bind(target_type)
+    .bind(target_id)
+    .bind(Utc::now().to_rfc3339())
+    .fetch_one(&state.db)
+    .await
+    .unwrap_or(0) > 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::posts::{create_post, get_feed};
+    use crate::test_support::{auth_request, befriend, create_user, json, response_json, test_state};
+
+    async fn mute(state: &web::Data<AppState>, user_id: &str, target_id: &str, body: Option<serde_json::Value>) -> HttpResponse {
+        mute_user(auth_request(user_id), state.clone(), web::Path::from(target_id.to_string()), body.map(json)).await
+    }
+
+    async fn feed_len(state: &web::Data<AppState>, user_id: &str) -> usize {
+        let response = get_feed(auth_request(user_id), state.clone(), web::Query::from_query("").unwrap()).await;
+        response_json(response).await.as_array().unwrap().len()
+    }
+
+    #[actix_rt::test]
+    async fn mute_durations_are_bounded() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+
+        for hours in [0, -1, MAX_MUTE_DURATION_HOURS + 1, i64::MAX] {
+            let response = mute(&state, &alice, &bob, Some(serde_json::json!({"duration_hours": hours}))).await;
+            assert_eq!(response.status().as_u16(), 400, "{} hours", hours);
+        }
+
+        let response = mute(&state, &alice, &bob, Some(serde_json::json!({"duration_hours": MAX_MUTE_DURATION_HOURS}))).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    #[actix_rt::test]
+    async fn temporary_mutes_lapse_on_their_own() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+        befriend(&state, &alice, &bob).await;
+        let response = create_post(auth_request(&bob), state.clone(), json(serde_json::json!({"content": "Hello"}))).await;
+        assert_eq!(response.status().as_u16(), 201);
+        assert_eq!(feed_len(&state, &alice).await, 1);
+
+        let response = mute(&state, &alice, &bob, Some(serde_json::json!({"duration_hours": 2}))).await;
+        assert_eq!(response.status().as_u16(), 200);
+        assert!(is_muted(&state, &alice, "user", &bob).await);
+        assert_eq!(feed_len(&state, &alice).await, 0);
+
+        sqlx::query("UPDATE mutes SET expires_at = ?")
+            .bind((Utc::now() - Duration::minutes(1)).to_rfc3339())
+            .execute(&state.db)
+            .await
+            .unwrap();
+
+        assert!(!is_muted(&state, &alice, "user", &bob).await);
+        assert_eq!(feed_len(&state, &alice).await, 1);
+        let mutes = response_json(get_mutes(auth_request(&alice), state.clone()).await).await;
+        assert!(mutes.as_array().unwrap().is_empty());
+
+        // Muting again without a duration lasts until it's lifted
+        mute(&state, &alice, &bob, None).await;
+        let mutes = response_json(get_mutes(auth_request(&alice), state.clone()).await).await;
+        assert_eq!(mutes[0]["expires_at"], serde_json::Value::Null);
+        assert_eq!(feed_len(&state, &alice).await, 0);
+    }
+}
@@ -0,0 +1,443 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::{DateTime, Utc};
+use sqlx::SqliteConnection;
+use uuid::Uuid;
+
+use crate::auth::require_auth;
+use crate::handlers::blocks::is_blocked;
+use crate::models::{
+    CreatePollRequest, Poll, PollOption, PollOptionResponse, PollResponse, PollVoteRequest, PollVoter, Post,
+};
+use crate::policy::{load_post_context, PostAction};
+use crate::AppState;
+
+const MIN_POLL_OPTIONS: usize = 2;
+const MAX_POLL_OPTIONS: usize = 10;
+const MAX_POLL_OPTION_LENGTH: usize = 100;
+
+/// Votes for one option, or several in a multiple-choice poll. Each user votes once;
+/// to change their answer they retract the vote first.
+pub async fn vote(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<PollVoteRequest>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let (post, poll) = match fetch_open_poll(&state, &path.into_inner(), &current_user.id).await {
+        Ok(found) => found,
+        Err(response) => return response,
+    };
+
+    let options = load_poll_options(&state, &post.id).await;
+
+    let mut option_ids: Vec<&String> = Vec::new();
+    for option_id in &body.option_ids {
+        if !options.iter().any(|o| &o.id == option_id) {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "Unknown poll option"
+            }));
+        }
+        if !option_ids.contains(&option_id) {
+            option_ids.push(option_id);
+        }
+    }
+
+    if option_ids.is_empty() {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Choose at least one option"
+        }));
+    }
+
+    if !poll.multiple_choice && option_ids.len() > 1 {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "This poll only allows one choice"
+        }));
+    }
+
+    let now = Utc::now().to_rfc3339();
+    let result: Result<bool, sqlx::Error> = async {
+        let mut tx = state.db.begin().await?;
+
+        // The first choice only goes in if the user hasn't voted yet, so concurrent
+        // requests can't both get through
+        let first_vote = sqlx::query(
+            r#"
+            INSERT INTO poll_votes (post_id, option_id, user_id, created_at)
+            SELECT ?1, ?2, ?3, ?4
+            WHERE NOT EXISTS (SELECT 1 FROM poll_votes WHERE post_id = ?1 AND user_id = ?3)
+            "#
+        )
+        .bind(&post.id)
+        .bind(option_ids[0])
+        .bind(&current_user.id)
+        .bind(&now)
+        .execute(&mut *tx)
+        .await?;
+
+        if first_vote.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        for option_id in &option_ids[1..] {
+            sqlx::query("INSERT INTO poll_votes (post_id, option_id, user_id, created_at) VALUES (?, ?, ?, ?)")
+                .bind(&post.id)
+                .bind(option_id)
+                .bind(&current_user.id)
+                .bind(&now)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(true)
+    }
+    .await;
+
+    match result {
+        Ok(true) => match load_poll(&state, &post, &current_user.id).await {
+            Some(poll) => HttpResponse::Ok().json(poll),
+            None => HttpResponse::NotFound().json(serde_json::json!({
+                "error": "This post has no poll"
+            })),
+        },
+        Ok(false) => HttpResponse::Conflict().json(serde_json::json!({
+            "error": "You already voted in this poll"
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to vote: {}", e)
+        })),
+    }
+}
+
+/// Takes back the user's vote while the poll is still open.
+pub async fn retract_vote(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let (post, _) = match fetch_open_poll(&state, &path.into_inner(), &current_user.id).await {
+        Ok(found) => found,
+        Err(response) => return response,
+    };
+
+    let result = sqlx::query("DELETE FROM poll_votes WHERE post_id = ? AND user_id = ?")
+        .bind(&post.id)
+        .bind(&current_user.id)
+        .execute(&state.db)
+        .await;
+
+    match result {
+        Ok(r) if r.rows_affected() == 0 => HttpResponse::NotFound().json(serde_json::json!({
+            "error": "You haven't voted in this poll"
+        })),
+        Ok(_) => match load_poll(&state, &post, &current_user.id).await {
+            Some(poll) => HttpResponse::Ok().json(poll),
+            None => HttpResponse::NotFound().json(serde_json::json!({
+                "error": "This post has no poll"
+            })),
+        },
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to retract vote: {}", e)
+        })),
+    }
+}
+
+// Helper functions
+
+/// Checks a new poll's options, results mode and close time.
+pub fn validate_poll(poll: &CreatePollRequest) -> Result<(), &'static str> {
+    let options: Vec<&str> = poll.options.iter().map(|o| o.trim()).collect();
+
+    if options.len() < MIN_POLL_OPTIONS || options.len() > MAX_POLL_OPTIONS {
+        return Err("A poll needs between 2 and 10 options");
+    }
+
+    if options.iter().any(|o| o.is_empty() || o.chars().count() > MAX_POLL_OPTION_LENGTH) {
+        return Err("Poll options must be between 1 and 100 characters");
+    }
+
+    if options.iter().enumerate().any(|(i, o)| options[..i].contains(o)) {
+        return Err("Poll options must be different");
+    }
+
+    if !matches!(poll.results_visibility.as_deref(), None | Some("always" | "after_vote" | "after_close")) {
+        return Err("results_visibility must be always, after_vote or after_close");
+    }
+
+    if let Some(ref closes_at) = poll.closes_at {
+        let closes_at = DateTime::parse_from_rfc3339(closes_at).map_err(|_| "closes_at must be an RFC 3339 timestamp")?;
+        if closes_at <= Utc::now() {
+            return Err("closes_at must be in the future");
+        }
+    }
+
+    Ok(())
+}
+
+/// Stores a poll validated with `validate_poll` for a new post.
+pub async fn insert_poll(conn: &mut SqliteConnection, post_id: &str, poll: &CreatePollRequest) -> Result<(), sqlx::Error> {
+    let closes_at = poll
+        .closes_at
+        .as_deref()
+        .and_then(|c| DateTime::parse_from_rfc3339(c).ok())
+        .map(|c| c.with_timezone(&Utc).to_rfc3339());
+
+    sqlx::query(
+        "INSERT INTO polls (post_id, multiple_choice, anonymous, results_visibility, closes_at, created_at) VALUES (?, ?, ?, ?, ?, ?)"
+    )
+    .bind(post_id)
+    .bind(poll.multiple_choice.unwrap_or(false))
+    .bind(poll.anonymous.unwrap_or(false))
+    .bind(poll.results_visibility.as_deref().unwrap_or("always"))
+    .bind(&closes_at)
+    .bind(Utc::now().to_rfc3339())
+    .execute(&mut *conn)
+    .await?;
+
+    for (position, text) in poll.options.iter().enumerate() {
+        sqlx::query("INSERT INTO poll_options (id, post_id, text, position) VALUES (?, ?, ?, ?)")
+            .bind(Uuid::new_v4().to_string())
+            .bind(post_id)
+            .bind(text.trim())
+            .bind(position as i64)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Removes a post's poll along with its options and votes, as part of the caller's
+/// transaction.
+pub async fn delete_poll(conn: &mut SqliteConnection, post_id: &str) -> Result<(), sqlx::Error> {
+    for statement in [
+        "DELETE FROM poll_votes WHERE post_id = ?",
+        "DELETE FROM poll_options WHERE post_id = ?",
+        "DELETE FROM polls WHERE post_id = ?",
+    ] {
+        sqlx::query(statement).bind(post_id).execute(&mut *conn).await?;
+    }
+
+    Ok(())
+}
+
+/// A post's poll as `viewer_id` sees it. Counts are hidden until the results mode
+/// allows them (the post's author always sees them), and voters are only listed in
+/// public polls, leaving out anyone blocked either way.
+pub async fn load_poll(state: &web::Data<AppState>, post: &Post, viewer_id: &str) -> Option<PollResponse> {
+    let poll = sqlx::query_as::<_, Poll>("SELECT * FROM polls WHERE post_id = ?")
+        .bind(&post.id)
+        .fetch_optional(&state.db)
+        .await
+        .ok()
+        .flatten()?;
+
+    let my_votes: Vec<String> = sqlx::query_scalar("SELECT option_id FROM poll_votes WHERE post_id = ? AND user_id = ?")
+        .bind(&post.id)
+        .bind(viewer_id)
+        .fetch_all(&state.db)
+        .await
+        .unwrap_or_default();
+
+    let is_closed = poll_is_closed(&poll);
+    let results_visible = post.user_id == viewer_id
+        || is_closed
+        || match poll.results_visibility.as_str() {
+            "after_vote" => !my_votes.is_empty(),
+            "after_close" => false,
+            _ => true,
+        };
+
+    let voters_count = if results_visible {
+        sqlx::query_scalar("SELECT COUNT(DISTINCT user_id) FROM poll_votes WHERE post_id = ?")
+            .bind(&post.id)
+            .fetch_one(&state.db)
+            .await
+            .ok()
+    } else {
+        None
+    };
+
+    let mut options = Vec::new();
+    for option in load_poll_options(state, &post.id).await {
+        let (votes_count, voters) = if results_visible {
+            let votes_count = sqlx::query_scalar("SELECT COUNT(*) FROM poll_votes WHERE option_id = ?")
+                .bind(&option.id)
+                .fetch_one(&state.db)
+                .await
+                .ok();
+
+            let voters = if poll.anonymous {
+                None
+            } else {
+                Some(load_voters(state, &option.id, viewer_id).await)
+            };
+
+            (votes_count, voters)
+        } else {
+            (None, None)
+        };
+
+        options.push(PollOptionResponse {
+            id: option.id,
+            text: option.text,
+            votes_count,
+            voters,
+        });
+    }
+
+    Some(PollResponse {
+        multiple_choice: poll.multiple_choice,
+        anonymous: poll.anonymous,
+        results_visibility: poll.results_visibility,
+        closes_at: poll.closes_at,
+        is_closed,
+        results_visible,
+        voters_count,
+        my_votes,
+        options,
+    })
+}
+
+/// Fetches a post's poll if the user may vote in it and it hasn't closed.
+async fn fetch_open_poll(state: &web::Data<AppState>, post_id: &str, user_id: &str) -> Result<(Post, Poll), HttpResponse> {
+    let post = match sqlx::query_as::<_, Post>("SELECT * FROM posts WHERE id = ?")
+        .bind(post_id)
+        .fetch_optional(&state.db)
+        .await
+    {
+        Ok(Some(post)) => post,
+        Ok(None) => return Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Post not found"
+        }))),
+        Err(e) => return Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        }))),
+    };
+
+    if !load_post_context(state, &post, user_id).await.allows(PostAction::Vote) {
+        return Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "You don't have permission to vote in this poll"
+        })));
+    }
+
+    let poll = match sqlx::query_as::<_, Poll>("SELECT * FROM polls WHERE post_id = ?")
+        .bind(post_id)
+        .fetch_optional(&state.db)
+        .await
+    {
+        Ok(Some(poll)) => poll,
+        Ok(None) => return Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "This post has no poll"
+        }))),
+        Err(e) => return Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        }))),
+    };
+
+    if poll_is_closed(&poll) {
+        return Err(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "This poll is closed"
+        })));
+    }
+
+    Ok((post, poll))
+}
+
+fn poll_is_closed(poll: &Poll) -> bool {
+    poll.closes_at
+        .as_deref()
+        .and_then(|c| DateTime::parse_from_rfc3339(c).ok())
+        .is_some_and(|c| c <= Utc::now())
+}
+
+async fn load_poll_options(state: &web::Data<AppState>, post_id: &str) -> Vec<PollOption> {
+    sqlx::query_as::<_, PollOption>("SELECT * FROM poll_options WHERE post_id = ? ORDER BY position")
+        .bind(post_id)
+        .fetch_all(&state.db)
+        .await
+        .unwrap_or_default()
+}
+
+async fn load_voters(state: &web::Data<AppState>, option_id: &str, viewer_id: &str) -> Vec<PollVoter> {
+    let voters = sqlx::query_as::<_, PollVoter>(
+        r#"
+        SELECT u.id AS user_id, u.username FROM poll_votes v
+        INNER JOIN users u ON u.id = v.user_id
+        WHERE v.option_id = ?
+        ORDER BY v.created_at
+        "#
+    )
+    .bind(option_id)
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+
+    let mut visible = Vec::new();
+    for voter in voters {
+        if !is_blocked(state, viewer_id, &voter.user_id).await {
+            visible.push(voter);
+        }
+    }
+    visible
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::posts::create_post;
+    use crate::test_support::{auth_request, count, create_user, json, response_json, test_state};
+
+    #[actix_rt::test]
+    async fn concurrent_votes_in_a_single_choice_poll_count_once() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+
+        let post = response_json(
+            create_post(
+                auth_request(&alice),
+                state.clone(),
+                json(serde_json::json!({
+                    "content": "Lunch?",
+                    "visibility": "public",
+                    "poll": {"options": ["Pizza", "Sushi"]}
+                })),
+            )
+            .await,
+        )
+        .await;
+        let post_id = post["id"].as_str().unwrap().to_string();
+        let option_ids: Vec<String> = post["poll"]["options"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|o| o["id"].as_str().unwrap().to_string())
+            .collect();
+
+        let vote_for = |option_id: &String| {
+            vote(
+                auth_request(&bob),
+                state.clone(),
+                web::Path::from(post_id.clone()),
+                json(serde_json::json!({"option_ids": [option_id]})),
+            )
+        };
+        let (first, second) = tokio::join!(vote_for(&option_ids[0]), vote_for(&option_ids[1]));
+
+        let mut statuses = [first.status().as_u16(), second.status().as_u16()];
+        statuses.sort();
+        assert_eq!(statuses, [200, 409]);
+        assert_eq!(count(&state, "SELECT COUNT(*) FROM poll_votes").await, 1);
+    }
+}
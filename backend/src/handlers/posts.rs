@@ -1,58 +1,79 @@
 use actix_web::{web, HttpRequest, HttpResponse};
-use chrono::Utc;
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use sqlx::SqliteConnection;
 use uuid::Uuid;
 
+use crate::anonymity::{find_alias, thread_alias};
 use crate::auth::require_auth;
-use crate::models::{Comment, CommentResponse, CreateCommentRequest, CreatePostRequest, Like, Post, PostResponse, User, Friendship};
+use crate::handlers::blocks::is_blocked;
+use crate::handlers::polls::{delete_poll, insert_poll, load_poll, validate_poll};
+use crate::models::{
+    Comment, CommentResponse, CreateCommentRequest, CreatePollRequest, CreatePostRequest, FriendListSummary, Friendship,
+    GroupSummary, Like, Post, PostAudienceRequest, PostAudienceResponse, PostResponse, PostVisibility, RepostRequest,
+    UpdatePostRequest, User,
+};
+use crate::link_previews::{first_url, load_link_preview, queue_link_preview};
+use crate::notifications::{notify, Notification};
+use crate::policy::{load_post_context, CommentAction, PostAction};
+use crate::ranking::{rank_posts, RANKED_CANDIDATES, RANKED_WINDOW_DAYS};
+use crate::scheduler::{release_post, ReleaseOutcome};
+use crate::tagging::{load_mentions, notify_comment_mentions, notify_post_mentions, record_comment_mentions, sync_post_tags};
 use crate::AppState;
 
+/// How many posts a feed page returns.
+const FEED_LIMIT: i64 = 50;
+
+#[derive(Deserialize)]
+pub struct FeedQuery {
+    pub mode: Option<String>, // chronological (default), ranked
+    pub include_groups: Option<bool>, // always on in ranked mode
+}
+
 pub async fn get_feed(
     req: HttpRequest,
     state: web::Data<AppState>,
+    query: web::Query<FeedQuery>,
 ) -> HttpResponse {
     let current_user = match require_auth(&req, &state).await {
         Ok(user) => user,
         Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
     };
 
-    // Get friend IDs
-    let friendships = sqlx::query_as::<_, Friendship>(
-        "SELECT * FROM friendships WHERE (user_id = ? OR friend_id = ?) AND status = 'accepted'"
-    )
-    .bind(&current_user.id)
-    .bind(&current_user.id)
-    .fetch_all(&state.db)
-    .await
-    .unwrap_or_default();
+    let posts = match query.mode.as_deref().unwrap_or("chronological") {
+        "chronological" if query.include_groups.unwrap_or(false) => {
+            let personal = fetch_personal_feed_posts(&state, &current_user.id, None, FEED_LIMIT).await;
+            let group = fetch_group_feed_posts(&state, &current_user.id, None, FEED_LIMIT).await;
 
-    let friend_ids: Vec<String> = friendships
-        .iter()
-        .map(|f| {
-            if f.user_id == current_user.id {
-                f.friend_id.clone()
-            } else {
-                f.user_id.clone()
+            match (personal, group) {
+                (Ok(mut posts), Ok(group_posts)) => {
+                    posts.extend(group_posts);
+                    posts.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+                    posts.truncate(FEED_LIMIT as usize);
+                    Ok(posts)
+                }
+                (Err(e), _) | (_, Err(e)) => Err(e),
             }
-        })
-        .collect();
+        }
+        "chronological" => fetch_personal_feed_posts(&state, &current_user.id, None, FEED_LIMIT).await,
+        "ranked" => {
+            // Score a window of recent posts, including the user's groups, and keep the best
+            let since = (Utc::now() - Duration::days(RANKED_WINDOW_DAYS)).to_rfc3339();
+            let personal = fetch_personal_feed_posts(&state, &current_user.id, Some(&since), RANKED_CANDIDATES).await;
+            let group = fetch_group_feed_posts(&state, &current_user.id, Some(&since), RANKED_CANDIDATES).await;
 
-    // Get posts: own posts + friends' posts (friends_only or public) + public posts
-    let posts = sqlx::query_as::<_, Post>(
-        r#"
-        SELECT * FROM posts 
-        WHERE group_id IS NULL AND (
-            user_id = ? 
-            OR visibility = 'public'
-            OR (visibility = 'friends_only' AND user_id IN (SELECT value FROM json_each(?)))
-        )
-        ORDER BY created_at DESC
-        LIMIT 50
-        "#
-    )
-    .bind(&current_user.id)
-    .bind(serde_json::to_string(&friend_ids).unwrap_or_else(|_| "[]".to_string()))
-    .fetch_all(&state.db)
-    .await;
+            match (personal, group) {
+                (Ok(mut posts), Ok(group_posts)) => {
+                    posts.extend(group_posts);
+                    Ok(rank_posts(&state, &current_user.id, posts, FEED_LIMIT as usize).await)
+                }
+                (Err(e), _) | (_, Err(e)) => Err(e),
+            }
+        }
+        other => return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("Unknown mode: {} (expected chronological or ranked)", other)
+        })),
+    };
 
     match posts {
         Ok(posts) => {
@@ -87,35 +108,61 @@ pub async fn create_post(
         }));
     }
 
-    let post_id = Uuid::new_v4().to_string();
     let now = Utc::now().to_rfc3339();
-    let visibility = body.visibility.clone().unwrap_or_else(|| "friends_only".to_string());
+    let visibility = body
+        .visibility
+        .clone()
+        .map(|v| PostVisibility::from(v).to_string())
+        .unwrap_or_else(|| PostVisibility::FriendsOnly.to_string());
+    let audience = body.audience.clone().unwrap_or_default();
 
-    let result = sqlx::query(
-        "INSERT INTO posts (id, user_id, content, visibility, is_anonymous, created_at, updated_at) VALUES (?, ?, ?, ?, 0, ?, ?)"
-    )
-    .bind(&post_id)
-    .bind(&current_user.id)
-    .bind(&body.content)
-    .bind(&visibility)
-    .bind(&now)
-    .bind(&now)
-    .execute(&state.db)
-    .await;
+    if let Err(message) = validate_audience(&state, &current_user.id, &visibility, &audience).await {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": message
+        }));
+    }
 
-    match result {
+    let (status, publish_at) = match parse_schedule(body.draft.unwrap_or(false), body.publish_at.as_deref()) {
+        Ok(schedule) => schedule,
+        Err(message) => return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": message
+        })),
+    };
+
+    if let Some(Err(message)) = body.poll.as_ref().map(validate_poll) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": message
+        }));
+    }
+
+    let post = Post {
+        id: Uuid::new_v4().to_string(),
+        user_id: current_user.id.clone(),
+        content: body.content.clone(),
+        visibility,
+        group_id: None,
+        is_anonymous: false,
+        created_at: now.clone(),
+        updated_at: now,
+        status: status.to_string(),
+        is_pinned: false,
+        comments_locked: false,
+        publish_at,
+        repost_of_id: None,
+        is_plain_repost: false,
+        link_url: first_url(&body.content),
+    };
+
+    match insert_personal_post(&state, &post, &audience, body.poll.as_ref()).await {
         Ok(_) => {
-            let post = Post {
-                id: post_id.clone(),
-                user_id: current_user.id.clone(),
-                content: body.content.clone(),
-                visibility,
-                group_id: None,
-                is_anonymous: false,
-                created_at: now.clone(),
-                updated_at: now,
-            };
-            
+            let _ = sync_post_tags(&state, &post).await;
+            if post.status == "published" {
+                notify_post_mentions(&state, &post).await;
+            }
+            if let Some(ref url) = post.link_url {
+                queue_link_preview(&state, url).await;
+            }
+
             let post_response = build_post_response(&state, &post, &current_user.id).await;
             HttpResponse::Created().json(post_response)
         }
@@ -145,7 +192,7 @@ pub async fn get_post(
     match post {
         Ok(Some(post)) => {
             // Check visibility permissions
-            if !can_view_post(&state, &post, &current_user.id).await {
+            if !load_post_context(&state, &post, &current_user.id).await.allows(PostAction::View) {
                 return HttpResponse::Forbidden().json(serde_json::json!({
                     "error": "You don't have permission to view this post"
                 }));
@@ -182,26 +229,18 @@ pub async fn delete_post(
 
     match post {
         Ok(Some(post)) => {
-            if post.user_id != current_user.id {
+            if !load_post_context(&state, &post, &current_user.id).await.allows(PostAction::Delete) {
                 return HttpResponse::Forbidden().json(serde_json::json!({
-                    "error": "You can only delete your own posts"
+                    "error": "You don't have permission to delete this post"
                 }));
             }
 
-            // Delete related comments and likes first
-            let _ = sqlx::query("DELETE FROM comments WHERE post_id = ?")
-                .bind(&post_id)
-                .execute(&state.db)
-                .await;
-            let _ = sqlx::query("DELETE FROM likes WHERE post_id = ?")
-                .bind(&post_id)
-                .execute(&state.db)
-                .await;
-
-            let result = sqlx::query("DELETE FROM posts WHERE id = ?")
-                .bind(&post_id)
-                .execute(&state.db)
-                .await;
+            let result: Result<(), sqlx::Error> = async {
+                let mut tx = state.db.begin().await?;
+                delete_post_rows(&mut tx, &post_id).await?;
+                tx.commit().await
+            }
+            .await;
 
             match result {
                 Ok(_) => HttpResponse::Ok().json(serde_json::json!({
@@ -233,6 +272,26 @@ pub async fn like_post(
 
     let post_id = path.into_inner();
 
+    let post = match sqlx::query_as::<_, Post>("SELECT * FROM posts WHERE id = ?")
+        .bind(&post_id)
+        .fetch_optional(&state.db)
+        .await
+    {
+        Ok(Some(post)) => post,
+        Ok(None) => return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Post not found"
+        })),
+        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        })),
+    };
+
+    if !load_post_context(&state, &post, &current_user.id).await.allows(PostAction::React) {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "You don't have permission to react to this post"
+        }));
+    }
+
     // Check if already liked
     let existing_like = sqlx::query_as::<_, Like>(
         "SELECT * FROM likes WHERE post_id = ? AND user_id = ?"
@@ -272,10 +331,20 @@ pub async fn like_post(
             .await;
 
             match result {
-                Ok(_) => HttpResponse::Ok().json(serde_json::json!({
-                    "message": "Post liked",
-                    "liked": true
-                })),
+                Ok(_) => {
+                    notify(&state, &post.user_id, &Notification {
+                        kind: "post_liked",
+                        actor_id: current_user.id.clone(),
+                        post_id: Some(post.id.clone()),
+                        group_id: post.group_id.clone(),
+                        is_anonymous: false,
+                    }).await;
+
+                    HttpResponse::Ok().json(serde_json::json!({
+                        "message": "Post liked",
+                        "liked": true
+                    }))
+                }
                 Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
                     "error": format!("Failed to like post: {}", e)
                 })),
@@ -287,6 +356,137 @@ pub async fn like_post(
     }
 }
 
+/// Shares a post, optionally with a quote. Reposting a repost shares its original.
+pub async fn repost_post(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<RepostRequest>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let post_id = path.into_inner();
+
+    let post = match sqlx::query_as::<_, Post>("SELECT * FROM posts WHERE id = ?")
+        .bind(&post_id)
+        .fetch_optional(&state.db)
+        .await
+    {
+        Ok(Some(post)) => post,
+        Ok(None) => return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Post not found"
+        })),
+        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        })),
+    };
+
+    let original = match post.repost_of_id {
+        Some(ref original_id) => match sqlx::query_as::<_, Post>("SELECT * FROM posts WHERE id = ?")
+            .bind(original_id)
+            .fetch_optional(&state.db)
+            .await
+        {
+            Ok(Some(original)) => original,
+            Ok(None) => return HttpResponse::NotFound().json(serde_json::json!({
+                "error": "The original post is no longer available"
+            })),
+            Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            })),
+        },
+        None => post,
+    };
+
+    if !load_post_context(&state, &original, &current_user.id).await.allows(PostAction::Repost) {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "This post can't be reposted"
+        }));
+    }
+
+    let quote = body.quote.clone().unwrap_or_default().trim().to_string();
+
+    if quote.is_empty() {
+        let already_reposted = sqlx::query_scalar::<_, i32>(
+            "SELECT COUNT(*) FROM posts WHERE repost_of_id = ? AND user_id = ? AND is_plain_repost = 1"
+        )
+        .bind(&original.id)
+        .bind(&current_user.id)
+        .fetch_one(&state.db)
+        .await
+        .unwrap_or(0) > 0;
+
+        if already_reposted {
+            return HttpResponse::Conflict().json(serde_json::json!({
+                "error": "You already reposted this post"
+            }));
+        }
+    }
+
+    let visibility = body
+        .visibility
+        .clone()
+        .map(|v| PostVisibility::from(v).to_string())
+        .unwrap_or_else(|| PostVisibility::FriendsOnly.to_string());
+
+    if visibility == "custom" {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Reposts can't have a custom audience"
+        }));
+    }
+
+    if visibility == "public" && original.visibility != "public" {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Reposts of friends-only posts can't be public"
+        }));
+    }
+
+    let now = Utc::now().to_rfc3339();
+    let repost = Post {
+        id: Uuid::new_v4().to_string(),
+        user_id: current_user.id.clone(),
+        visibility,
+        group_id: None,
+        is_anonymous: false,
+        created_at: now.clone(),
+        updated_at: now,
+        status: "published".to_string(),
+        is_pinned: false,
+        comments_locked: false,
+        publish_at: None,
+        repost_of_id: Some(original.id.clone()),
+        is_plain_repost: quote.is_empty(),
+        link_url: first_url(&quote),
+        content: quote,
+    };
+
+    match insert_personal_post(&state, &repost, &PostAudienceRequest::default(), None).await {
+        Ok(_) => {
+            let _ = sync_post_tags(&state, &repost).await;
+            notify_post_mentions(&state, &repost).await;
+            if let Some(ref url) = repost.link_url {
+                queue_link_preview(&state, url).await;
+            }
+
+            notify(&state, &original.user_id, &Notification {
+                kind: "post_reposted",
+                actor_id: current_user.id.clone(),
+                post_id: Some(repost.id.clone()),
+                group_id: None,
+                is_anonymous: false,
+            }).await;
+
+            HttpResponse::Created().json(build_post_response(&state, &repost, &current_user.id).await)
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to repost: {}", e)
+        })),
+    }
+}
+
 pub async fn add_comment(
     req: HttpRequest,
     state: web::Data<AppState>,
@@ -312,35 +512,97 @@ pub async fn add_comment(
         .fetch_optional(&state.db)
         .await;
 
-    if let Ok(None) = post {
-        return HttpResponse::NotFound().json(serde_json::json!({
+    let post = match post {
+        Ok(Some(post)) => post,
+        Ok(None) => return HttpResponse::NotFound().json(serde_json::json!({
             "error": "Post not found"
+        })),
+        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        })),
+    };
+
+    let is_anonymous = body.is_anonymous.unwrap_or(false);
+    let policy = load_post_context(&state, &post, &current_user.id).await;
+
+    if !policy.allows(PostAction::View) {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "You don't have permission to view this post"
+        }));
+    }
+
+    if !policy.allows(PostAction::Comment) {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": if post.comments_locked { "Comments are locked on this post" } else { "This post can't be commented on" }
+        }));
+    }
+
+    if is_anonymous && !policy.allows(PostAction::CommentAnonymously) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Anonymous comments are only allowed on posts in groups that permit anonymity"
         }));
     }
 
     let comment_id = Uuid::new_v4().to_string();
     let now = Utc::now().to_rfc3339();
-    let is_anonymous = body.is_anonymous.unwrap_or(false);
 
-    let result = sqlx::query(
-        "INSERT INTO comments (id, post_id, user_id, content, is_anonymous, created_at) VALUES (?, ?, ?, ?, ?, ?)"
-    )
-    .bind(&comment_id)
-    .bind(&post_id)
-    .bind(&current_user.id)
-    .bind(&body.content)
-    .bind(is_anonymous)
-    .bind(&now)
-    .execute(&state.db)
+    // The alias is assigned with the comment, so the thread never has an unnamed one
+    let result: Result<Option<String>, sqlx::Error> = async {
+        let mut tx = state.db.begin().await?;
+
+        let anonymous_alias = if is_anonymous {
+            Some(thread_alias(&mut tx, &post.id, &current_user.id).await?)
+        } else {
+            None
+        };
+
+        sqlx::query(
+            "INSERT INTO comments (id, post_id, user_id, content, is_anonymous, created_at) VALUES (?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&comment_id)
+        .bind(&post_id)
+        .bind(&current_user.id)
+        .bind(&body.content)
+        .bind(is_anonymous)
+        .bind(&now)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(anonymous_alias)
+    }
     .await;
 
     match result {
-        Ok(_) => {
+        Ok(anonymous_alias) => {
+            notify(&state, &post.user_id, &Notification {
+                kind: "post_commented",
+                actor_id: current_user.id.clone(),
+                post_id: Some(post.id.clone()),
+                group_id: post.group_id.clone(),
+                is_anonymous,
+            }).await;
+
+            let comment = Comment {
+                id: comment_id.clone(),
+                post_id: post.id.clone(),
+                user_id: current_user.id.clone(),
+                content: body.content.clone(),
+                is_anonymous,
+                created_at: now.clone(),
+            };
+            let _ = record_comment_mentions(&state, &comment).await;
+            notify_comment_mentions(&state, &post, &comment).await;
+
+            let mentions = load_mentions(&state, &post.id, Some(&comment_id)).await;
+
             let comment_response = CommentResponse {
                 id: comment_id,
                 user: if is_anonymous { None } else { Some(current_user.into()) },
                 content: body.content.clone(),
                 is_anonymous,
+                anonymous_alias,
+                mentions,
                 created_at: now,
             };
             HttpResponse::Created().json(comment_response)
@@ -356,13 +618,35 @@ pub async fn get_comments(
     state: web::Data<AppState>,
     path: web::Path<String>,
 ) -> HttpResponse {
-    let _current_user = match require_auth(&req, &state).await {
+    let current_user = match require_auth(&req, &state).await {
         Ok(user) => user,
         Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
     };
 
     let post_id = path.into_inner();
 
+    let post = match sqlx::query_as::<_, Post>("SELECT * FROM posts WHERE id = ?")
+        .bind(&post_id)
+        .fetch_optional(&state.db)
+        .await
+    {
+        Ok(Some(post)) => post,
+        Ok(None) => return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Post not found"
+        })),
+        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        })),
+    };
+
+    let policy = load_post_context(&state, &post, &current_user.id).await;
+
+    if !policy.allows(PostAction::View) {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "You don't have permission to view this post"
+        }));
+    }
+
     let comments = sqlx::query_as::<_, Comment>(
         "SELECT * FROM comments WHERE post_id = ? ORDER BY created_at ASC"
     )
@@ -375,6 +659,22 @@ pub async fn get_comments(
             let mut comment_responses: Vec<CommentResponse> = Vec::new();
             
             for comment in comments {
+                let author_blocked = !comment.is_anonymous && is_blocked(&state, &comment.user_id, &current_user.id).await;
+                if !policy.allows_comment(CommentAction::View, &comment, &current_user.id, author_blocked) {
+                    continue;
+                }
+
+                let anonymous_alias = if comment.is_anonymous {
+                    match find_alias(&state.db, &comment.post_id, &comment.user_id).await {
+                        Ok(alias) => alias,
+                        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+                            "error": format!("Failed to get comments: {}", e)
+                        })),
+                    }
+                } else {
+                    None
+                };
+
                 let user = if comment.is_anonymous {
                     None
                 } else {
@@ -387,11 +687,15 @@ pub async fn get_comments(
                         .map(|u| u.into())
                 };
 
+                let mentions = load_mentions(&state, &post_id, Some(&comment.id)).await;
+
                 comment_responses.push(CommentResponse {
                     id: comment.id,
                     user,
                     content: comment.content,
                     is_anonymous: comment.is_anonymous,
+                    anonymous_alias,
+                    mentions,
                     created_at: comment.created_at,
                 });
             }
@@ -404,79 +708,1001 @@ pub async fn get_comments(
     }
 }
 
-// Helper functions
-async fn build_post_response(state: &web::Data<AppState>, post: &Post, current_user_id: &str) -> PostResponse {
-    let user = if post.is_anonymous {
-        None
-    } else {
-        sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
-            .bind(&post.user_id)
-            .fetch_optional(&state.db)
-            .await
-            .ok()
-            .flatten()
-            .map(|u| u.into())
+pub async fn delete_comment(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
     };
 
-    let likes_count: i32 = sqlx::query_scalar("SELECT COUNT(*) FROM likes WHERE post_id = ?")
-        .bind(&post.id)
-        .fetch_one(&state.db)
+    let comment_id = path.into_inner();
+
+    let comment = match sqlx::query_as::<_, Comment>("SELECT * FROM comments WHERE id = ?")
+        .bind(&comment_id)
+        .fetch_optional(&state.db)
         .await
-        .unwrap_or(0);
+    {
+        Ok(Some(comment)) => comment,
+        Ok(None) => return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Comment not found"
+        })),
+        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        })),
+    };
 
-    let comments_count: i32 = sqlx::query_scalar("SELECT COUNT(*) FROM comments WHERE post_id = ?")
-        .bind(&post.id)
-        .fetch_one(&state.db)
+    let post = match sqlx::query_as::<_, Post>("SELECT * FROM posts WHERE id = ?")
+        .bind(&comment.post_id)
+        .fetch_optional(&state.db)
         .await
-        .unwrap_or(0);
+    {
+        Ok(Some(post)) => post,
+        Ok(None) => return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Post not found"
+        })),
+        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        })),
+    };
 
-    let is_liked = sqlx::query_as::<_, Like>(
-        "SELECT * FROM likes WHERE post_id = ? AND user_id = ?"
-    )
-    .bind(&post.id)
-    .bind(current_user_id)
-    .fetch_optional(&state.db)
-    .await
-    .ok()
-    .flatten()
-    .is_some();
+    let policy = load_post_context(&state, &post, &current_user.id).await;
 
-    PostResponse {
-        id: post.id.clone(),
-        user,
-        content: post.content.clone(),
-        visibility: post.visibility.clone(),
-        is_anonymous: post.is_anonymous,
-        likes_count,
-        comments_count,
-        is_liked,
-        created_at: post.created_at.clone(),
+    if !policy.allows_comment(CommentAction::Delete, &comment, &current_user.id, false) {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "You don't have permission to delete this comment"
+        }));
     }
-}
 
-async fn can_view_post(state: &web::Data<AppState>, post: &Post, viewer_id: &str) -> bool {
-    // Owner can always view
-    if post.user_id == viewer_id {
-        return true;
+    let _ = sqlx::query("DELETE FROM mentions WHERE comment_id = ?")
+        .bind(&comment_id)
+        .execute(&state.db)
+        .await;
+
+    let result = sqlx::query("DELETE FROM comments WHERE id = ?")
+        .bind(&comment_id)
+        .execute(&state.db)
+        .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Comment deleted"
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to delete comment: {}", e)
+        })),
+    }
+}
+
+pub async fn get_drafts(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    match own_posts_with_status(&state, &current_user.id, "draft").await {
+        Ok(posts) => HttpResponse::Ok().json(posts),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to get drafts: {}", e)
+        })),
+    }
+}
+
+pub async fn get_scheduled_posts(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    match own_posts_with_status(&state, &current_user.id, "scheduled").await {
+        Ok(posts) => HttpResponse::Ok().json(posts),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to get scheduled posts: {}", e)
+        })),
+    }
+}
+
+pub async fn update_post(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<UpdatePostRequest>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let mut post = match fetch_editable_post(&state, &path.into_inner(), &current_user.id).await {
+        Ok(post) => post,
+        Err(response) => return response,
+    };
+
+    if let Some(ref content) = body.content {
+        if content.trim().is_empty() {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "Post content cannot be empty"
+            }));
+        }
+        post.content = content.clone();
+    }
+
+    if post.group_id.is_some() && (body.visibility.is_some() || body.audience.is_some()) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Group posts are always visible to the group"
+        }));
+    }
+
+    if let Some(ref visibility) = body.visibility {
+        post.visibility = PostVisibility::from(visibility.clone()).to_string();
+    }
+
+    let audience = match body.audience {
+        Some(ref audience) => audience.clone(),
+        None => load_audience_request(&state, &post.id).await,
+    };
+
+    if post.group_id.is_none() {
+        if let Err(message) = validate_audience(&state, &current_user.id, &post.visibility, &audience).await {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": message
+            }));
+        }
+    }
+
+    if body.publish_at.is_some() {
+        match parse_schedule(false, body.publish_at.as_deref()) {
+            Ok((status, publish_at)) => {
+                post.status = status.to_string();
+                post.publish_at = publish_at;
+            }
+            Err(message) => return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": message
+            })),
+        }
     }
 
-    match post.visibility.as_str() {
-        "public" => true,
-        "private" => false,
-        "friends_only" => {
-            // Check if viewer is a friend
-            let friendship = sqlx::query_as::<_, Friendship>(
-                "SELECT * FROM friendships WHERE ((user_id = ? AND friend_id = ?) OR (user_id = ? AND friend_id = ?)) AND status = 'accepted'"
+    post.updated_at = Utc::now().to_rfc3339();
+    post.link_url = first_url(&post.content);
+
+    // The scheduler may have released the post since it was loaded
+    let result: Result<bool, sqlx::Error> = async {
+        let mut tx = state.db.begin().await?;
+
+        let updated = sqlx::query(
+            "UPDATE posts SET content = ?, visibility = ?, status = ?, publish_at = ?, link_url = ?, updated_at = ? WHERE id = ? AND status IN ('draft', 'scheduled')"
+        )
+        .bind(&post.content)
+        .bind(&post.visibility)
+        .bind(&post.status)
+        .bind(&post.publish_at)
+        .bind(&post.link_url)
+        .bind(&post.updated_at)
+        .bind(&post.id)
+        .execute(&mut *tx)
+        .await?;
+
+        if updated.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        if body.audience.is_some() {
+            sqlx::query("DELETE FROM post_audience WHERE post_id = ?")
+                .bind(&post.id)
+                .execute(&mut *tx)
+                .await?;
+
+            insert_audience(&mut tx, &post.id, &audience).await?;
+        }
+
+        tx.commit().await?;
+        Ok(true)
+    }
+    .await;
+
+    match result {
+        Ok(false) => HttpResponse::Conflict().json(serde_json::json!({
+            "error": "Post has already been published"
+        })),
+        Ok(true) => {
+            // Mentioned users hear about it when it's published
+            let _ = sync_post_tags(&state, &post).await;
+            if let Some(ref url) = post.link_url {
+                queue_link_preview(&state, url).await;
+            }
+            HttpResponse::Ok().json(build_post_response(&state, &post, &current_user.id).await)
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to update post: {}", e)
+        })),
+    }
+}
+
+pub async fn publish_post(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let post = match fetch_editable_post(&state, &path.into_inner(), &current_user.id).await {
+        Ok(post) => post,
+        Err(response) => return response,
+    };
+
+    match release_post(&state, &post).await {
+        Ok(ReleaseOutcome::Published) | Ok(ReleaseOutcome::Pending) => {
+            match sqlx::query_as::<_, Post>("SELECT * FROM posts WHERE id = ?")
+                .bind(&post.id)
+                .fetch_one(&state.db)
+                .await
+            {
+                Ok(post) => HttpResponse::Ok().json(build_post_response(&state, &post, &current_user.id).await),
+                Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+                    "error": format!("Database error: {}", e)
+                })),
+            }
+        }
+        Ok(ReleaseOutcome::NotAllowed) => HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "You can no longer post this in its group"
+        })),
+        Ok(ReleaseOutcome::AlreadyReleased) => HttpResponse::Conflict().json(serde_json::json!({
+            "error": "Post has already been published"
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to publish post: {}", e)
+        })),
+    }
+}
+
+pub async fn cancel_scheduled_post(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let mut post = match fetch_editable_post(&state, &path.into_inner(), &current_user.id).await {
+        Ok(post) => post,
+        Err(response) => return response,
+    };
+
+    // The post stays around as a draft
+    let result = sqlx::query(
+        "UPDATE posts SET status = 'draft', publish_at = NULL, updated_at = ? WHERE id = ? AND status = 'scheduled'"
+    )
+    .bind(Utc::now().to_rfc3339())
+    .bind(&post.id)
+    .execute(&state.db)
+    .await;
+
+    match result {
+        Ok(r) if r.rows_affected() > 0 => {
+            post.status = "draft".to_string();
+            post.publish_at = None;
+            HttpResponse::Ok().json(build_post_response(&state, &post, &current_user.id).await)
+        }
+        Ok(_) => HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Post is not scheduled"
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to cancel scheduled post: {}", e)
+        })),
+    }
+}
+
+// Helper functions
+
+/// Which personal posts the viewer (`?1`, with friend ids as a JSON array in `?2`) may see:
+/// own posts + friends' posts (friends_only or public) + public posts + custom posts whose
+/// audience includes the viewer, where public posts of private accounts only show up for
+/// friends and accepted followers, minus posts the viewer was left out of and anything
+/// written by users on either side of a block.
+const VISIBLE_PERSONAL_POSTS: &str = r#"
+    (
+        user_id = ?1
+        OR (visibility = 'public' AND (
+            user_id NOT IN (SELECT id FROM users WHERE requires_follow_approval = 1)
+            OR user_id IN (SELECT value FROM json_each(?2))
+            OR user_id IN (SELECT followee_id FROM follows WHERE follower_id = ?1 AND status = 'accepted')
+        ))
+        OR (visibility = 'friends_only' AND user_id IN (SELECT value FROM json_each(?2)))
+        OR (visibility = 'custom' AND id IN (
+            SELECT post_id FROM post_audience
+            WHERE (kind = 'user' AND target_id = ?1)
+               OR (kind = 'list' AND target_id IN (SELECT list_id FROM friend_list_members WHERE user_id = ?1))
+        ))
+    )
+    AND id NOT IN (SELECT post_id FROM post_audience WHERE kind = 'except' AND target_id = ?1)
+    AND user_id NOT IN (SELECT blocked_id FROM blocks WHERE blocker_id = ?1)
+    AND user_id NOT IN (SELECT blocker_id FROM blocks WHERE blocked_id = ?1)
+"#;
+
+/// Personal posts for the viewer's feed, newest first: everything in
+/// `VISIBLE_PERSONAL_POSTS` except posts by currently muted users.
+async fn fetch_personal_feed_posts(
+    state: &web::Data<AppState>,
+    user_id: &str,
+    since: Option<&str>,
+    limit: i64,
+) -> Result<Vec<Post>, sqlx::Error> {
+    let friend_ids = get_friend_ids(state, user_id).await;
+
+    let friend_ids_json = serde_json::to_string(&friend_ids).unwrap_or_else(|_| "[]".to_string());
+
+    let query = format!(
+        r#"
+        SELECT * FROM posts
+        WHERE group_id IS NULL AND status = 'published' AND {}
+        AND user_id NOT IN (
+            SELECT target_id FROM mutes
+            WHERE user_id = ?1 AND target_type = 'user' AND (expires_at IS NULL OR expires_at > ?3)
+        )
+        AND (?4 IS NULL OR created_at > ?4)
+        ORDER BY created_at DESC
+        LIMIT ?5
+        "#,
+        VISIBLE_PERSONAL_POSTS
+    );
+
+    sqlx::query_as::<_, Post>(&query)
+        .bind(user_id)
+        .bind(&friend_ids_json)
+        .bind(Utc::now().to_rfc3339())
+        .bind(since)
+        .bind(limit)
+        .fetch_all(&state.db)
+        .await
+}
+
+/// Published posts from the viewer's groups, newest first, skipping muted groups and
+/// groups the viewer hid from their feed. Named posts by muted users or users on either side of a block are left out
+/// as in the group itself; anonymous posts are kept so their authors stay hidden.
+async fn fetch_group_feed_posts(
+    state: &web::Data<AppState>,
+    user_id: &str,
+    since: Option<&str>,
+    limit: i64,
+) -> Result<Vec<Post>, sqlx::Error> {
+    sqlx::query_as::<_, Post>(
+        r#"
+        SELECT * FROM posts
+        WHERE status = 'published' AND (?2 IS NULL OR created_at > ?2)
+        AND group_id IN (SELECT group_id FROM group_members WHERE user_id = ?1 AND show_in_feed = 1)
+        AND group_id NOT IN (
+            SELECT target_id FROM mutes
+            WHERE user_id = ?1 AND target_type = 'group' AND (expires_at IS NULL OR expires_at > ?3)
+        )
+        AND (is_anonymous = 1 OR user_id = ?1 OR (
+            user_id NOT IN (SELECT blocked_id FROM blocks WHERE blocker_id = ?1)
+            AND user_id NOT IN (SELECT blocker_id FROM blocks WHERE blocked_id = ?1)
+            AND user_id NOT IN (
+                SELECT target_id FROM mutes
+                WHERE user_id = ?1 AND target_type = 'user' AND (expires_at IS NULL OR expires_at > ?3)
             )
-            .bind(&post.user_id)
-            .bind(viewer_id)
-            .bind(viewer_id)
+        ))
+        ORDER BY created_at DESC
+        LIMIT ?4
+        "#
+    )
+    .bind(user_id)
+    .bind(since)
+    .bind(Utc::now().to_rfc3339())
+    .bind(limit)
+    .fetch_all(&state.db)
+    .await
+}
+
+pub async fn build_post_response(state: &web::Data<AppState>, post: &Post, current_user_id: &str) -> PostResponse {
+    let user = if post.is_anonymous {
+        None
+    } else {
+        sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
             .bind(&post.user_id)
             .fetch_optional(&state.db)
-            .await;
+            .await
+            .ok()
+            .flatten()
+            .map(|u| u.into())
+    };
+
+    let anonymous_alias = if post.is_anonymous {
+        find_alias(&state.db, &post.id, &post.user_id).await.ok().flatten()
+    } else {
+        None
+    };
+
+    let likes_count: i32 = sqlx::query_scalar("SELECT COUNT(*) FROM likes WHERE post_id = ?")
+        .bind(&post.id)
+        .fetch_one(&state.db)
+        .await
+        .unwrap_or(0);
 
-            matches!(friendship, Ok(Some(_)))
+    let comments_count: i32 = sqlx::query_scalar("SELECT COUNT(*) FROM comments WHERE post_id = ?")
+        .bind(&post.id)
+        .fetch_one(&state.db)
+        .await
+        .unwrap_or(0);
+
+    let is_liked = sqlx::query_as::<_, Like>(
+        "SELECT * FROM likes WHERE post_id = ? AND user_id = ?"
+    )
+    .bind(&post.id)
+    .bind(current_user_id)
+    .fetch_optional(&state.db)
+    .await
+    .ok()
+    .flatten()
+    .is_some();
+
+    let audience = if post.user_id == current_user_id {
+        load_post_audience(state, &post.id).await
+    } else {
+        None
+    };
+
+    // Only reposts the viewer could see themselves are counted
+    let friend_ids_json = serde_json::to_string(&get_friend_ids(state, current_user_id).await)
+        .unwrap_or_else(|_| "[]".to_string());
+    let shares_count: i32 = sqlx::query_scalar(&format!(
+        "SELECT COUNT(*) FROM posts WHERE repost_of_id = ?3 AND status = 'published' AND {}",
+        VISIBLE_PERSONAL_POSTS
+    ))
+    .bind(current_user_id)
+    .bind(&friend_ids_json)
+    .bind(&post.id)
+    .fetch_one(&state.db)
+    .await
+    .unwrap_or(0);
+
+    // The original is embedded only while it exists and the viewer may still see it
+    let mut repost_of = None;
+    if let Some(ref original_id) = post.repost_of_id {
+        if let Ok(Some(original)) = sqlx::query_as::<_, Post>("SELECT * FROM posts WHERE id = ?")
+            .bind(original_id)
+            .fetch_optional(&state.db)
+            .await
+        {
+            if load_post_context(state, &original, current_user_id).await.allows(PostAction::View) {
+                repost_of = Some(Box::new(Box::pin(build_post_response(state, &original, current_user_id)).await));
+            }
+        }
+    }
+
+    let link_preview = match post.link_url {
+        Some(ref url) => load_link_preview(state, url).await,
+        None => None,
+    };
+
+    let group = match post.group_id {
+        Some(ref group_id) => sqlx::query_as::<_, GroupSummary>("SELECT id, name FROM groups WHERE id = ?")
+            .bind(group_id)
+            .fetch_optional(&state.db)
+            .await
+            .ok()
+            .flatten(),
+        None => None,
+    };
+
+    PostResponse {
+        id: post.id.clone(),
+        user,
+        content: post.content.clone(),
+        visibility: post.visibility.clone(),
+        is_anonymous: post.is_anonymous,
+        anonymous_alias,
+        likes_count,
+        comments_count,
+        is_liked,
+        status: post.status.clone(),
+        is_pinned: post.is_pinned,
+        comments_locked: post.comments_locked,
+        group,
+        repost_of_id: post.repost_of_id.clone(),
+        repost_of,
+        shares_count,
+        mentions: load_mentions(state, &post.id, None).await,
+        link_preview,
+        poll: load_poll(state, post, current_user_id).await,
+        audience,
+        publish_at: post.publish_at.clone(),
+        created_at: post.created_at.clone(),
+    }
+}
+
+/// The lists and users a post targets or leaves out, or None if it has no audience rows.
+async fn load_post_audience(state: &web::Data<AppState>, post_id: &str) -> Option<PostAudienceResponse> {
+    let lists = sqlx::query_as::<_, FriendListSummary>(
+        "SELECT l.id, l.name FROM friend_lists l INNER JOIN post_audience a ON a.target_id = l.id WHERE a.post_id = ? AND a.kind = 'list' ORDER BY l.name"
+    )
+    .bind(post_id)
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+
+    let users_of_kind = |kind: &'static str| {
+        sqlx::query_as::<_, User>(
+            "SELECT u.* FROM users u INNER JOIN post_audience a ON a.target_id = u.id WHERE a.post_id = ? AND a.kind = ? ORDER BY u.display_name"
+        )
+        .bind(post_id)
+        .bind(kind)
+        .fetch_all(&state.db)
+    };
+
+    let users = users_of_kind("user").await.unwrap_or_default();
+    let except_users = users_of_kind("except").await.unwrap_or_default();
+
+    if lists.is_empty() && users.is_empty() && except_users.is_empty() {
+        return None;
+    }
+
+    Some(PostAudienceResponse {
+        lists,
+        users: users.into_iter().map(|u| u.into()).collect(),
+        except_users: except_users.into_iter().map(|u| u.into()).collect(),
+    })
+}
+
+/// The status and publish time for a new post: a draft, scheduled for a future
+/// RFC 3339 `publish_at` (stored in UTC), or published right away.
+pub fn parse_schedule(draft: bool, publish_at: Option<&str>) -> Result<(&'static str, Option<String>), &'static str> {
+    match (draft, publish_at) {
+        (true, Some(_)) => Err("A post can't be both a draft and scheduled"),
+        (true, None) => Ok(("draft", None)),
+        (false, Some(publish_at)) => {
+            let publish_at = DateTime::parse_from_rfc3339(publish_at)
+                .map_err(|_| "publish_at must be an RFC 3339 timestamp")?
+                .with_timezone(&Utc);
+
+            if publish_at <= Utc::now() {
+                return Err("publish_at must be in the future");
+            }
+
+            Ok(("scheduled", Some(publish_at.to_rfc3339())))
+        }
+        (false, None) => Ok(("published", None)),
+    }
+}
+
+/// Fetches a draft or scheduled post the user may edit.
+async fn fetch_editable_post(state: &web::Data<AppState>, post_id: &str, user_id: &str) -> Result<Post, HttpResponse> {
+    let post = match sqlx::query_as::<_, Post>("SELECT * FROM posts WHERE id = ?")
+        .bind(post_id)
+        .fetch_optional(&state.db)
+        .await
+    {
+        Ok(Some(post)) => post,
+        Ok(None) => return Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Post not found"
+        }))),
+        Err(e) => return Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        }))),
+    };
+
+    if !load_post_context(state, &post, user_id).await.allows(PostAction::Edit) {
+        return Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "You can only edit your own posts"
+        })));
+    }
+
+    if post.status != "draft" && post.status != "scheduled" {
+        return Err(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Only drafts and scheduled posts can be changed"
+        })));
+    }
+
+    Ok(post)
+}
+
+/// The author's posts with `status`, for the drafts and scheduled lists.
+async fn own_posts_with_status(state: &web::Data<AppState>, user_id: &str, status: &str) -> Result<Vec<PostResponse>, sqlx::Error> {
+    let posts = sqlx::query_as::<_, Post>(
+        "SELECT * FROM posts WHERE user_id = ? AND status = ? ORDER BY COALESCE(publish_at, updated_at) ASC"
+    )
+    .bind(user_id)
+    .bind(status)
+    .fetch_all(&state.db)
+    .await?;
+
+    let mut post_responses: Vec<PostResponse> = Vec::new();
+    for post in posts {
+        post_responses.push(build_post_response(state, &post, user_id).await);
+    }
+
+    Ok(post_responses)
+}
+
+/// The audience rows a post already has, in request form.
+async fn load_audience_request(state: &web::Data<AppState>, post_id: &str) -> PostAudienceRequest {
+    let rows: Vec<(String, String)> = sqlx::query_as("SELECT kind, target_id FROM post_audience WHERE post_id = ?")
+        .bind(post_id)
+        .fetch_all(&state.db)
+        .await
+        .unwrap_or_default();
+
+    let mut audience = PostAudienceRequest::default();
+    for (kind, target_id) in rows {
+        match kind.as_str() {
+            "list" => audience.list_ids.push(target_id),
+            "user" => audience.user_ids.push(target_id),
+            _ => audience.except_user_ids.push(target_id),
+        }
+    }
+
+    audience
+}
+
+/// Inserts a personal post together with its audience rows.
+async fn insert_personal_post(
+    state: &web::Data<AppState>,
+    post: &Post,
+    audience: &PostAudienceRequest,
+    poll: Option<&CreatePollRequest>,
+) -> Result<(), sqlx::Error> {
+    let mut tx = state.db.begin().await?;
+
+    sqlx::query(
+        "INSERT INTO posts (id, user_id, content, visibility, is_anonymous, created_at, updated_at, status, publish_at, repost_of_id, is_plain_repost, link_url) VALUES (?, ?, ?, ?, 0, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(&post.id)
+    .bind(&post.user_id)
+    .bind(&post.content)
+    .bind(&post.visibility)
+    .bind(&post.created_at)
+    .bind(&post.updated_at)
+    .bind(&post.status)
+    .bind(&post.publish_at)
+    .bind(&post.repost_of_id)
+    .bind(post.is_plain_repost)
+    .bind(&post.link_url)
+    .execute(&mut *tx)
+    .await?;
+
+    insert_audience(&mut tx, &post.id, audience).await?;
+
+    if let Some(poll) = poll {
+        insert_poll(&mut tx, &post.id, poll).await?;
+    }
+
+    tx.commit().await
+}
+
+async fn insert_audience(conn: &mut SqliteConnection, post_id: &str, audience: &PostAudienceRequest) -> Result<(), sqlx::Error> {
+    let targets = [("list", &audience.list_ids), ("user", &audience.user_ids), ("except", &audience.except_user_ids)];
+    for (kind, target_ids) in targets {
+        for target_id in target_ids {
+            sqlx::query("INSERT OR IGNORE INTO post_audience (post_id, kind, target_id) VALUES (?, ?, ?)")
+                .bind(post_id)
+                .bind(kind)
+                .bind(target_id)
+                .execute(&mut *conn)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks a new post's audience against its visibility: only `custom` posts target
+/// lists and users, and friends can only be left out of `friends_only` and `custom`.
+async fn validate_audience(
+    state: &web::Data<AppState>,
+    author_id: &str,
+    visibility: &str,
+    audience: &PostAudienceRequest,
+) -> Result<(), &'static str> {
+    let targets_anyone = !audience.list_ids.is_empty() || !audience.user_ids.is_empty();
+
+    if visibility == "custom" && !targets_anyone {
+        return Err("A custom audience needs at least one friend list or user");
+    }
+
+    if visibility != "custom" && targets_anyone {
+        return Err("Only custom posts can target friend lists or users");
+    }
+
+    if !audience.except_user_ids.is_empty() && visibility != "custom" && visibility != "friends_only" {
+        return Err("Friends can only be left out of friends_only or custom posts");
+    }
+
+    for list_id in &audience.list_ids {
+        let owned: i32 = sqlx::query_scalar("SELECT COUNT(*) FROM friend_lists WHERE id = ? AND owner_id = ?")
+            .bind(list_id)
+            .bind(author_id)
+            .fetch_one(&state.db)
+            .await
+            .unwrap_or(0);
+
+        if owned == 0 {
+            return Err("Friend list not found");
+        }
+    }
+
+    for user_id in audience.user_ids.iter().chain(&audience.except_user_ids) {
+        let exists: i32 = sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_one(&state.db)
+            .await
+            .unwrap_or(0);
+
+        if exists == 0 || user_id == author_id {
+            return Err("Audience users must be other existing users");
         }
-        _ => false,
+    }
+
+    Ok(())
+}
+
+/// Deletes a post, its plain reposts and everything attached to them (poll, mentions,
+/// hashtags, aliases, comments, audience and likes), children first. Quotes stay and
+/// show the original as unavailable.
+pub async fn delete_post_rows(conn: &mut SqliteConnection, post_id: &str) -> Result<(), sqlx::Error> {
+    let plain_reposts: Vec<String> = sqlx::query_scalar("SELECT id FROM posts WHERE repost_of_id = ? AND is_plain_repost = 1")
+        .bind(post_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+    for id in plain_reposts.iter().map(String::as_str).chain([post_id]) {
+        delete_poll(&mut *conn, id).await?;
+
+        for statement in [
+            "DELETE FROM mentions WHERE post_id = ?",
+            "DELETE FROM post_hashtags WHERE post_id = ?",
+            "DELETE FROM anonymous_aliases WHERE post_id = ?",
+            "DELETE FROM comments WHERE post_id = ?",
+            "DELETE FROM post_audience WHERE post_id = ?",
+            "DELETE FROM likes WHERE post_id = ?",
+            "DELETE FROM posts WHERE id = ?",
+        ] {
+            sqlx::query(statement).bind(id).execute(&mut *conn).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The ids of everyone `user_id` has an accepted friendship with.
+pub async fn get_friend_ids(state: &web::Data<AppState>, user_id: &str) -> Vec<String> {
+    let friendships = sqlx::query_as::<_, Friendship>(
+        "SELECT * FROM friendships WHERE (user_id = ? OR friend_id = ?) AND status = 'accepted'"
+    )
+    .bind(user_id)
+    .bind(user_id)
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+
+    friendships
+        .into_iter()
+        .map(|f| if f.user_id == user_id { f.friend_id } else { f.user_id })
+        .collect()
+}
+
+pub async fn are_friends(state: &web::Data<AppState>, user_a: &str, user_b: &str) -> bool {
+    let friendship = sqlx::query_as::<_, Friendship>(
+        "SELECT * FROM friendships WHERE ((user_id = ? AND friend_id = ?) OR (user_id = ? AND friend_id = ?)) AND status = 'accepted'"
+    )
+    .bind(user_a)
+    .bind(user_b)
+    .bind(user_b)
+    .bind(user_a)
+    .fetch_optional(&state.db)
+    .await;
+
+    matches!(friendship, Ok(Some(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::polls::vote;
+    use crate::test_support::{auth_request, befriend, block, count, create_user, json, response_json, test_state, visibility_fixture};
+
+    async fn post_as(state: &web::Data<AppState>, user_id: &str, body: serde_json::Value) -> serde_json::Value {
+        let response = create_post(auth_request(user_id), state.clone(), json(body)).await;
+        assert_eq!(response.status().as_u16(), 201, "create post");
+        response_json(response).await
+    }
+
+    #[actix_rt::test]
+    async fn deleting_a_post_removes_everything_attached_and_plain_reposts() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+
+        let post = post_as(&state, &alice, serde_json::json!({
+            "content": "Tabs or spaces, @bob? #style",
+            "visibility": "public",
+            "poll": {"options": ["Tabs", "Spaces"]}
+        }))
+        .await;
+        let post_id = post["id"].as_str().unwrap().to_string();
+        let path = || web::Path::from(post_id.clone());
+
+        assert!(like_post(auth_request(&bob), state.clone(), path()).await.status().is_success());
+        let response = add_comment(auth_request(&bob), state.clone(), path(), json(serde_json::json!({"content": "Spaces, @alice"}))).await;
+        assert_eq!(response.status().as_u16(), 201);
+        let response = vote(
+            auth_request(&bob),
+            state.clone(),
+            path(),
+            json(serde_json::json!({"option_ids": [post["poll"]["options"][1]["id"]]})),
+        )
+        .await;
+        assert_eq!(response.status().as_u16(), 200);
+        let response = repost_post(auth_request(&bob), state.clone(), path(), json(serde_json::json!({}))).await;
+        assert_eq!(response.status().as_u16(), 201);
+
+        let response = delete_post(auth_request(&alice), state.clone(), path()).await;
+        assert_eq!(response.status().as_u16(), 200);
+        for table in ["posts", "likes", "comments", "mentions", "post_hashtags", "polls", "poll_options", "poll_votes"] {
+            assert_eq!(count(&state, &format!("SELECT COUNT(*) FROM {}", table)).await, 0, "{}", table);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn quotes_outlive_the_original_but_plain_reposts_do_not() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+
+        let post = post_as(&state, &alice, serde_json::json!({"content": "Original", "visibility": "public"})).await;
+        let post_id = post["id"].as_str().unwrap().to_string();
+        let path = || web::Path::from(post_id.clone());
+
+        let response = repost_post(auth_request(&bob), state.clone(), path(), json(serde_json::json!({"quote": "Worth reading"}))).await;
+        assert_eq!(response.status().as_u16(), 201);
+        let quote_id = response_json(response).await["id"].as_str().unwrap().to_string();
+
+        // A quote doesn't count as the plain repost, so bob can still share it plainly once
+        let response = repost_post(auth_request(&bob), state.clone(), path(), json(serde_json::json!({"quote": "  "}))).await;
+        assert_eq!(response.status().as_u16(), 201);
+        let response = repost_post(auth_request(&bob), state.clone(), path(), json(serde_json::json!({}))).await;
+        assert_eq!(response.status().as_u16(), 409);
+
+        let response = delete_post(auth_request(&alice), state.clone(), path()).await;
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(count(&state, "SELECT COUNT(*) FROM posts").await, 1);
+
+        let response = get_post(auth_request(&bob), state.clone(), web::Path::from(quote_id)).await;
+        assert_eq!(response.status().as_u16(), 200);
+        let quote = response_json(response).await;
+        assert_eq!(quote["content"], "Worth reading");
+        assert!(quote["repost_of"].is_null());
+    }
+
+    #[actix_rt::test]
+    async fn shares_count_only_counts_reposts_the_viewer_can_see() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+        let carol = create_user(&state, "carol").await;
+        let dave = create_user(&state, "dave").await;
+        befriend(&state, &bob, &carol).await;
+
+        let post = post_as(&state, &alice, serde_json::json!({"content": "Original", "visibility": "public"})).await;
+        let post_id = post["id"].as_str().unwrap().to_string();
+        let path = || web::Path::from(post_id.clone());
+
+        let response = repost_post(auth_request(&bob), state.clone(), path(), json(serde_json::json!({}))).await;
+        assert_eq!(response.status().as_u16(), 201);
+        let response = repost_post(auth_request(&dave), state.clone(), path(), json(serde_json::json!({"visibility": "public"}))).await;
+        assert_eq!(response.status().as_u16(), 201);
+
+        let shares = |viewer: String| {
+            let state = state.clone();
+            let path = path();
+            async move {
+                let response = get_post(auth_request(&viewer), state, path).await;
+                assert_eq!(response.status().as_u16(), 200);
+                response_json(response).await["shares_count"].as_i64().unwrap()
+            }
+        };
+
+        assert_eq!(shares(carol.clone()).await, 2);
+        assert_eq!(shares(alice.clone()).await, 1);
+
+        block(&state, &carol, &dave).await;
+        assert_eq!(shares(carol.clone()).await, 1);
+    }
+
+    async fn feed_contents(state: &web::Data<AppState>, viewer: &str, query: &str) -> Vec<String> {
+        let response = get_feed(auth_request(viewer), state.clone(), web::Query::from_query(query).unwrap()).await;
+        assert_eq!(response.status().as_u16(), 200);
+        let mut contents: Vec<String> = response_json(response)
+            .await
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["content"].as_str().unwrap().to_string())
+            .collect();
+        contents.sort();
+        contents
+    }
+
+    #[actix_rt::test]
+    async fn ranked_feeds_favour_engagement_and_cap_each_author() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+        let carol = create_user(&state, "carol").await;
+        befriend(&state, &alice, &bob).await;
+        befriend(&state, &alice, &carol).await;
+
+        for n in 0..5 {
+            post_as(&state, &bob, serde_json::json!({"content": format!("Bob #{}", n), "visibility": "public"})).await;
+        }
+        let popular = post_as(&state, &carol, serde_json::json!({"content": "Popular", "visibility": "public"})).await;
+        let popular_id = popular["id"].as_str().unwrap().to_string();
+        for user in [&alice, &bob] {
+            like_post(auth_request(user), state.clone(), web::Path::from(popular_id.clone())).await;
+            add_comment(auth_request(user), state.clone(), web::Path::from(popular_id.clone()), json(serde_json::json!({"content": "Nice"}))).await;
+        }
+        let stale = post_as(&state, &carol, serde_json::json!({"content": "Stale", "visibility": "public"})).await;
+        sqlx::query("UPDATE posts SET created_at = ? WHERE id = ?")
+            .bind((Utc::now() - Duration::days(RANKED_WINDOW_DAYS + 1)).to_rfc3339())
+            .bind(stale["id"].as_str().unwrap())
+            .execute(&state.db)
+            .await
+            .unwrap();
+
+        let response = get_feed(auth_request(&alice), state.clone(), web::Query::from_query("mode=ranked").unwrap()).await;
+        assert_eq!(response.status().as_u16(), 200);
+        let feed: Vec<String> = response_json(response)
+            .await
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["content"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(feed[0], "Popular");
+        assert_eq!(feed.iter().filter(|c| c.starts_with("Bob")).count(), 3);
+        assert!(!feed.contains(&"Stale".to_string()));
+
+        let response = get_feed(auth_request(&alice), state.clone(), web::Query::from_query("mode=best").unwrap()).await;
+        assert_eq!(response.status().as_u16(), 400);
+    }
+
+    #[actix_rt::test]
+    async fn feeds_leave_out_blocked_private_and_unpublished_posts() {
+        let state = test_state().await;
+        let (viewer, personal, _) = visibility_fixture(&state).await;
+
+        let mut expected: Vec<String> = personal.iter().map(|c| c.to_string()).collect();
+        expected.sort();
+        assert_eq!(feed_contents(&state, &viewer, "").await, expected);
+    }
+
+    #[actix_rt::test]
+    async fn group_posts_join_the_feed_without_naming_anonymous_authors() {
+        let state = test_state().await;
+        let (viewer, personal, group) = visibility_fixture(&state).await;
+
+        // Anonymous group posts stay in even though their author is blocked
+        let mut expected: Vec<String> = personal.iter().chain(&group).map(|c| c.to_string()).collect();
+        expected.sort();
+        assert_eq!(feed_contents(&state, &viewer, "include_groups=true").await, expected);
+        assert_eq!(feed_contents(&state, &viewer, "mode=ranked").await, expected);
+
+        let response = get_feed(auth_request(&viewer), state.clone(), web::Query::from_query("include_groups=true").unwrap()).await;
+        let feed = response_json(response).await;
+        let anonymous = feed.as_array().unwrap().iter().find(|p| p["is_anonymous"] == true).unwrap();
+        assert_eq!(anonymous["user"], serde_json::Value::Null);
+        assert!(anonymous["anonymous_alias"].as_str().unwrap().starts_with("Anonymous "));
     }
 }
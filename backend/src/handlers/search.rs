@@ -0,0 +1,253 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+
+use crate::auth::require_auth;
+use crate::handlers::groups::build_group_response;
+use crate::handlers::posts::build_post_response;
+use crate::handlers::users::user_response_for;
+use crate::models::{GroupMatch, GroupSearchResult, PostMatch, PostSearchResult, SearchResponse, User};
+use crate::AppState;
+
+#[derive(Deserialize)]
+pub struct UnifiedSearchQuery {
+    pub q: Option<String>,
+    #[serde(rename = "type")]
+    pub types: Option<String>, // comma separated: posts, users, groups
+    pub limit: Option<i64>,
+}
+
+pub async fn search(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<UnifiedSearchQuery>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let match_query = match build_match_query(query.q.as_deref().unwrap_or_default()) {
+        Some(q) => q,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Search query cannot be empty"
+        })),
+    };
+
+    let types: Vec<String> = query
+        .types
+        .as_deref()
+        .unwrap_or("posts,users,groups")
+        .split(',')
+        .map(|t| t.trim().to_lowercase())
+        .collect();
+
+    if let Some(unknown) = types.iter().find(|t| !["posts", "users", "groups"].contains(&t.as_str())) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("Unknown search type: {}", unknown)
+        }));
+    }
+
+    let limit = query.limit.unwrap_or(20).clamp(1, 50);
+    let mut response = SearchResponse {
+        posts: Vec::new(),
+        users: Vec::new(),
+        groups: Vec::new(),
+    };
+
+    if types.iter().any(|t| t == "posts") {
+        // Only content is indexed, so searching for a name never surfaces that
+        // person's anonymous posts. Visibility mirrors the feed for personal posts
+        // and requires membership for group posts.
+        let matches = sqlx::query_as::<_, PostMatch>(
+            r#"
+            SELECT p.*, snippet(posts_fts, 1, char(2), char(3), '…', 12) AS snippet, bm25(posts_fts) AS rank
+            FROM posts_fts
+            INNER JOIN posts p ON p.id = posts_fts.post_id
+            WHERE posts_fts MATCH ?1
+              AND p.status = 'published'
+              AND (
+                  (p.group_id IS NULL AND (
+                      p.user_id = ?2
+                      OR (p.visibility = 'public' AND (
+                          p.user_id NOT IN (SELECT id FROM users WHERE requires_follow_approval = 1)
+                          OR p.user_id IN (SELECT friend_id FROM friend_edges WHERE user_id = ?2)
+                          OR p.user_id IN (SELECT followee_id FROM follows WHERE follower_id = ?2 AND status = 'accepted')
+                      ))
+                      OR (p.visibility = 'friends_only' AND p.user_id IN (SELECT friend_id FROM friend_edges WHERE user_id = ?2))
+                      OR (p.visibility = 'custom' AND p.id IN (
+                          SELECT post_id FROM post_audience
+                          WHERE (kind = 'user' AND target_id = ?2)
+                             OR (kind = 'list' AND target_id IN (SELECT list_id FROM friend_list_members WHERE user_id = ?2))
+                      ))
+                  )
+                  AND p.id NOT IN (SELECT post_id FROM post_audience WHERE kind = 'except' AND target_id = ?2))
+                  OR (p.group_id IS NOT NULL AND p.group_id IN (SELECT group_id FROM group_members WHERE user_id = ?2))
+              )
+              AND (
+                  p.is_anonymous = 1 OR (
+                      p.user_id NOT IN (SELECT blocked_id FROM blocks WHERE blocker_id = ?2)
+                      AND p.user_id NOT IN (SELECT blocker_id FROM blocks WHERE blocked_id = ?2)
+                  )
+              )
+            ORDER BY rank
+            LIMIT ?3
+            "#
+        )
+        .bind(&match_query)
+        .bind(&current_user.id)
+        .bind(limit)
+        .fetch_all(&state.db)
+        .await;
+
+        match matches {
+            Ok(matches) => {
+                for m in matches {
+                    response.posts.push(PostSearchResult {
+                        post: build_post_response(&state, &m.post, &current_user.id).await,
+                        snippet: highlight(&m.snippet),
+                        rank: m.rank,
+                    });
+                }
+            }
+            Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Failed to search posts: {}", e)
+            })),
+        }
+    }
+
+    if types.iter().any(|t| t == "users") {
+        let users = sqlx::query_as::<_, User>(
+            r#"
+            SELECT u.* FROM users_fts
+            INNER JOIN users u ON u.id = users_fts.user_id
+            WHERE users_fts MATCH ?1
+              AND u.id != ?2
+              AND u.id NOT IN (SELECT blocked_id FROM blocks WHERE blocker_id = ?2)
+              AND u.id NOT IN (SELECT blocker_id FROM blocks WHERE blocked_id = ?2)
+              AND (
+                  u.searchable_by = 'everyone'
+                  OR (u.searchable_by = 'friends_of_friends' AND u.id IN (
+                      SELECT friend_id FROM friend_edges WHERE user_id = ?2
+                      UNION
+                      SELECT fof.friend_id FROM friend_edges f
+                      INNER JOIN friend_edges fof ON fof.user_id = f.friend_id
+                      WHERE f.user_id = ?2
+                  ))
+              )
+            ORDER BY bm25(users_fts, 0.0, 10.0, 5.0)
+            LIMIT ?3
+            "#
+        )
+        .bind(&match_query)
+        .bind(&current_user.id)
+        .bind(limit)
+        .fetch_all(&state.db)
+        .await;
+
+        match users {
+            Ok(users) => {
+                for user in users {
+                    response.users.push(user_response_for(&state, user, &current_user.id).await);
+                }
+            }
+            Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Failed to search users: {}", e)
+            })),
+        }
+    }
+
+    if types.iter().any(|t| t == "groups") {
+        // Private groups are only searchable by their members
+        let matches = sqlx::query_as::<_, GroupMatch>(
+            r#"
+            SELECT g.*, snippet(groups_fts, 2, char(2), char(3), '…', 12) AS snippet, bm25(groups_fts, 0.0, 10.0, 1.0) AS rank
+            FROM groups_fts
+            INNER JOIN groups g ON g.id = groups_fts.group_id
+            WHERE groups_fts MATCH ?1
+              AND (g.is_private = 0 OR g.id IN (SELECT group_id FROM group_members WHERE user_id = ?2))
+            ORDER BY rank
+            LIMIT ?3
+            "#
+        )
+        .bind(&match_query)
+        .bind(&current_user.id)
+        .bind(limit)
+        .fetch_all(&state.db)
+        .await;
+
+        match matches {
+            Ok(matches) => {
+                for m in matches {
+                    response.groups.push(GroupSearchResult {
+                        group: build_group_response(&state, &m.group, &current_user.id).await,
+                        snippet: highlight(&m.snippet),
+                        rank: m.rank,
+                    });
+                }
+            }
+            Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Failed to search groups: {}", e)
+            })),
+        }
+    }
+
+    HttpResponse::Ok().json(response)
+}
+
+// Helper functions
+
+/// Turns free text into an FTS5 query that matches every term as a prefix.
+/// Terms are quoted so user input can't use FTS5 operators or syntax.
+pub fn build_match_query(q: &str) -> Option<String> {
+    let terms: Vec<String> = q
+        .split_whitespace()
+        .map(|term| term.replace('"', ""))
+        .filter(|term| !term.is_empty())
+        .map(|term| format!("\"{}\"*", term))
+        .collect();
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+/// HTML-escapes a snippet and turns the match markers into `<mark>` tags.
+fn highlight(snippet: &str) -> String {
+    snippet
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+        .replace('\u{2}', "<mark>")
+        .replace('\u{3}', "</mark>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{auth_request, response_json, test_state, visibility_fixture};
+
+    #[actix_rt::test]
+    async fn post_search_only_finds_posts_the_viewer_may_see() {
+        let state = test_state().await;
+        let (viewer, personal, group) = visibility_fixture(&state).await;
+
+        let response = search(auth_request(&viewer), state.clone(), web::Query::from_query("q=zebras&type=posts").unwrap()).await;
+        assert_eq!(response.status().as_u16(), 200);
+        let results = response_json(response).await;
+
+        let mut found: Vec<&str> = results["posts"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["post"]["content"].as_str().unwrap())
+            .collect();
+        found.sort();
+        let mut expected: Vec<&str> = personal.into_iter().chain(group).collect();
+        expected.sort();
+        assert_eq!(found, expected);
+    }
+}
@@ -0,0 +1,420 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+
+use crate::auth::require_auth;
+use crate::handlers::posts::get_friend_ids;
+use crate::models::{CreateStoryRequest, PostVisibility, Story, StoryGroup, StoryResponse, StoryViewer, User};
+use crate::policy::can_view_story;
+use crate::AppState;
+
+/// How long a story lasts unless the author picks a duration.
+const DEFAULT_STORY_DURATION_HOURS: i64 = 24;
+const MAX_STORY_DURATION_HOURS: i64 = 168;
+
+pub async fn create_story(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<CreateStoryRequest>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let content = body.content.clone().unwrap_or_default();
+    let media_url = body.media_url.clone().filter(|url| !url.trim().is_empty());
+
+    if content.trim().is_empty() && media_url.is_none() {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "A story needs content or media"
+        }));
+    }
+
+    let visibility = body
+        .visibility
+        .clone()
+        .map(|v| PostVisibility::from(v).to_string())
+        .unwrap_or_else(|| PostVisibility::FriendsOnly.to_string());
+
+    if visibility != "public" && visibility != "friends_only" {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Stories can only be public or friends_only"
+        }));
+    }
+
+    let duration_hours = body.duration_hours.unwrap_or(DEFAULT_STORY_DURATION_HOURS);
+    if !(1..=MAX_STORY_DURATION_HOURS).contains(&duration_hours) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("duration_hours must be between 1 and {}", MAX_STORY_DURATION_HOURS)
+        }));
+    }
+
+    let now = Utc::now();
+    let story = Story {
+        id: Uuid::new_v4().to_string(),
+        user_id: current_user.id.clone(),
+        content,
+        media_url,
+        visibility,
+        created_at: now.to_rfc3339(),
+        expires_at: (now + Duration::hours(duration_hours)).to_rfc3339(),
+    };
+
+    let result = sqlx::query(
+        "INSERT INTO stories (id, user_id, content, media_url, visibility, created_at, expires_at) VALUES (?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(&story.id)
+    .bind(&story.user_id)
+    .bind(&story.content)
+    .bind(&story.media_url)
+    .bind(&story.visibility)
+    .bind(&story.created_at)
+    .bind(&story.expires_at)
+    .execute(&state.db)
+    .await;
+
+    match result {
+        Ok(_) => HttpResponse::Created().json(build_story_response(&state, story, &current_user.id).await),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to create story: {}", e)
+        })),
+    }
+}
+
+/// Active stories from the user and their friends, grouped by author: your own
+/// first, then authors with stories you haven't seen, then by latest story.
+pub async fn get_stories(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let friend_ids = get_friend_ids(&state, &current_user.id).await;
+    let friend_ids_json = serde_json::to_string(&friend_ids).unwrap_or_else(|_| "[]".to_string());
+    let now = Utc::now().to_rfc3339();
+
+    // Friends see both public and friends_only stories, so no visibility filter is needed
+    let stories = sqlx::query_as::<_, Story>(
+        r#"
+        SELECT * FROM stories
+        WHERE expires_at > ? AND (user_id = ? OR user_id IN (SELECT value FROM json_each(?)))
+        AND user_id NOT IN (
+            SELECT target_id FROM mutes
+            WHERE user_id = ? AND target_type = 'user' AND (expires_at IS NULL OR expires_at > ?)
+        )
+        ORDER BY created_at ASC
+        "#
+    )
+    .bind(&now)
+    .bind(&current_user.id)
+    .bind(&friend_ids_json)
+    .bind(&current_user.id)
+    .bind(&now)
+    .fetch_all(&state.db)
+    .await;
+
+    let stories = match stories {
+        Ok(stories) => stories,
+        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to get stories: {}", e)
+        })),
+    };
+
+    let mut groups: Vec<StoryGroup> = Vec::new();
+    for story in stories {
+        let author_id = story.user_id.clone();
+        let response = build_story_response(&state, story, &current_user.id).await;
+
+        if let Some(index) = groups.iter().position(|g| g.author.id == author_id) {
+            groups[index].has_unseen |= !response.seen;
+            groups[index].stories.push(response);
+            continue;
+        }
+
+        let author = match sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+            .bind(&author_id)
+            .fetch_optional(&state.db)
+            .await
+        {
+            Ok(Some(author)) => author,
+            _ => continue,
+        };
+
+        groups.push(StoryGroup {
+            author: author.into(),
+            has_unseen: !response.seen,
+            stories: vec![response],
+        });
+    }
+
+    groups.sort_by(|a, b| {
+        let latest = |g: &StoryGroup| g.stories.last().map(|s| s.created_at.clone()).unwrap_or_default();
+        (b.author.id == current_user.id)
+            .cmp(&(a.author.id == current_user.id))
+            .then(b.has_unseen.cmp(&a.has_unseen))
+            .then(latest(b).cmp(&latest(a)))
+    });
+
+    HttpResponse::Ok().json(groups)
+}
+
+/// Returns a story and marks it as seen by the viewer.
+pub async fn get_story(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let story = match fetch_active_story(&state, &path.into_inner()).await {
+        Ok(story) => story,
+        Err(response) => return response,
+    };
+
+    if !can_view_story(&state, &story, &current_user.id).await {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "You don't have permission to view this story"
+        }));
+    }
+
+    if story.user_id != current_user.id {
+        let _ = sqlx::query(
+            "INSERT OR IGNORE INTO story_views (story_id, viewer_id, viewed_at) VALUES (?, ?, ?)"
+        )
+        .bind(&story.id)
+        .bind(&current_user.id)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&state.db)
+        .await;
+    }
+
+    HttpResponse::Ok().json(build_story_response(&state, story, &current_user.id).await)
+}
+
+/// Who has seen a story, most recent first. Only the author can see this.
+pub async fn get_story_viewers(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let story = match fetch_active_story(&state, &path.into_inner()).await {
+        Ok(story) => story,
+        Err(response) => return response,
+    };
+
+    if story.user_id != current_user.id {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Only the author can see who viewed a story"
+        }));
+    }
+
+    let views: Vec<(String, String)> = match sqlx::query_as(
+        "SELECT viewer_id, viewed_at FROM story_views WHERE story_id = ? ORDER BY viewed_at DESC"
+    )
+    .bind(&story.id)
+    .fetch_all(&state.db)
+    .await
+    {
+        Ok(views) => views,
+        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to get story viewers: {}", e)
+        })),
+    };
+
+    let mut viewers: Vec<StoryViewer> = Vec::new();
+    for (viewer_id, viewed_at) in views {
+        if let Ok(Some(user)) = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+            .bind(&viewer_id)
+            .fetch_optional(&state.db)
+            .await
+        {
+            viewers.push(StoryViewer {
+                user: user.into(),
+                viewed_at,
+            });
+        }
+    }
+
+    HttpResponse::Ok().json(viewers)
+}
+
+pub async fn delete_story(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    let story = match fetch_active_story(&state, &path.into_inner()).await {
+        Ok(story) => story,
+        Err(response) => return response,
+    };
+
+    if story.user_id != current_user.id {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "You can only delete your own stories"
+        }));
+    }
+
+    match delete_stories(&state.db, &[story.id]).await {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Story deleted"
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to delete story: {}", e)
+        })),
+    }
+}
+
+// Helper functions
+
+/// Hard-deletes stories along with their views.
+pub async fn delete_stories(pool: &sqlx::SqlitePool, story_ids: &[String]) -> Result<(), sqlx::Error> {
+    let ids_json = serde_json::to_string(story_ids).unwrap_or_else(|_| "[]".to_string());
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM story_views WHERE story_id IN (SELECT value FROM json_each(?))")
+        .bind(&ids_json)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM stories WHERE id IN (SELECT value FROM json_each(?))")
+        .bind(&ids_json)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await
+}
+
+/// Fetches a story that hasn't expired yet; expired stories are gone even before
+/// the sweeper deletes them.
+async fn fetch_active_story(state: &web::Data<AppState>, story_id: &str) -> Result<Story, HttpResponse> {
+    match sqlx::query_as::<_, Story>("SELECT * FROM stories WHERE id = ? AND expires_at > ?")
+        .bind(story_id)
+        .bind(Utc::now().to_rfc3339())
+        .fetch_optional(&state.db)
+        .await
+    {
+        Ok(Some(story)) => Ok(story),
+        Ok(None) => Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Story not found"
+        }))),
+        Err(e) => Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        }))),
+    }
+}
+
+async fn build_story_response(state: &web::Data<AppState>, story: Story, current_user_id: &str) -> StoryResponse {
+    let is_author = story.user_id == current_user_id;
+
+    let seen = is_author || sqlx::query_scalar::<_, i32>(
+        "SELECT COUNT(*) FROM story_views WHERE story_id = ? AND viewer_id = ?"
+    )
+    .bind(&story.id)
+    .bind(current_user_id)
+    .fetch_one(&state.db)
+    .await
+    .unwrap_or(0) > 0;
+
+    let views_count = if is_author {
+        sqlx::query_scalar("SELECT COUNT(*) FROM story_views WHERE story_id = ?")
+            .bind(&story.id)
+            .fetch_one(&state.db)
+            .await
+            .ok()
+    } else {
+        None
+    };
+
+    StoryResponse {
+        id: story.id,
+        content: story.content,
+        media_url: story.media_url,
+        visibility: story.visibility,
+        seen,
+        views_count,
+        created_at: story.created_at,
+        expires_at: story.expires_at,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::blocks::block_user;
+    use crate::test_support::{auth_request, befriend, create_user, json, response_json, test_state};
+
+    async fn story_as(state: &web::Data<AppState>, user_id: &str, visibility: &str) -> String {
+        let response = create_story(
+            auth_request(user_id),
+            state.clone(),
+            json(serde_json::json!({"content": "at the zoo", "visibility": visibility})),
+        )
+        .await;
+        assert_eq!(response.status().as_u16(), 201);
+        response_json(response).await["id"].as_str().unwrap().to_string()
+    }
+
+    async fn story_status(state: &web::Data<AppState>, viewer: &str, story_id: &str) -> u16 {
+        get_story(auth_request(viewer), state.clone(), web::Path::from(story_id.to_string()))
+            .await
+            .status()
+            .as_u16()
+    }
+
+    #[actix_rt::test]
+    async fn blocked_users_lose_sight_of_a_friends_stories() {
+        let state = test_state().await;
+        let viewer = create_user(&state, "viewer").await;
+        let friend = create_user(&state, "friend").await;
+        befriend(&state, &viewer, &friend).await;
+        let story_id = story_as(&state, &friend, "friends_only").await;
+
+        let stories = response_json(get_stories(auth_request(&viewer), state.clone()).await).await;
+        assert_eq!(stories.as_array().unwrap().len(), 1);
+        assert_eq!(story_status(&state, &viewer, &story_id).await, 200);
+
+        let response = block_user(auth_request(&friend), state.clone(), web::Path::from(viewer.clone())).await;
+        assert!(response.status().is_success());
+
+        let stories = response_json(get_stories(auth_request(&viewer), state.clone()).await).await;
+        assert!(stories.as_array().unwrap().is_empty());
+        assert_eq!(story_status(&state, &viewer, &story_id).await, 403);
+    }
+
+    #[actix_rt::test]
+    async fn expired_stories_are_gone_before_the_sweeper_runs() {
+        let state = test_state().await;
+        let author = create_user(&state, "author").await;
+        let viewer = create_user(&state, "viewer").await;
+        let story_id = story_as(&state, &author, "public").await;
+        assert_eq!(story_status(&state, &viewer, &story_id).await, 200);
+
+        sqlx::query("UPDATE stories SET expires_at = ? WHERE id = ?")
+            .bind((Utc::now() - Duration::minutes(1)).to_rfc3339())
+            .bind(&story_id)
+            .execute(&state.db)
+            .await
+            .unwrap();
+
+        assert_eq!(story_status(&state, &viewer, &story_id).await, 404);
+        assert_eq!(story_status(&state, &author, &story_id).await, 404);
+        let stories = response_json(get_stories(auth_request(&author), state.clone()).await).await;
+        assert!(stories.as_array().unwrap().is_empty());
+    }
+}
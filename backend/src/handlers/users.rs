@@ -2,7 +2,9 @@ use actix_web::{web, HttpRequest, HttpResponse};
 use serde::Deserialize;
 
 use crate::auth::require_auth;
-use crate::models::{User, UserResponse};
+use crate::handlers::posts::build_post_response;
+use crate::models::{Friendship, GroupSummary, Post, PostResponse, ProfileResponse, SettingsResponse, UpdateSettingsRequest, User, UserResponse};
+use crate::policy::{audience_permits, load_closeness, visible_visibilities};
 use crate::AppState;
 
 #[derive(Deserialize)]
@@ -10,6 +12,12 @@ pub struct SearchQuery {
     pub q: Option<String>,
 }
 
+#[derive(Deserialize)]
+pub struct ProfileQuery {
+    pub page: Option<i64>,
+    pub limit: Option<i64>,
+}
+
 pub async fn search_users(
     req: HttpRequest,
     state: web::Data<AppState>,
@@ -21,29 +29,73 @@ pub async fn search_users(
     };
 
     let search_term = query.q.clone().unwrap_or_default();
-    
+
+    // Users choose whether everyone, only friends of friends, or nobody can find them
     let users = if search_term.is_empty() {
         sqlx::query_as::<_, User>(
-            "SELECT * FROM users WHERE id != ? LIMIT 50"
+            r#"
+            SELECT * FROM users
+            WHERE id != ?
+              AND id NOT IN (SELECT blocked_id FROM blocks WHERE blocker_id = ?)
+              AND id NOT IN (SELECT blocker_id FROM blocks WHERE blocked_id = ?)
+              AND (
+                  searchable_by = 'everyone'
+                  OR (searchable_by = 'friends_of_friends' AND id IN (
+                      SELECT friend_id FROM friend_edges WHERE user_id = ?
+                      UNION
+                      SELECT fof.friend_id FROM friend_edges f
+                      INNER JOIN friend_edges fof ON fof.user_id = f.friend_id
+                      WHERE f.user_id = ?
+                  ))
+              )
+            LIMIT 50
+            "#
         )
         .bind(&current_user.id)
+        .bind(&current_user.id)
+        .bind(&current_user.id)
+        .bind(&current_user.id)
+        .bind(&current_user.id)
         .fetch_all(&state.db)
         .await
     } else {
-        let search_pattern = format!("%{}%", search_term);
+        let search_pattern = format!("%{}%", escape_like(&search_term));
         sqlx::query_as::<_, User>(
-            "SELECT * FROM users WHERE id != ? AND (username LIKE ? OR display_name LIKE ?) LIMIT 50"
+            r#"
+            SELECT * FROM users
+            WHERE id != ? AND (username LIKE ? ESCAPE '\' OR display_name LIKE ? ESCAPE '\')
+              AND id NOT IN (SELECT blocked_id FROM blocks WHERE blocker_id = ?)
+              AND id NOT IN (SELECT blocker_id FROM blocks WHERE blocked_id = ?)
+              AND (
+                  searchable_by = 'everyone'
+                  OR (searchable_by = 'friends_of_friends' AND id IN (
+                      SELECT friend_id FROM friend_edges WHERE user_id = ?
+                      UNION
+                      SELECT fof.friend_id FROM friend_edges f
+                      INNER JOIN friend_edges fof ON fof.user_id = f.friend_id
+                      WHERE f.user_id = ?
+                  ))
+              )
+            LIMIT 50
+            "#
         )
         .bind(&current_user.id)
         .bind(&search_pattern)
         .bind(&search_pattern)
+        .bind(&current_user.id)
+        .bind(&current_user.id)
+        .bind(&current_user.id)
+        .bind(&current_user.id)
         .fetch_all(&state.db)
         .await
     };
 
     match users {
         Ok(users) => {
-            let user_responses: Vec<UserResponse> = users.into_iter().map(|u| u.into()).collect();
+            let mut user_responses: Vec<UserResponse> = Vec::new();
+            for user in users {
+                user_responses.push(user_response_for(&state, user, &current_user.id).await);
+            }
             HttpResponse::Ok().json(user_responses)
         }
         Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
@@ -56,8 +108,9 @@ pub async fn get_user(
     req: HttpRequest,
     state: web::Data<AppState>,
     path: web::Path<String>,
+    query: web::Query<ProfileQuery>,
 ) -> HttpResponse {
-    let _current_user = match require_auth(&req, &state).await {
+    let current_user = match require_auth(&req, &state).await {
         Ok(user) => user,
         Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
     };
@@ -69,13 +122,447 @@ pub async fn get_user(
         .fetch_optional(&state.db)
         .await;
 
-    match user {
-        Ok(Some(user)) => HttpResponse::Ok().json(UserResponse::from(user)),
-        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
+    let user = match user {
+        Ok(Some(user)) => user,
+        Ok(None) => return HttpResponse::NotFound().json(serde_json::json!({
             "error": "User not found"
         })),
-        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({
             "error": format!("Database error: {}", e)
         })),
+    };
+
+    let relationship = get_relationship(&state, &current_user.id, &user.id).await;
+
+    // Users who blocked the viewer look like they don't exist
+    if relationship == "blocked_by" {
+        return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "User not found"
+        }));
+    }
+
+    // Mutual friends come out of the same friend list
+    let friends_visible = relationship != "blocked"
+        && audience_allows(&state, &user.friends_list_visibility, &user.id, &current_user.id).await;
+
+    let friends_count: Option<i32> = if friends_visible {
+        sqlx::query_scalar("SELECT COUNT(*) FROM friend_edges WHERE user_id = ?")
+            .bind(&user.id)
+            .fetch_one(&state.db)
+            .await
+            .ok()
+    } else {
+        None
+    };
+
+    let (mutual_friends_count, mutual_friends, shared_groups, posts_count, posts) = if relationship == "blocked" {
+        (0, Vec::new(), Vec::new(), 0, Vec::new())
+    } else {
+        let (mutual_friends_count, mutual_friends) = if friends_visible {
+            load_mutual_friends(&state, &current_user.id, &user.id).await
+        } else {
+            (0, Vec::new())
+        };
+
+        let shared_groups = sqlx::query_as::<_, GroupSummary>(
+            r#"
+            SELECT g.id, g.name FROM groups g
+            INNER JOIN group_members mine ON mine.group_id = g.id AND mine.user_id = ?
+            INNER JOIN group_members theirs ON theirs.group_id = g.id AND theirs.user_id = ?
+            ORDER BY g.name
+            "#
+        )
+        .bind(&current_user.id)
+        .bind(&user.id)
+        .fetch_all(&state.db)
+        .await
+        .unwrap_or_default();
+
+        // Page through the personal posts this viewer is allowed to see
+        let visibilities = visible_visibilities(&state, &user.id, &current_user.id).await;
+        let visibilities_json = serde_json::to_string(&visibilities).unwrap_or_else(|_| "[]".to_string());
+
+        let posts_count: i32 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM posts
+            WHERE user_id = ? AND group_id IS NULL AND status = 'published' AND visibility IN (SELECT value FROM json_each(?))
+              AND (visibility != 'custom' OR user_id = ? OR id IN (
+                  SELECT post_id FROM post_audience
+                  WHERE (kind = 'user' AND target_id = ?)
+                     OR (kind = 'list' AND target_id IN (SELECT list_id FROM friend_list_members WHERE user_id = ?))
+              ))
+              AND id NOT IN (SELECT post_id FROM post_audience WHERE kind = 'except' AND target_id = ?)
+            "#
+        )
+        .bind(&user.id)
+        .bind(&visibilities_json)
+        .bind(&current_user.id)
+        .bind(&current_user.id)
+        .bind(&current_user.id)
+        .bind(&current_user.id)
+        .fetch_one(&state.db)
+        .await
+        .unwrap_or(0);
+
+        let limit = query.limit.unwrap_or(20).clamp(1, 50);
+        let offset = query.page.unwrap_or(0).max(0) * limit;
+
+        let post_rows = sqlx::query_as::<_, Post>(
+            r#"
+            SELECT * FROM posts
+            WHERE user_id = ? AND group_id IS NULL AND status = 'published' AND visibility IN (SELECT value FROM json_each(?))
+              AND (visibility != 'custom' OR user_id = ? OR id IN (
+                  SELECT post_id FROM post_audience
+                  WHERE (kind = 'user' AND target_id = ?)
+                     OR (kind = 'list' AND target_id IN (SELECT list_id FROM friend_list_members WHERE user_id = ?))
+              ))
+              AND id NOT IN (SELECT post_id FROM post_audience WHERE kind = 'except' AND target_id = ?)
+            ORDER BY created_at DESC
+            LIMIT ? OFFSET ?
+            "#
+        )
+        .bind(&user.id)
+        .bind(&visibilities_json)
+        .bind(&current_user.id)
+        .bind(&current_user.id)
+        .bind(&current_user.id)
+        .bind(&current_user.id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&state.db)
+        .await
+        .unwrap_or_default();
+
+        let mut posts: Vec<PostResponse> = Vec::new();
+        for post in post_rows {
+            posts.push(build_post_response(&state, &post, &current_user.id).await);
+        }
+
+        (
+            mutual_friends_count,
+            mutual_friends.into_iter().map(UserResponse::from).collect(),
+            shared_groups,
+            posts_count,
+            posts,
+        )
+    };
+
+    HttpResponse::Ok().json(ProfileResponse {
+        user: user_response_for(&state, user, &current_user.id).await,
+        relationship,
+        mutual_friends_count,
+        mutual_friends,
+        friends_count,
+        posts_count,
+        shared_groups,
+        posts,
+    })
+}
+
+pub async fn get_settings(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> HttpResponse {
+    match require_auth(&req, &state).await {
+        Ok(user) => HttpResponse::Ok().json(SettingsResponse::from(user)),
+        Err(e) => HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    }
+}
+
+pub async fn update_settings(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<UpdateSettingsRequest>,
+) -> HttpResponse {
+    let current_user = match require_auth(&req, &state).await {
+        Ok(user) => user,
+        Err(e) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})),
+    };
+
+    // Field visibility can be limited to friends; discoverability only to friends of friends
+    let field_audiences = ["everyone", "friends_of_friends", "friends", "nobody"];
+    let reach_audiences = ["everyone", "friends_of_friends", "nobody"];
+
+    let checks = [
+        ("email_visibility", &body.email_visibility, &field_audiences[..]),
+        ("bio_visibility", &body.bio_visibility, &field_audiences[..]),
+        ("friends_list_visibility", &body.friends_list_visibility, &field_audiences[..]),
+        ("searchable_by", &body.searchable_by, &reach_audiences[..]),
+        ("friend_requests_from", &body.friend_requests_from, &reach_audiences[..]),
+    ];
+
+    for (field, value, allowed) in checks {
+        if let Some(value) = value {
+            if !allowed.contains(&value.as_str()) {
+                return HttpResponse::BadRequest().json(serde_json::json!({
+                    "error": format!("Invalid value for {}: must be one of {}", field, allowed.join(", "))
+                }));
+            }
+        }
+    }
+
+    let requires_follow_approval = body
+        .requires_follow_approval
+        .unwrap_or(current_user.requires_follow_approval);
+
+    let mut updated = current_user.clone();
+    updated.requires_follow_approval = requires_follow_approval;
+    updated.email_visibility = body.email_visibility.clone().unwrap_or(updated.email_visibility);
+    updated.bio_visibility = body.bio_visibility.clone().unwrap_or(updated.bio_visibility);
+    updated.friends_list_visibility = body.friends_list_visibility.clone().unwrap_or(updated.friends_list_visibility);
+    updated.searchable_by = body.searchable_by.clone().unwrap_or(updated.searchable_by);
+    updated.friend_requests_from = body.friend_requests_from.clone().unwrap_or(updated.friend_requests_from);
+
+    let result = sqlx::query(
+        r#"
+        UPDATE users SET
+            requires_follow_approval = ?,
+            email_visibility = ?,
+            bio_visibility = ?,
+            friends_list_visibility = ?,
+            searchable_by = ?,
+            friend_requests_from = ?
+        WHERE id = ?
+        "#
+    )
+    .bind(updated.requires_follow_approval)
+    .bind(&updated.email_visibility)
+    .bind(&updated.bio_visibility)
+    .bind(&updated.friends_list_visibility)
+    .bind(&updated.searchable_by)
+    .bind(&updated.friend_requests_from)
+    .bind(&current_user.id)
+    .execute(&state.db)
+    .await;
+
+    if let Err(e) = result {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to update settings: {}", e)
+        }));
+    }
+
+    // Opening up a private account lets everyone who was waiting in
+    if current_user.requires_follow_approval && !requires_follow_approval {
+        let _ = sqlx::query("UPDATE follows SET status = 'accepted' WHERE followee_id = ? AND status = 'pending'")
+            .bind(&current_user.id)
+            .execute(&state.db)
+            .await;
+    }
+
+    HttpResponse::Ok().json(SettingsResponse::from(updated))
+}
+
+// Helper functions
+
+/// How many friends two users have in common, and the first of them by name.
+async fn load_mutual_friends(state: &web::Data<AppState>, viewer_id: &str, user_id: &str) -> (i32, Vec<User>) {
+    let mutual_friends_count: i32 = sqlx::query_scalar(
+        r#"
+        SELECT COUNT(*) FROM friend_edges
+        WHERE user_id = ? AND friend_id IN (SELECT friend_id FROM friend_edges WHERE user_id = ?)
+        "#
+    )
+    .bind(viewer_id)
+    .bind(user_id)
+    .fetch_one(&state.db)
+    .await
+    .unwrap_or(0);
+
+    let mutual_friends = sqlx::query_as::<_, User>(
+        r#"
+        SELECT * FROM users
+        WHERE id IN (SELECT friend_id FROM friend_edges WHERE user_id = ?)
+          AND id IN (SELECT friend_id FROM friend_edges WHERE user_id = ?)
+        ORDER BY display_name
+        LIMIT 20
+        "#
+    )
+    .bind(viewer_id)
+    .bind(user_id)
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+
+    (mutual_friends_count, mutual_friends)
+}
+
+/// Describes how `viewer_id` relates to `user_id`. `blocked_by` is internal and
+/// never returned to clients.
+async fn get_relationship(state: &web::Data<AppState>, viewer_id: &str, user_id: &str) -> String {
+    if viewer_id == user_id {
+        return "self".to_string();
+    }
+
+    let blocks: Vec<String> = sqlx::query_scalar(
+        "SELECT blocker_id FROM blocks WHERE (blocker_id = ? AND blocked_id = ?) OR (blocker_id = ? AND blocked_id = ?)"
+    )
+    .bind(viewer_id)
+    .bind(user_id)
+    .bind(user_id)
+    .bind(viewer_id)
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+
+    if blocks.iter().any(|blocker_id| blocker_id == viewer_id) {
+        return "blocked".to_string();
+    }
+    if !blocks.is_empty() {
+        return "blocked_by".to_string();
+    }
+
+    let friendship = sqlx::query_as::<_, Friendship>(
+        "SELECT * FROM friendships WHERE ((user_id = ? AND friend_id = ?) OR (user_id = ? AND friend_id = ?)) AND status IN ('pending', 'accepted')"
+    )
+    .bind(viewer_id)
+    .bind(user_id)
+    .bind(user_id)
+    .bind(viewer_id)
+    .fetch_optional(&state.db)
+    .await
+    .ok()
+    .flatten();
+
+    match friendship {
+        Some(f) if f.status == "accepted" => "friends".to_string(),
+        Some(f) if f.user_id == viewer_id => "pending_outgoing".to_string(),
+        Some(_) => "pending_incoming".to_string(),
+        None => "none".to_string(),
+    }
+}
+
+/// Returns true if `viewer_id` falls within `audience` (everyone, friends_of_friends,
+/// friends or nobody) of `owner_id`. Owners are always in their own audience.
+pub async fn audience_allows(state: &web::Data<AppState>, audience: &str, owner_id: &str, viewer_id: &str) -> bool {
+    audience_permits(audience, load_closeness(state, owner_id, viewer_id).await)
+}
+
+/// The view of `user` that `viewer_id` is allowed to see under the user's privacy settings.
+pub async fn user_response_for(state: &web::Data<AppState>, user: User, viewer_id: &str) -> UserResponse {
+    if user.id == viewer_id {
+        return UserResponse::for_self(user);
+    }
+
+    let show_email = audience_allows(state, &user.email_visibility, &user.id, viewer_id).await;
+    let show_bio = audience_allows(state, &user.bio_visibility, &user.id, viewer_id).await;
+
+    UserResponse {
+        id: user.id,
+        email: if show_email { Some(user.email) } else { None },
+        username: user.username,
+        display_name: user.display_name,
+        avatar_url: user.avatar_url,
+        bio: if show_bio { user.bio } else { None },
+    }
+}
+
+/// Escapes `%`, `_` and the escape character itself so user input is matched
+/// literally by `LIKE ... ESCAPE '\'`.
+pub fn escape_like(term: &str) -> String {
+    term.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::friends::send_friend_request;
+    use crate::handlers::posts::create_post;
+    use crate::test_support::{auth_request, befriend, block, create_group, create_user, json, response_json, test_state};
+
+    async fn profile(state: &web::Data<AppState>, viewer: &str, user_id: &str) -> HttpResponse {
+        get_user(auth_request(viewer), state.clone(), web::Path::from(user_id.to_string()), web::Query::from_query("").unwrap()).await
+    }
+
+    #[actix_rt::test]
+    async fn profiles_show_what_the_viewer_shares_with_the_user() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+        let carol = create_user(&state, "carol").await;
+        befriend(&state, &alice, &bob).await;
+        befriend(&state, &bob, &carol).await;
+        befriend(&state, &alice, &carol).await;
+        create_group(&state, &alice, &[&bob]).await;
+
+        for (content, visibility) in [("For everyone", "public"), ("For friends", "friends_only")] {
+            let response = create_post(auth_request(&bob), state.clone(), json(serde_json::json!({"content": content, "visibility": visibility}))).await;
+            assert_eq!(response.status().as_u16(), 201);
+        }
+
+        let response = profile(&state, &alice, &bob).await;
+        assert_eq!(response.status().as_u16(), 200);
+        let body = response_json(response).await;
+        assert_eq!(body["relationship"], "friends");
+        assert_eq!(body["mutual_friends_count"], 1);
+        assert_eq!(body["mutual_friends"][0]["id"], carol.as_str());
+        assert_eq!(body["friends_count"], 2);
+        assert_eq!(body["shared_groups"].as_array().unwrap().len(), 1);
+        assert_eq!(body["posts_count"], 2);
+
+        let stranger = create_user(&state, "stranger").await;
+        let body = response_json(profile(&state, &stranger, &bob).await).await;
+        assert_eq!(body["relationship"], "none");
+        assert_eq!(body["mutual_friends_count"], 0);
+        assert_eq!(body["posts_count"], 1);
+        assert_eq!(body["posts"][0]["content"], "For everyone");
+
+        // Blocking hides everything but the user; being blocked hides the user too
+        block(&state, &stranger, &bob).await;
+        let body = response_json(profile(&state, &stranger, &bob).await).await;
+        assert_eq!(body["relationship"], "blocked");
+        assert_eq!(body["posts_count"], 0);
+        assert!(body["friends_count"].is_null());
+        assert_eq!(profile(&state, &bob, &stranger).await.status().as_u16(), 404);
+    }
+
+    async fn update(state: &web::Data<AppState>, user_id: &str, body: serde_json::Value) -> HttpResponse {
+        update_settings(auth_request(user_id), state.clone(), json(body)).await
+    }
+
+    #[actix_rt::test]
+    async fn privacy_settings_reach_profiles_search_and_friend_requests() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+        let carol = create_user(&state, "carol").await;
+        befriend(&state, &alice, &bob).await;
+
+        // Discoverability can't be limited to friends only
+        assert_eq!(update(&state, &alice, serde_json::json!({"searchable_by": "friends"})).await.status().as_u16(), 400);
+
+        let response = update(&state, &alice, serde_json::json!({
+            "email_visibility": "friends",
+            "searchable_by": "friends_of_friends",
+            "friend_requests_from": "nobody"
+        }))
+        .await;
+        assert_eq!(response.status().as_u16(), 200);
+        let settings = response_json(get_settings(auth_request(&alice), state.clone()).await).await;
+        assert_eq!(settings["email_visibility"], "friends");
+        assert_eq!(settings["bio_visibility"], "everyone");
+
+        let email = |viewer: String| {
+            let state = state.clone();
+            let alice = alice.clone();
+            async move { response_json(profile(&state, &viewer, &alice).await).await["email"].clone() }
+        };
+        assert_eq!(email(bob.clone()).await, "alice@example.com");
+        assert!(email(carol.clone()).await.is_null());
+
+        let found = |viewer: String| {
+            let state = state.clone();
+            async move {
+                let response = search_users(auth_request(&viewer), state, web::Query::from_query("q=alice").unwrap()).await;
+                response_json(response).await.as_array().unwrap().len()
+            }
+        };
+        assert_eq!(found(carol.clone()).await, 0);
+        befriend(&state, &bob, &carol).await;
+        assert_eq!(found(carol.clone()).await, 1);
+
+        let response = send_friend_request(auth_request(&carol), state.clone(), web::Path::from(alice.clone())).await;
+        assert_eq!(response.status().as_u16(), 403);
     }
 }
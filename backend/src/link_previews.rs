@@ -0,0 +1,601 @@
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration as StdDuration;
+
+use actix_web::web;
+use chrono::{Duration, Utc};
+use reqwest::{header, redirect, Url};
+use sqlx::SqlitePool;
+
+use crate::models::LinkPreview;
+use crate::AppState;
+
+/// How often queued URLs are looked for.
+pub const LINK_PREVIEW_INTERVAL_SECONDS: u64 = 10;
+
+/// Cached previews are fetched again when a new post links them after this long.
+const LINK_PREVIEW_TTL_DAYS: i64 = 7;
+
+/// How many queued URLs are fetched per run.
+const LINK_PREVIEW_BATCH_SIZE: i64 = 20;
+
+const FETCH_TIMEOUT_SECONDS: u64 = 5;
+const MAX_REDIRECTS: usize = 3;
+
+/// Only the start of a page is read; OpenGraph tags and the title live in its head.
+const MAX_BODY_BYTES: usize = 256 * 1024;
+
+/// A fetched HTML page and the URL it was finally served from.
+pub struct FetchedPage {
+    pub url: Url,
+    pub html: String,
+}
+
+/// Fetches pages for link previews. The background previewer runs with
+/// `HttpFetcher`; tests can substitute a stub.
+pub trait PreviewFetcher: 'static {
+    fn fetch(&self, url: &str) -> impl Future<Output = Result<FetchedPage, String>>;
+}
+
+/// Fetches pages over HTTP(S) from public addresses only.
+///
+/// Hosts are resolved here and every address checked before connecting, and the
+/// connection is pinned to the checked address so a second DNS answer can't point
+/// it somewhere else. Redirects are followed by hand so each hop gets the same
+/// checks. Responses are limited in time and size.
+pub struct HttpFetcher {
+    timeout: StdDuration,
+    allow_private_addresses: bool,
+}
+
+impl HttpFetcher {
+    pub fn new() -> Self {
+        HttpFetcher {
+            timeout: StdDuration::from_secs(FETCH_TIMEOUT_SECONDS),
+            allow_private_addresses: false,
+        }
+    }
+
+    /// Resolves the URL's host to an address that is safe to connect to.
+    async fn resolve(&self, url: &Url) -> Result<SocketAddr, String> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(format!("Unsupported scheme: {}", url.scheme()));
+        }
+
+        let host = url.host_str().ok_or("URL has no host")?;
+        let port = url.port_or_known_default().ok_or("URL has no port")?;
+
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.trim_matches(['[', ']']), port))
+            .await
+            .map_err(|e| format!("Failed to resolve {}: {}", host, e))?
+            .collect();
+
+        if addrs.is_empty() {
+            return Err(format!("Failed to resolve {}", host));
+        }
+
+        // Any private answer rules the host out, not just the one we'd pick
+        if !self.allow_private_addresses && addrs.iter().any(|addr| !is_public_address(addr.ip())) {
+            return Err(format!("{} resolves to a private address", host));
+        }
+
+        Ok(addrs[0])
+    }
+}
+
+impl PreviewFetcher for HttpFetcher {
+    async fn fetch(&self, url: &str) -> Result<FetchedPage, String> {
+        let mut url = Url::parse(url).map_err(|e| format!("Invalid URL: {}", e))?;
+
+        for _ in 0..=MAX_REDIRECTS {
+            let addr = self.resolve(&url).await?;
+            let host = url.host_str().unwrap_or_default().to_string();
+
+            let client = reqwest::Client::builder()
+                .redirect(redirect::Policy::none())
+                .no_proxy()
+                .timeout(self.timeout)
+                .resolve(&host, addr)
+                .user_agent("SocialSpaceBot/1.0 (+link previews)")
+                .build()
+                .map_err(|e| e.to_string())?;
+
+            let mut response = client
+                .get(url.clone())
+                .header(header::ACCEPT, "text/html,application/xhtml+xml")
+                .send()
+                .await
+                .map_err(|e| format!("Request failed: {}", e))?;
+
+            if response.status().is_redirection() {
+                let location = response
+                    .headers()
+                    .get(header::LOCATION)
+                    .and_then(|value| value.to_str().ok())
+                    .ok_or("Redirect without a location")?;
+                url = url.join(location).map_err(|e| format!("Invalid redirect: {}", e))?;
+                continue;
+            }
+
+            if !response.status().is_success() {
+                return Err(format!("Server responded with {}", response.status()));
+            }
+
+            let content_type = response
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default()
+                .to_lowercase();
+            if !content_type.starts_with("text/html") && !content_type.starts_with("application/xhtml+xml") {
+                return Err(format!("Not an HTML page: {}", content_type));
+            }
+
+            let mut body: Vec<u8> = Vec::new();
+            while let Some(chunk) = response.chunk().await.map_err(|e| format!("Failed to read page: {}", e))? {
+                body.extend_from_slice(&chunk);
+                if body.len() >= MAX_BODY_BYTES {
+                    body.truncate(MAX_BODY_BYTES);
+                    break;
+                }
+            }
+
+            return Ok(FetchedPage {
+                url,
+                html: String::from_utf8_lossy(&body).into_owned(),
+            });
+        }
+
+        Err("Too many redirects".to_string())
+    }
+}
+
+/// Returns false for loopback, private, link-local, shared, documentation,
+/// multicast and other addresses that aren't on the public internet.
+pub fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || a == 0
+                || (a == 100 && (64..128).contains(&b)) // shared address space
+                || (a == 192 && b == 0 && ip.octets()[2] == 0) // IETF protocol assignments
+                || (a == 198 && (b == 18 || b == 19)) // benchmarking
+                || a >= 240) // reserved
+        }
+        IpAddr::V6(ip) => {
+            if let Some(v4) = ip.to_ipv4_mapped() {
+                return is_public_address(IpAddr::V4(v4));
+            }
+
+            let first = ip.segments()[0];
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                || (first & 0xfe00) == 0xfc00 // unique local
+                || (first & 0xffc0) == 0xfe80 // link-local
+                || first == 0x2001 && ip.segments()[1] == 0x0db8 // documentation
+                || first == 0x0064 && ip.segments()[1] == 0xff9b) // NAT64
+        }
+    }
+}
+
+/// The first http(s) URL in `content`, without trailing punctuation.
+pub fn first_url(content: &str) -> Option<String> {
+    content
+        .split_whitespace()
+        .map(|word| word.trim_start_matches(['(', '[', '<', '"', '\'']))
+        .filter(|word| word.starts_with("http://") || word.starts_with("https://"))
+        .map(|word| word.trim_end_matches(['.', ',', ';', ':', '!', '?', ')', ']', '>', '"', '\'']))
+        .find(|word| Url::parse(word).is_ok_and(|url| url.host_str().is_some()))
+        .map(str::to_string)
+}
+
+/// Builds a preview from a page's OpenGraph tags, falling back to its `<title>`
+/// and description meta tag. Returns None if the page has no title at all.
+pub fn parse_preview(html: &str, page_url: &Url) -> Option<LinkPreview> {
+    let mut og_title = None;
+    let mut og_description = None;
+    let mut og_image = None;
+    let mut og_site_name = None;
+    let mut description = None;
+
+    let lower = html.to_lowercase();
+    let mut rest = 0;
+    while let Some(start) = lower[rest..].find("<meta") {
+        let start = rest + start;
+        let end = lower[start..].find('>').map(|i| start + i).unwrap_or(lower.len());
+        let attributes = parse_attributes(&html[start + 5..end]);
+        rest = end;
+
+        let key = attributes
+            .iter()
+            .find(|(name, _)| name == "property" || name == "name")
+            .map(|(_, value)| value.to_lowercase());
+        let content = attributes
+            .iter()
+            .find(|(name, _)| name == "content")
+            .map(|(_, value)| decode_entities(value).trim().to_string())
+            .filter(|value| !value.is_empty());
+
+        let slot = match key.as_deref() {
+            Some("og:title") => &mut og_title,
+            Some("og:description") => &mut og_description,
+            Some("og:image") => &mut og_image,
+            Some("og:site_name") => &mut og_site_name,
+            Some("description") => &mut description,
+            _ => continue,
+        };
+        if slot.is_none() {
+            *slot = content;
+        }
+    }
+
+    let title = og_title.or_else(|| {
+        let start = lower.find("<title")?;
+        let start = start + lower[start..].find('>')? + 1;
+        let end = start + lower[start..].find("</title")?;
+        Some(decode_entities(&html[start..end]).trim().to_string()).filter(|title| !title.is_empty())
+    })?;
+
+    Some(LinkPreview {
+        url: page_url.to_string(),
+        title: Some(truncate(&title, 300)),
+        description: og_description.or(description).map(|d| truncate(&d, 500)),
+        // Relative images are resolved against the page; only web images are kept
+        image_url: og_image
+            .and_then(|image| page_url.join(&image).ok())
+            .filter(|image| matches!(image.scheme(), "http" | "https"))
+            .map(|image| image.to_string()),
+        site_name: og_site_name.or_else(|| page_url.host_str().map(str::to_string)),
+    })
+}
+
+/// Fetches `url` and builds its preview.
+pub async fn unfurl<F: PreviewFetcher>(fetcher: &F, url: &str) -> Result<LinkPreview, String> {
+    let page = fetcher.fetch(url).await?;
+    let mut preview = parse_preview(&page.html, &page.url).ok_or("Page has no title")?;
+    // Cached under the URL that was posted, whatever it redirected to
+    preview.url = url.to_string();
+    Ok(preview)
+}
+
+/// Queues a preview for the post's link unless a fresh one is already cached.
+pub async fn queue_link_preview(state: &web::Data<AppState>, url: &str) {
+    let now = Utc::now();
+    let stale_before = (now - Duration::days(LINK_PREVIEW_TTL_DAYS)).to_rfc3339();
+
+    let _ = sqlx::query(
+        r#"
+        INSERT INTO link_previews (url, status, requested_at) VALUES (?, 'pending', ?)
+        ON CONFLICT(url) DO UPDATE SET status = 'pending', requested_at = excluded.requested_at
+        WHERE link_previews.status != 'pending' AND link_previews.fetched_at < ?
+        "#
+    )
+    .bind(url)
+    .bind(now.to_rfc3339())
+    .bind(&stale_before)
+    .execute(&state.db)
+    .await;
+}
+
+/// The cached preview for a link, if it has been fetched successfully.
+pub async fn load_link_preview(state: &web::Data<AppState>, url: &str) -> Option<LinkPreview> {
+    sqlx::query_as::<_, LinkPreview>(
+        "SELECT url, title, description, image_url, site_name FROM link_previews WHERE url = ? AND status = 'ready'"
+    )
+    .bind(url)
+    .fetch_optional(&state.db)
+    .await
+    .ok()
+    .flatten()
+}
+
+/// Fetches previews for queued URLs, recording failures so they aren't retried
+/// until they go stale.
+pub async fn fetch_pending_previews<F: PreviewFetcher>(pool: &SqlitePool, fetcher: &F) {
+    let urls: Vec<String> = sqlx::query_scalar(
+        "SELECT url FROM link_previews WHERE status = 'pending' ORDER BY requested_at ASC LIMIT ?"
+    )
+    .bind(LINK_PREVIEW_BATCH_SIZE)
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default();
+
+    for url in urls {
+        let now = Utc::now().to_rfc3339();
+
+        let result = match unfurl(fetcher, &url).await {
+            Ok(preview) => sqlx::query(
+                r#"
+                UPDATE link_previews
+                SET status = 'ready', title = ?, description = ?, image_url = ?, site_name = ?, error = NULL, fetched_at = ?
+                WHERE url = ?
+                "#
+            )
+            .bind(&preview.title)
+            .bind(&preview.description)
+            .bind(&preview.image_url)
+            .bind(&preview.site_name)
+            .bind(&now)
+            .bind(&url)
+            .execute(pool)
+            .await,
+            Err(error) => sqlx::query(
+                "UPDATE link_previews SET status = 'failed', error = ?, fetched_at = ? WHERE url = ?"
+            )
+            .bind(&error)
+            .bind(&now)
+            .bind(&url)
+            .execute(pool)
+            .await,
+        };
+
+        if let Err(e) = result {
+            eprintln!("Failed to store link preview for {}: {}", url, e);
+        }
+    }
+}
+
+/// Periodically fetches previews for queued links.
+pub async fn run_link_previewer<F: PreviewFetcher>(pool: SqlitePool, fetcher: F) {
+    let mut interval = tokio::time::interval(StdDuration::from_secs(LINK_PREVIEW_INTERVAL_SECONDS));
+
+    loop {
+        interval.tick().await;
+        fetch_pending_previews(&pool, &fetcher).await;
+    }
+}
+
+// Helper functions
+
+/// `name="value"` pairs of an HTML tag, with lowercased names.
+fn parse_attributes(tag: &str) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    let mut chars = tag.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        if !c.is_alphabetic() {
+            continue;
+        }
+
+        let mut name_end = tag.len();
+        while let Some(&(i, c)) = chars.peek() {
+            if !(c.is_alphanumeric() || c == '-' || c == ':' || c == '_') {
+                name_end = i;
+                break;
+            }
+            chars.next();
+        }
+        let name = tag[start..name_end].to_lowercase();
+
+        while chars.peek().is_some_and(|&(_, c)| c.is_whitespace()) {
+            chars.next();
+        }
+        if chars.peek().map(|&(_, c)| c) != Some('=') {
+            continue;
+        }
+        chars.next();
+        while chars.peek().is_some_and(|&(_, c)| c.is_whitespace()) {
+            chars.next();
+        }
+
+        let value = match chars.peek().copied() {
+            Some((i, quote @ ('"' | '\''))) => {
+                chars.next();
+                let end = tag[i + 1..].find(quote).map(|j| i + 1 + j).unwrap_or(tag.len());
+                while chars.peek().is_some_and(|&(j, _)| j <= end) {
+                    chars.next();
+                }
+                &tag[i + 1..end]
+            }
+            Some((i, _)) => {
+                let end = tag[i..].find(|c: char| c.is_whitespace() || c == '/').map(|j| i + j).unwrap_or(tag.len());
+                while chars.peek().is_some_and(|&(j, _)| j < end) {
+                    chars.next();
+                }
+                &tag[i..end]
+            }
+            None => "",
+        };
+
+        attributes.push((name, value.to_string()));
+    }
+
+    attributes
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    text.chars().take(max_chars).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::net::{Ipv4Addr, Ipv6Addr};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    impl HttpFetcher {
+        /// A fetcher that may reach the local stub servers below.
+        fn for_local_stub(timeout: StdDuration) -> Self {
+            HttpFetcher {
+                timeout,
+                allow_private_addresses: true,
+            }
+        }
+    }
+
+    /// Serves `responses` in order on a local port, one per connection.
+    async fn stub_server(responses: Vec<String>) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = [0u8; 4096];
+                let _ = socket.read(&mut request).await;
+                let _ = socket.write_all(response.as_bytes()).await;
+                let _ = socket.shutdown().await;
+            }
+        });
+
+        addr
+    }
+
+    fn html_response(body: &str) -> String {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    }
+
+    #[test]
+    fn public_and_private_addresses() {
+        let cases: [(IpAddr, bool); 16] = [
+            (Ipv4Addr::new(93, 184, 216, 34).into(), true),
+            (Ipv4Addr::new(8, 8, 8, 8).into(), true),
+            (Ipv4Addr::new(127, 0, 0, 1).into(), false),
+            (Ipv4Addr::new(10, 1, 2, 3).into(), false),
+            (Ipv4Addr::new(172, 16, 0, 1).into(), false),
+            (Ipv4Addr::new(192, 168, 1, 1).into(), false),
+            (Ipv4Addr::new(169, 254, 169, 254).into(), false),
+            (Ipv4Addr::new(100, 64, 0, 1).into(), false),
+            (Ipv4Addr::new(0, 0, 0, 0).into(), false),
+            (Ipv4Addr::new(224, 0, 0, 1).into(), false),
+            (Ipv4Addr::new(255, 255, 255, 255).into(), false),
+            (Ipv6Addr::LOCALHOST.into(), false),
+            ("fd00::1".parse::<Ipv6Addr>().unwrap().into(), false),
+            ("fe80::1".parse::<Ipv6Addr>().unwrap().into(), false),
+            ("::ffff:127.0.0.1".parse::<Ipv6Addr>().unwrap().into(), false),
+            ("2606:4700::1111".parse::<Ipv6Addr>().unwrap().into(), true),
+        ];
+
+        for (ip, expected) in cases {
+            assert_eq!(is_public_address(ip), expected, "{}", ip);
+        }
+    }
+
+    #[test]
+    fn finds_the_first_link() {
+        assert_eq!(first_url("see https://example.com/a?b=1, it's good").as_deref(), Some("https://example.com/a?b=1"));
+        assert_eq!(first_url("(http://example.org/x). and https://b.com").as_deref(), Some("http://example.org/x"));
+        assert_eq!(first_url("no links here, just example.com"), None);
+        assert_eq!(first_url("ftp://example.com https://"), None);
+    }
+
+    #[test]
+    fn parses_open_graph_tags() {
+        let url = Url::parse("https://example.com/articles/1").unwrap();
+        let html = r#"
+            <html><head>
+            <title>Fallback</title>
+            <meta property="og:title" content="Tom &amp; Jerry">
+            <META name='description' content='A classic'>
+            <meta property="og:image" content="/img/cover.png" />
+            <meta property="og:site_name" content="Example">
+            </head></html>
+        "#;
+
+        let preview = parse_preview(html, &url).unwrap();
+        assert_eq!(preview.title.as_deref(), Some("Tom & Jerry"));
+        assert_eq!(preview.description.as_deref(), Some("A classic"));
+        assert_eq!(preview.image_url.as_deref(), Some("https://example.com/img/cover.png"));
+        assert_eq!(preview.site_name.as_deref(), Some("Example"));
+    }
+
+    #[test]
+    fn falls_back_to_the_title_tag() {
+        let url = Url::parse("https://example.com/").unwrap();
+
+        let preview = parse_preview("<html><head><TITLE> Plain page </TITLE></head></html>", &url).unwrap();
+        assert_eq!(preview.title.as_deref(), Some("Plain page"));
+        assert_eq!(preview.site_name.as_deref(), Some("example.com"));
+        assert_eq!(preview.image_url, None);
+
+        assert!(parse_preview("<html><body>No title</body></html>", &url).is_none());
+    }
+
+    #[tokio::test]
+    async fn refuses_private_and_non_web_urls() {
+        let addr = stub_server(vec![html_response("<title>Internal</title>")]).await;
+        let fetcher = HttpFetcher::new();
+
+        for url in [
+            format!("http://{}/", addr),
+            "http://localhost/".to_string(),
+            "http://169.254.169.254/latest/meta-data/".to_string(),
+            "http://[::1]/".to_string(),
+            "file:///etc/passwd".to_string(),
+        ] {
+            assert!(fetcher.fetch(&url).await.is_err(), "{}", url);
+        }
+    }
+
+    #[tokio::test]
+    async fn unfurls_a_page_from_a_stub_server() {
+        let addr = stub_server(vec![
+            format!("HTTP/1.1 301 Moved Permanently\r\nLocation: /real\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"),
+            html_response(r#"<head><meta property="og:title" content="Stubbed"><meta property="og:image" content="pic.jpg"></head>"#),
+        ])
+        .await;
+        let fetcher = HttpFetcher::for_local_stub(StdDuration::from_secs(2));
+        let url = format!("http://{}/start", addr);
+
+        let preview = unfurl(&fetcher, &url).await.unwrap();
+        assert_eq!(preview.url, url);
+        assert_eq!(preview.title.as_deref(), Some("Stubbed"));
+        assert_eq!(preview.image_url, Some(format!("http://{}/pic.jpg", addr)));
+    }
+
+    #[tokio::test]
+    async fn rejects_non_html_and_reads_only_the_start_of_large_pages() {
+        let huge = format!("<title>Big</title>{}", "x".repeat(4 * MAX_BODY_BYTES));
+        let addr = stub_server(vec![
+            "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: 3\r\nConnection: close\r\n\r\nabc".to_string(),
+            html_response(&huge),
+        ])
+        .await;
+        let fetcher = HttpFetcher::for_local_stub(StdDuration::from_secs(2));
+
+        assert!(fetcher.fetch(&format!("http://{}/file", addr)).await.is_err());
+
+        let page = fetcher.fetch(&format!("http://{}/big", addr)).await.unwrap();
+        assert_eq!(page.html.len(), MAX_BODY_BYTES);
+        assert_eq!(parse_preview(&page.html, &page.url).unwrap().title.as_deref(), Some("Big"));
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_slow_servers() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (_socket, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(StdDuration::from_secs(10)).await;
+        });
+
+        let fetcher = HttpFetcher::for_local_stub(StdDuration::from_millis(300));
+        let started = std::time::Instant::now();
+        assert!(fetcher.fetch(&format!("http://{}/", addr)).await.is_err());
+        assert!(started.elapsed() < StdDuration::from_secs(2));
+    }
+}
@@ -3,6 +3,17 @@ mod handlers;
 mod db;
 mod auth;
 mod websocket;
+mod notifications;
+mod suggestions;
+mod anonymity;
+mod policy;
+mod ranking;
+mod scheduler;
+mod sweeper;
+mod tagging;
+mod link_previews;
+#[cfg(test)]
+mod test_support;
 
 use actix_cors::Cors;
 use actix_web::{web, App, HttpServer, middleware, HttpResponse};
@@ -34,6 +45,18 @@ async fn main() -> std::io::Result<()> {
         ws_connections: Arc::new(RwLock::new(HashMap::new())),
     });
 
+    // Keep the friend suggestions cache warm in the background
+    actix_rt::spawn(suggestions::run_refresher(app_state.db.clone()));
+
+    // Publish scheduled posts when they come due
+    actix_rt::spawn(scheduler::run_scheduler(app_state.clone()));
+
+    // Fetch previews for links in new posts
+    actix_rt::spawn(link_previews::run_link_previewer(app_state.db.clone(), link_previews::HttpFetcher::new()));
+
+    // Delete stories once they expire
+    actix_rt::spawn(sweeper::run_story_sweeper(app_state.db.clone()));
+
     let bind_addr = std::env::var("BIND_ADDR").unwrap_or_else(|_| "0.0.0.0:8080".to_string());
 
     println!("Server running at http://{bind_addr}");
@@ -55,29 +78,119 @@ async fn main() -> std::io::Result<()> {
             .route("/api/auth/me", web::get().to(handlers::auth::get_me))
             // User routes
             .route("/api/users", web::get().to(handlers::users::search_users))
+            .route("/api/users/me/settings", web::get().to(handlers::users::get_settings))
+            .route("/api/users/me/settings", web::put().to(handlers::users::update_settings))
             .route("/api/users/{id}", web::get().to(handlers::users::get_user))
+            .route("/api/users/{id}/friends", web::get().to(handlers::friends::get_user_friends))
+            .route("/api/users/{id}/followers", web::get().to(handlers::follows::get_followers))
+            .route("/api/users/{id}/following", web::get().to(handlers::follows::get_following))
+            .route("/api/users/{id}/follow-counts", web::get().to(handlers::follows::get_follow_counts))
+            // Search routes
+            .route("/api/search", web::get().to(handlers::search::search))
+            // Hashtag routes
+            .route("/api/hashtags/trending", web::get().to(handlers::hashtags::get_trending_hashtags))
+            .route("/api/hashtags/{tag}", web::get().to(handlers::hashtags::get_hashtag_posts))
             // Friend routes
             .route("/api/friends", web::get().to(handlers::friends::get_friends))
             .route("/api/friends/requests", web::get().to(handlers::friends::get_friend_requests))
+            .route("/api/friends/requests/sent", web::get().to(handlers::friends::get_sent_friend_requests))
+            .route("/api/friends/suggestions", web::get().to(handlers::friends::get_friend_suggestions))
             .route("/api/friends/request/{user_id}", web::post().to(handlers::friends::send_friend_request))
             .route("/api/friends/accept/{user_id}", web::post().to(handlers::friends::accept_friend_request))
             .route("/api/friends/reject/{user_id}", web::post().to(handlers::friends::reject_friend_request))
+            .route("/api/friends/cancel/{user_id}", web::post().to(handlers::friends::cancel_friend_request))
+            .route("/api/friends/{user_id}", web::delete().to(handlers::friends::remove_friend))
+            // Friend list routes
+            .route("/api/friend-lists", web::get().to(handlers::friend_lists::get_friend_lists))
+            .route("/api/friend-lists", web::post().to(handlers::friend_lists::create_friend_list))
+            .route("/api/friend-lists/{id}", web::get().to(handlers::friend_lists::get_friend_list))
+            .route("/api/friend-lists/{id}", web::put().to(handlers::friend_lists::rename_friend_list))
+            .route("/api/friend-lists/{id}", web::delete().to(handlers::friend_lists::delete_friend_list))
+            .route("/api/friend-lists/{id}/members/{user_id}", web::post().to(handlers::friend_lists::add_list_member))
+            .route("/api/friend-lists/{id}/members/{user_id}", web::delete().to(handlers::friend_lists::remove_list_member))
+            // Follow routes
+            .route("/api/follows/requests", web::get().to(handlers::follows::get_follow_requests))
+            .route("/api/follows/accept/{user_id}", web::post().to(handlers::follows::accept_follow_request))
+            .route("/api/follows/reject/{user_id}", web::post().to(handlers::follows::reject_follow_request))
+            .route("/api/follows/{user_id}", web::post().to(handlers::follows::follow_user))
+            .route("/api/follows/{user_id}", web::delete().to(handlers::follows::unfollow_user))
+            // Block routes
+            .route("/api/blocks", web::get().to(handlers::blocks::get_blocked_users))
+            .route("/api/blocks/{user_id}", web::post().to(handlers::blocks::block_user))
+            .route("/api/blocks/{user_id}", web::delete().to(handlers::blocks::unblock_user))
+            // Mute routes
+            .route("/api/mutes", web::get().to(handlers::mutes::get_mutes))
+            .route("/api/mutes/users/{user_id}", web::post().to(handlers::mutes::mute_user))
+            .route("/api/mutes/users/{user_id}", web::delete().to(handlers::mutes::unmute_user))
+            .route("/api/mutes/groups/{group_id}", web::post().to(handlers::mutes::mute_group))
+            .route("/api/mutes/groups/{group_id}", web::delete().to(handlers::mutes::unmute_group))
             // Post routes
             .route("/api/posts", web::get().to(handlers::posts::get_feed))
             .route("/api/posts", web::post().to(handlers::posts::create_post))
+            .route("/api/posts/drafts", web::get().to(handlers::posts::get_drafts))
+            .route("/api/posts/scheduled", web::get().to(handlers::posts::get_scheduled_posts))
             .route("/api/posts/{id}", web::get().to(handlers::posts::get_post))
+            .route("/api/posts/{id}", web::put().to(handlers::posts::update_post))
             .route("/api/posts/{id}", web::delete().to(handlers::posts::delete_post))
+            .route("/api/posts/{id}/publish", web::post().to(handlers::posts::publish_post))
+            .route("/api/posts/{id}/schedule", web::delete().to(handlers::posts::cancel_scheduled_post))
             .route("/api/posts/{id}/like", web::post().to(handlers::posts::like_post))
+            .route("/api/posts/{id}/repost", web::post().to(handlers::posts::repost_post))
+            .route("/api/posts/{id}/poll/vote", web::post().to(handlers::polls::vote))
+            .route("/api/posts/{id}/poll/vote", web::delete().to(handlers::polls::retract_vote))
             .route("/api/posts/{id}/comment", web::post().to(handlers::posts::add_comment))
             .route("/api/posts/{id}/comments", web::get().to(handlers::posts::get_comments))
+            .route("/api/comments/{id}", web::delete().to(handlers::posts::delete_comment))
+            // Story routes
+            .route("/api/stories", web::get().to(handlers::stories::get_stories))
+            .route("/api/stories", web::post().to(handlers::stories::create_story))
+            .route("/api/stories/{id}", web::get().to(handlers::stories::get_story))
+            .route("/api/stories/{id}", web::delete().to(handlers::stories::delete_story))
+            .route("/api/stories/{id}/viewers", web::get().to(handlers::stories::get_story_viewers))
             // Group routes
             .route("/api/groups", web::get().to(handlers::groups::get_groups))
             .route("/api/groups", web::post().to(handlers::groups::create_group))
+            .route("/api/groups/discover", web::get().to(handlers::groups::discover_groups))
+            .route("/api/groups/invitations", web::get().to(handlers::groups::get_my_invitations))
+            .route("/api/group-invites/{code}", web::post().to(handlers::groups::join_with_invite_code))
             .route("/api/groups/{id}", web::get().to(handlers::groups::get_group))
+            .route("/api/groups/{id}", web::put().to(handlers::groups::update_group))
+            .route("/api/groups/{id}", web::delete().to(handlers::groups::delete_group))
             .route("/api/groups/{id}/join", web::post().to(handlers::groups::join_group))
             .route("/api/groups/{id}/leave", web::post().to(handlers::groups::leave_group))
+            .route("/api/groups/{id}/feed", web::post().to(handlers::groups::show_in_feed))
+            .route("/api/groups/{id}/feed", web::delete().to(handlers::groups::hide_from_feed))
             .route("/api/groups/{id}/posts", web::get().to(handlers::groups::get_group_posts))
             .route("/api/groups/{id}/posts", web::post().to(handlers::groups::create_group_post))
+            .route("/api/groups/{id}/posts/pending", web::get().to(handlers::group_moderation::get_pending_posts))
+            .route("/api/groups/{id}/posts/{post_id}", web::delete().to(handlers::group_moderation::delete_group_post))
+            .route("/api/groups/{id}/posts/{post_id}/approve", web::post().to(handlers::group_moderation::approve_post))
+            .route("/api/groups/{id}/posts/{post_id}/reject", web::post().to(handlers::group_moderation::reject_post))
+            .route("/api/groups/{id}/posts/{post_id}/pin", web::post().to(handlers::group_moderation::pin_post))
+            .route("/api/groups/{id}/posts/{post_id}/pin", web::delete().to(handlers::group_moderation::unpin_post))
+            .route("/api/groups/{id}/posts/{post_id}/lock", web::post().to(handlers::group_moderation::lock_comments))
+            .route("/api/groups/{id}/posts/{post_id}/lock", web::delete().to(handlers::group_moderation::unlock_comments))
+            .route("/api/groups/{id}/comments/{comment_id}", web::delete().to(handlers::group_moderation::delete_group_comment))
+            .route("/api/groups/{id}/posts/{post_id}/ban-author", web::post().to(handlers::group_moderation::ban_post_author))
+            .route("/api/groups/{id}/comments/{comment_id}/ban-author", web::post().to(handlers::group_moderation::ban_comment_author))
+            .route("/api/groups/{id}/members", web::get().to(handlers::group_members::get_members))
+            .route("/api/groups/{id}/members/{user_id}", web::delete().to(handlers::group_members::kick_member))
+            .route("/api/groups/{id}/members/{user_id}/role", web::put().to(handlers::group_members::update_member_role))
+            .route("/api/groups/{id}/bans", web::get().to(handlers::group_members::get_bans))
+            .route("/api/groups/{id}/bans/{user_id}", web::post().to(handlers::group_members::ban_member))
+            .route("/api/groups/{id}/bans/{user_id}", web::delete().to(handlers::group_members::unban_member))
+            .route("/api/groups/{id}/transfer/{user_id}", web::post().to(handlers::group_members::transfer_ownership))
+            .route("/api/groups/{id}/requests", web::get().to(handlers::groups::get_join_requests))
+            .route("/api/groups/{id}/requests/{user_id}/approve", web::post().to(handlers::groups::approve_join_request))
+            .route("/api/groups/{id}/requests/{user_id}/deny", web::post().to(handlers::groups::deny_join_request))
+            .route("/api/groups/{id}/invite-codes", web::get().to(handlers::groups::get_invite_codes))
+            .route("/api/groups/{id}/invite-codes", web::post().to(handlers::groups::create_invite_code))
+            .route("/api/groups/{id}/invite-codes/{code}", web::delete().to(handlers::groups::revoke_invite_code))
+            .route("/api/groups/{id}/invitations/{user_id}", web::post().to(handlers::groups::invite_member))
+            .route("/api/groups/{id}/invitation", web::delete().to(handlers::groups::decline_invitation))
+            // Admin routes
+            .route("/api/admin/anonymity/reveal", web::post().to(handlers::admin::reveal_anonymous_author))
+            .route("/api/admin/anonymity/reveals", web::get().to(handlers::admin::get_reveal_log))
             // Chat routes
             .route("/api/chat/conversations", web::get().to(handlers::chat::get_conversations))
             .route("/api/chat/messages/{user_id}", web::get().to(handlers::chat::get_messages))
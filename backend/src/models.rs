@@ -13,6 +13,14 @@ pub struct User {
     pub avatar_url: Option<String>,
     pub bio: Option<String>,
     pub created_at: String,
+    pub requires_follow_approval: bool,
+    // Privacy audiences: everyone, friends_of_friends, friends, nobody
+    pub email_visibility: String,
+    pub bio_visibility: String,
+    pub friends_list_visibility: String,
+    pub searchable_by: String,
+    pub friend_requests_from: String,
+    pub is_admin: bool, // site admin, set directly in the database
 }
 
 #[derive(Debug, Deserialize)]
@@ -38,26 +46,55 @@ pub struct AuthResponse {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserResponse {
     pub id: String,
-    pub email: String,
+    pub email: Option<String>,
     pub username: String,
     pub display_name: String,
     pub avatar_url: Option<String>,
     pub bio: Option<String>,
 }
 
+impl UserResponse {
+    /// The full view of a user, including private fields, for the user themselves.
+    pub fn for_self(user: User) -> Self {
+        UserResponse {
+            id: user.id,
+            email: Some(user.email),
+            username: user.username,
+            display_name: user.display_name,
+            avatar_url: user.avatar_url,
+            bio: user.bio,
+        }
+    }
+}
+
+/// The view of a user anyone may see: email and bio are only included when
+/// their owner shares them with everyone.
 impl From<User> for UserResponse {
     fn from(user: User) -> Self {
         UserResponse {
             id: user.id,
-            email: user.email,
+            email: if user.email_visibility == "everyone" { Some(user.email) } else { None },
             username: user.username,
             display_name: user.display_name,
             avatar_url: user.avatar_url,
-            bio: user.bio,
+            bio: if user.bio_visibility == "everyone" { user.bio } else { None },
         }
     }
 }
 
+#[derive(Debug, Serialize)]
+pub struct ProfileResponse {
+    #[serde(flatten)]
+    pub user: UserResponse,
+    pub relationship: String, // self, none, pending_outgoing, pending_incoming, friends, blocked
+    pub mutual_friends_count: i32,
+    pub mutual_friends: Vec<UserResponse>,
+    pub friends_count: Option<i32>, // hidden when the friend list isn't shared with the viewer
+    pub posts_count: i32,
+    pub shared_groups: Vec<GroupSummary>,
+    pub posts: Vec<PostResponse>,
+}
+
 // Friend models
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Friendship {
@@ -66,6 +103,18 @@ pub struct Friendship {
     pub friend_id: String,
     pub status: String, // pending, accepted, rejected
     pub created_at: String,
+    pub responded_at: Option<String>,
+}
+
+/// A friend or follow request, read from either table through `RequestKind`.
+#[derive(Debug, Clone, FromRow)]
+pub struct UserRequest {
+    pub id: String,
+    pub sender_id: String,
+    pub recipient_id: String,
+    pub status: String, // pending, accepted, rejected
+    pub created_at: String,
+    pub responded_at: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -76,12 +125,170 @@ pub struct FriendWithUser {
     pub created_at: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct FriendSuggestion {
+    pub user_id: String,
+    pub suggested_id: String,
+    pub mutual_friends_count: i32,
+    pub shared_groups_count: i32,
+    pub interactions_count: i32,
+    pub score: f64,
+    pub computed_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FriendSuggestionResponse {
+    pub user: UserResponse,
+    pub mutual_friends_count: i32,
+    pub mutual_friends: Vec<UserResponse>,
+    pub shared_groups_count: i32,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct FriendList {
+    pub id: String,
+    pub owner_id: String,
+    pub name: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateFriendListRequest {
+    pub name: String,
+    pub member_ids: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateFriendListRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FriendListResponse {
+    pub id: String,
+    pub name: String,
+    pub members: Vec<UserResponse>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct FriendListSummary {
+    pub id: String,
+    pub name: String,
+}
+
+// Follow models
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Follow {
+    pub id: String,
+    pub follower_id: String,
+    pub followee_id: String,
+    pub status: String, // pending, accepted, rejected
+    pub created_at: String,
+    pub responded_at: Option<String>,
+}
+
+impl From<UserRequest> for Follow {
+    fn from(request: UserRequest) -> Self {
+        Follow {
+            id: request.id,
+            follower_id: request.sender_id,
+            followee_id: request.recipient_id,
+            status: request.status,
+            created_at: request.created_at,
+            responded_at: request.responded_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct FollowWithUser {
+    pub follow_id: String,
+    pub user: UserResponse,
+    pub status: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FollowCounts {
+    pub followers_count: i32,
+    pub following_count: i32,
+    pub follow_status: Option<String>, // the viewer's follow of this user, if any
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateSettingsRequest {
+    pub requires_follow_approval: Option<bool>,
+    pub email_visibility: Option<String>,
+    pub bio_visibility: Option<String>,
+    pub friends_list_visibility: Option<String>,
+    pub searchable_by: Option<String>,
+    pub friend_requests_from: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SettingsResponse {
+    pub requires_follow_approval: bool,
+    pub email_visibility: String,
+    pub bio_visibility: String,
+    pub friends_list_visibility: String,
+    pub searchable_by: String,
+    pub friend_requests_from: String,
+}
+
+impl From<User> for SettingsResponse {
+    fn from(user: User) -> Self {
+        SettingsResponse {
+            requires_follow_approval: user.requires_follow_approval,
+            email_visibility: user.email_visibility,
+            bio_visibility: user.bio_visibility,
+            friends_list_visibility: user.friends_list_visibility,
+            searchable_by: user.searchable_by,
+            friend_requests_from: user.friend_requests_from,
+        }
+    }
+}
+
+// Block models
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Block {
+    pub id: String,
+    pub blocker_id: String,
+    pub blocked_id: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BlockedUser {
+    pub block_id: String,
+    pub user: UserResponse,
+    pub created_at: String,
+}
+
+// Mute models
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Mute {
+    pub id: String,
+    pub user_id: String,
+    pub target_type: String, // user, group
+    pub target_id: String,
+    pub expires_at: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MuteRequest {
+    pub duration_hours: Option<i64>,
+}
+
 // Post models
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum PostVisibility {
     Public,
     FriendsOnly,
     Private,
+    Custom,
 }
 
 impl From<String> for PostVisibility {
@@ -89,17 +296,19 @@ impl From<String> for PostVisibility {
         match s.as_str() {
             "public" => PostVisibility::Public,
             "private" => PostVisibility::Private,
+            "custom" => PostVisibility::Custom,
             _ => PostVisibility::FriendsOnly,
         }
     }
 }
 
-impl ToString for PostVisibility {
-    fn to_string(&self) -> String {
+impl std::fmt::Display for PostVisibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            PostVisibility::Public => "public".to_string(),
-            PostVisibility::FriendsOnly => "friends_only".to_string(),
-            PostVisibility::Private => "private".to_string(),
+            PostVisibility::Public => write!(f, "public"),
+            PostVisibility::FriendsOnly => write!(f, "friends_only"),
+            PostVisibility::Private => write!(f, "private"),
+            PostVisibility::Custom => write!(f, "custom"),
         }
     }
 }
@@ -114,12 +323,59 @@ pub struct Post {
     pub is_anonymous: bool,
     pub created_at: String,
     pub updated_at: String,
+    pub status: String, // published, pending, draft, scheduled
+    pub is_pinned: bool,
+    pub comments_locked: bool,
+    pub publish_at: Option<String>,
+    pub repost_of_id: Option<String>,
+    pub is_plain_repost: bool, // a repost without a quote; deleted along with its original
+    pub link_url: Option<String>, // first link in the content, previewed in the background
 }
 
 #[derive(Debug, Deserialize)]
 pub struct CreatePostRequest {
     pub content: String,
     pub visibility: Option<String>,
+    pub audience: Option<PostAudienceRequest>,
+    pub draft: Option<bool>,
+    pub publish_at: Option<String>,
+    pub poll: Option<CreatePollRequest>,
+}
+
+/// Changes to a draft or scheduled post. Setting `publish_at` (re)schedules it.
+#[derive(Debug, Deserialize)]
+pub struct UpdatePostRequest {
+    pub content: Option<String>,
+    pub visibility: Option<String>,
+    pub audience: Option<PostAudienceRequest>,
+    pub publish_at: Option<String>,
+}
+
+/// Shares a post, optionally with a quote of your own.
+#[derive(Debug, Deserialize)]
+pub struct RepostRequest {
+    pub quote: Option<String>,
+    pub visibility: Option<String>,
+}
+
+/// Friend lists and users a `custom` post is shown to, and friends left out of a
+/// `friends_only` or `custom` post.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PostAudienceRequest {
+    #[serde(default)]
+    pub list_ids: Vec<String>,
+    #[serde(default)]
+    pub user_ids: Vec<String>,
+    #[serde(default)]
+    pub except_user_ids: Vec<String>,
+}
+
+/// A post's audience as shown to its author.
+#[derive(Debug, Serialize)]
+pub struct PostAudienceResponse {
+    pub lists: Vec<FriendListSummary>,
+    pub users: Vec<UserResponse>,
+    pub except_users: Vec<UserResponse>,
 }
 
 #[derive(Debug, Serialize)]
@@ -129,12 +385,109 @@ pub struct PostResponse {
     pub content: String,
     pub visibility: String,
     pub is_anonymous: bool,
+    pub anonymous_alias: Option<String>,
     pub likes_count: i32,
     pub comments_count: i32,
     pub is_liked: bool,
+    pub status: String,
+    pub is_pinned: bool,
+    pub comments_locked: bool,
+    pub group: Option<GroupSummary>, // set for group posts
+    pub repost_of_id: Option<String>,
+    pub repost_of: Option<Box<PostResponse>>, // None if the original was deleted or isn't visible to you
+    pub shares_count: i32,
+    pub mentions: Vec<Mention>,
+    pub link_preview: Option<LinkPreview>, // once fetched
+    pub poll: Option<PollResponse>,
+    pub audience: Option<PostAudienceResponse>, // only for the author
+    pub publish_at: Option<String>,
+    pub created_at: String,
+}
+
+// Poll models
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreatePollRequest {
+    pub options: Vec<String>,
+    pub multiple_choice: Option<bool>,
+    pub anonymous: Option<bool>,
+    pub results_visibility: Option<String>, // always, after_vote or after_close
+    pub closes_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Poll {
+    pub post_id: String,
+    pub multiple_choice: bool,
+    pub anonymous: bool,
+    pub results_visibility: String,
+    pub closes_at: Option<String>,
     pub created_at: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PollOption {
+    pub id: String,
+    pub post_id: String,
+    pub text: String,
+    pub position: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PollVoteRequest {
+    pub option_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct PollVoter {
+    pub user_id: String,
+    pub username: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PollOptionResponse {
+    pub id: String,
+    pub text: String,
+    pub votes_count: Option<i64>, // None while results are hidden
+    pub voters: Option<Vec<PollVoter>>, // only for public polls with visible results
+}
+
+#[derive(Debug, Serialize)]
+pub struct PollResponse {
+    pub multiple_choice: bool,
+    pub anonymous: bool,
+    pub results_visibility: String,
+    pub closes_at: Option<String>,
+    pub is_closed: bool,
+    pub results_visible: bool,
+    pub voters_count: Option<i64>,
+    pub my_votes: Vec<String>, // option ids
+    pub options: Vec<PollOptionResponse>,
+}
+
+// Link preview models
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct LinkPreview {
+    pub url: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub image_url: Option<String>,
+    pub site_name: Option<String>,
+}
+
+// Mention and hashtag models
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Mention {
+    pub user_id: String,
+    pub username: String,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct TrendingHashtag {
+    pub tag: String,
+    pub posts_count: i64,
+    pub authors_count: i64,
+}
+
 // Comment models
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Comment {
@@ -158,6 +511,8 @@ pub struct CommentResponse {
     pub user: Option<UserResponse>,
     pub content: String,
     pub is_anonymous: bool,
+    pub anonymous_alias: Option<String>,
+    pub mentions: Vec<Mention>,
     pub created_at: String,
 }
 
@@ -170,6 +525,52 @@ pub struct Like {
     pub created_at: String,
 }
 
+// Story models
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Story {
+    pub id: String,
+    pub user_id: String,
+    pub content: String,
+    pub media_url: Option<String>,
+    pub visibility: String, // public, friends_only
+    pub created_at: String,
+    pub expires_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateStoryRequest {
+    pub content: Option<String>,
+    pub media_url: Option<String>,
+    pub visibility: Option<String>,
+    pub duration_hours: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StoryResponse {
+    pub id: String,
+    pub content: String,
+    pub media_url: Option<String>,
+    pub visibility: String,
+    pub seen: bool,
+    pub views_count: Option<i32>, // only for the author
+    pub created_at: String,
+    pub expires_at: String,
+}
+
+/// One author's active stories, oldest first.
+#[derive(Debug, Serialize)]
+pub struct StoryGroup {
+    pub author: UserResponse,
+    pub stories: Vec<StoryResponse>,
+    pub has_unseen: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StoryViewer {
+    pub user: UserResponse,
+    pub viewed_at: String,
+}
+
 // Group models
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Group {
@@ -180,6 +581,10 @@ pub struct Group {
     pub creator_id: String,
     pub is_private: bool,
     pub created_at: String,
+    pub category: Option<String>,
+    pub posting_policy: String, // everyone, admins
+    pub require_post_approval: bool,
+    pub allow_anonymous: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -187,6 +592,21 @@ pub struct CreateGroupRequest {
     pub name: String,
     pub description: Option<String>,
     pub is_private: Option<bool>,
+    pub category: Option<String>,
+    pub tags: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateGroupRequest {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub cover_image: Option<String>,
+    pub is_private: Option<bool>,
+    pub category: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub posting_policy: Option<String>,
+    pub require_post_approval: Option<bool>,
+    pub allow_anonymous: Option<bool>,
 }
 
 #[derive(Debug, Serialize)]
@@ -197,11 +617,24 @@ pub struct GroupResponse {
     pub cover_image: Option<String>,
     pub creator: UserResponse,
     pub is_private: bool,
+    pub category: Option<String>,
+    pub tags: Vec<String>,
+    pub posting_policy: String,
+    pub require_post_approval: bool,
+    pub allow_anonymous: bool,
     pub members_count: i32,
     pub is_member: bool,
+    pub show_in_feed: bool, // whether the group's posts appear in your home feed
+    pub has_pending_request: bool,
     pub created_at: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct GroupSummary {
+    pub id: String,
+    pub name: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct GroupMember {
     pub id: String,
@@ -215,6 +648,160 @@ pub struct GroupMember {
 pub struct CreateGroupPostRequest {
     pub content: String,
     pub is_anonymous: Option<bool>,
+    pub draft: Option<bool>,
+    pub publish_at: Option<String>,
+    pub poll: Option<CreatePollRequest>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct GroupJoinRequest {
+    pub id: String,
+    pub group_id: String,
+    pub user_id: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GroupJoinRequestWithUser {
+    pub request_id: String,
+    pub user: UserResponse,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct GroupInviteCode {
+    pub code: String,
+    pub group_id: String,
+    pub created_by: String,
+    pub expires_at: Option<String>,
+    pub max_uses: Option<i32>,
+    pub uses: i32,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateInviteCodeRequest {
+    pub expires_in_hours: Option<i64>,
+    pub max_uses: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct GroupInvitation {
+    pub id: String,
+    pub group_id: String,
+    pub user_id: String,
+    pub invited_by: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GroupMemberWithUser {
+    pub user: UserResponse,
+    pub role: String,
+    pub is_owner: bool,
+    pub joined_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateMemberRoleRequest {
+    pub role: String, // moderator, member
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct GroupBan {
+    pub id: String,
+    pub group_id: String,
+    pub user_id: String,
+    pub banned_by: String,
+    pub reason: Option<String>,
+    pub anonymous_alias: Option<String>, // set when an anonymous author was banned through their content
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BanMemberRequest {
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GroupBanWithUser {
+    pub ban_id: String,
+    pub user: Option<UserResponse>, // hidden for bans of anonymous authors
+    pub anonymous_alias: Option<String>,
+    pub banned_by: String,
+    pub reason: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GroupInvitationResponse {
+    pub invitation_id: String,
+    pub group: GroupResponse,
+    pub invited_by: UserResponse,
+    pub created_at: String,
+}
+
+// Search models
+#[derive(Debug, FromRow)]
+pub struct PostMatch {
+    #[sqlx(flatten)]
+    pub post: Post,
+    pub snippet: String,
+    pub rank: f64,
+}
+
+#[derive(Debug, FromRow)]
+pub struct GroupMatch {
+    #[sqlx(flatten)]
+    pub group: Group,
+    pub snippet: String,
+    pub rank: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PostSearchResult {
+    pub post: PostResponse,
+    pub snippet: String, // HTML-escaped, matches wrapped in <mark>
+    pub rank: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GroupSearchResult {
+    pub group: GroupResponse,
+    pub snippet: String, // HTML-escaped, matches wrapped in <mark>
+    pub rank: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchResponse {
+    pub posts: Vec<PostSearchResult>,
+    pub users: Vec<UserResponse>,
+    pub groups: Vec<GroupSearchResult>,
+}
+
+// Admin models
+#[derive(Debug, Deserialize)]
+pub struct RevealAuthorRequest {
+    pub post_id: Option<String>,
+    pub comment_id: Option<String>,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AnonymityReveal {
+    pub id: String,
+    pub admin_id: String,
+    pub target_type: String, // post, comment
+    pub target_id: String,
+    pub revealed_user_id: String,
+    pub reason: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RevealAuthorResponse {
+    pub reveal_id: String,
+    pub user: UserResponse,
 }
 
 // Chat models
@@ -229,13 +816,6 @@ pub struct Message {
     pub is_read: bool,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct SendMessageRequest {
-    pub receiver_id: String,
-    pub encrypted_content: String,
-    pub iv: String,
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MessageResponse {
     pub id: String,
@@ -290,4 +870,15 @@ pub enum WsMessage {
     Error { message: String },
     #[serde(rename = "connected")]
     Connected { user_id: String },
+    #[serde(rename = "notification")]
+    Notification { notification: NotificationPayload },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationPayload {
+    pub kind: String, // friend_request, friend_accepted, post_liked, post_commented, group_post
+    pub actor_id: Option<String>,
+    pub post_id: Option<String>,
+    pub group_id: Option<String>,
+    pub created_at: String,
 }
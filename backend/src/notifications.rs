@@ -0,0 +1,133 @@
+use actix_web::web;
+use chrono::Utc;
+
+use crate::handlers::blocks::is_blocked;
+use crate::handlers::mutes::is_muted;
+use crate::models::{NotificationPayload, WsMessage};
+use crate::AppState;
+
+/// A real-time event about `actor_id` doing something the recipient cares about.
+pub struct Notification {
+    pub kind: &'static str,
+    pub actor_id: String,
+    pub post_id: Option<String>,
+    pub group_id: Option<String>,
+    /// Anonymous actions are delivered without the actor's id.
+    pub is_anonymous: bool,
+}
+
+/// Pushes a notification to every open connection of `recipient_id`, unless the
+/// recipient is the actor, the two are blocked, or the recipient muted the actor
+/// or the group the event happened in. Blocks and mutes of the actor don't apply to
+/// anonymous actions, since a missing notification would give the actor away.
+pub async fn notify(state: &web::Data<AppState>, recipient_id: &str, notification: &Notification) {
+    if recipient_id == notification.actor_id {
+        return;
+    }
+
+    if !notification.is_anonymous
+        && (is_blocked(state, recipient_id, &notification.actor_id).await
+            || is_muted(state, recipient_id, "user", &notification.actor_id).await)
+    {
+        return;
+    }
+
+    if let Some(ref group_id) = notification.group_id {
+        if is_muted(state, recipient_id, "group", group_id).await {
+            return;
+        }
+    }
+
+    deliver(state, recipient_id, notification).await;
+}
+
+/// Pushes a notification without any of `notify`'s checks, for events about the
+/// recipient's own content such as a scheduled post going live.
+pub async fn deliver(state: &web::Data<AppState>, recipient_id: &str, notification: &Notification) {
+    let message = WsMessage::Notification {
+        notification: NotificationPayload {
+            kind: notification.kind.to_string(),
+            actor_id: if notification.is_anonymous { None } else { Some(notification.actor_id.clone()) },
+            post_id: notification.post_id.clone(),
+            group_id: notification.group_id.clone(),
+            created_at: Utc::now().to_rfc3339(),
+        },
+    };
+    let message_json = serde_json::to_string(&message).unwrap();
+
+    let connections = state.ws_connections.read().await;
+    if let Some(channels) = connections.get(recipient_id) {
+        for channel in channels {
+            let _ = channel.send(message_json.clone());
+        }
+    }
+}
+
+/// Fans a notification out to every member of the group.
+pub async fn notify_group_members(state: &web::Data<AppState>, group_id: &str, notification: &Notification) {
+    let member_ids: Vec<String> = sqlx::query_scalar("SELECT user_id FROM group_members WHERE group_id = ?")
+        .bind(group_id)
+        .fetch_all(&state.db)
+        .await
+        .unwrap_or_default();
+
+    for member_id in member_ids {
+        notify(state, &member_id, notification).await;
+    }
+}
+
+/// Fans a notification out to the group's admins and moderators.
+pub async fn notify_group_moderators(state: &web::Data<AppState>, group_id: &str, notification: &Notification) {
+    let moderator_ids: Vec<String> = sqlx::query_scalar(
+        "SELECT user_id FROM group_members WHERE group_id = ? AND role IN ('admin', 'moderator')"
+    )
+    .bind(group_id)
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+
+    for moderator_id in moderator_ids {
+        notify(state, &moderator_id, notification).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{create_user, test_state};
+    use tokio::sync::mpsc;
+
+    #[actix_rt::test]
+    async fn blocks_and_mutes_do_not_unmask_anonymous_actors() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+        sqlx::query("INSERT INTO blocks (id, blocker_id, blocked_id, created_at) VALUES ('b1', ?, ?, ?)")
+            .bind(&alice)
+            .bind(&bob)
+            .bind(Utc::now().to_rfc3339())
+            .execute(&state.db)
+            .await
+            .unwrap();
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        state.ws_connections.write().await.insert(alice.clone(), vec![sender]);
+
+        for is_anonymous in [false, true] {
+            notify(&state, &alice, &Notification {
+                kind: "group_post",
+                actor_id: bob.clone(),
+                post_id: None,
+                group_id: None,
+                is_anonymous,
+            })
+            .await;
+        }
+
+        // Only the anonymous one arrives, without bob's id
+        let message: serde_json::Value = serde_json::from_str(&receiver.try_recv().unwrap()).unwrap();
+        assert_eq!(message["notification"]["kind"], "group_post");
+        assert_eq!(message["notification"]["actor_id"], serde_json::Value::Null);
+        assert!(receiver.try_recv().is_err());
+    }
+}
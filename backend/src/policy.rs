@@ -0,0 +1,832 @@
+//! Authorization decisions for users, posts, comments and groups.
+//!
+//! Handlers load the facts a decision depends on with the async `load_*`
+//! helpers and then ask the pure decision functions, so every rule lives here
+//! and can be tested without a database.
+
+use actix_web::web;
+
+use crate::handlers::blocks::is_blocked;
+use crate::handlers::follows::is_following;
+use crate::handlers::group_members::is_banned;
+use crate::handlers::groups::get_member_role;
+use crate::handlers::posts::are_friends;
+use crate::models::{Comment, Group, Post, Story};
+use crate::AppState;
+
+/// How a viewer relates to the author of some content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relationship {
+    Own,
+    Friend,
+    /// Has an accepted follow of the author.
+    Follower,
+    Stranger,
+    /// Either side has blocked the other.
+    Blocked,
+}
+
+/// Where a viewer stands against a personal post's audience lists.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AudienceMatch {
+    /// In one of the friend lists a `custom` post targets, or targeted by name.
+    pub included: bool,
+    /// Left out with "except these friends".
+    pub excluded: bool,
+}
+
+/// How close a viewer is to a user whose field is shared with an audience.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Closeness {
+    Own,
+    Friend,
+    FriendOfFriend,
+    Other,
+}
+
+/// A user's standing in a group, ordered by privilege.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Membership {
+    None,
+    Member,
+    Moderator,
+    Admin,
+}
+
+impl Membership {
+    pub fn from_role(role: Option<&str>) -> Self {
+        match role {
+            Some("admin") => Membership::Admin,
+            Some("moderator") => Membership::Moderator,
+            Some(_) => Membership::Member,
+            None => Membership::None,
+        }
+    }
+
+    pub fn is_moderator(self) -> bool {
+        self >= Membership::Moderator
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostAction {
+    View,
+    React,
+    Comment,
+    CommentAnonymously,
+    Repost,
+    Vote,
+    Edit,
+    Delete,
+    Moderate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentAction {
+    View,
+    Delete,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupAction {
+    /// Description, cover image, tags and member list.
+    ViewDetails,
+    ViewPosts,
+    Post,
+    PostAnonymously,
+    Invite,
+    Moderate,
+    Administer,
+}
+
+/// Everything a decision about one post depends on, from one viewer's side.
+pub struct PostContext<'a> {
+    pub post: &'a Post,
+    pub group: Option<Group>,
+    pub relationship: Relationship,
+    pub membership: Membership,
+    /// The author has a private account (follows need approval).
+    pub author_requires_approval: bool,
+    pub audience: AudienceMatch,
+}
+
+impl PostContext<'_> {
+    pub fn allows(&self, action: PostAction) -> bool {
+        let own = self.relationship == Relationship::Own;
+        let moderator = self.group.is_some() && self.membership.is_moderator();
+
+        match action {
+            PostAction::View => {
+                if own {
+                    return true;
+                }
+
+                // Posts waiting for approval are only visible to the group's moderators,
+                // drafts and scheduled posts only to their author
+                match self.post.status.as_str() {
+                    "published" => {}
+                    "pending" => return moderator,
+                    _ => return false,
+                }
+
+                match self.group {
+                    Some(_) => {
+                        self.membership >= Membership::Member
+                            && (self.relationship != Relationship::Blocked || moderator)
+                    }
+                    None => personal_visibility_allows(
+                        &self.post.visibility,
+                        self.relationship,
+                        self.author_requires_approval,
+                        self.audience,
+                    ),
+                }
+            }
+            PostAction::React => self.post.status == "published" && self.allows(PostAction::View),
+            PostAction::Comment => {
+                self.allows(PostAction::React) && (!self.post.comments_locked || moderator)
+            }
+            // Anonymity is a group feature, and only where the group allows it
+            PostAction::CommentAnonymously => {
+                self.allows(PostAction::Comment)
+                    && match (&self.post.group_id, &self.group) {
+                        (Some(group_id), Some(group)) => *group_id == group.id && group.allow_anonymous,
+                        _ => false,
+                    }
+            }
+            // Only personal posts with a wide enough audience can be shared, and the
+            // shared copy still only shows the original to people who could see it
+            PostAction::Repost => {
+                self.post.group_id.is_none()
+                    && matches!(self.post.visibility.as_str(), "public" | "friends_only")
+                    && self.allows(PostAction::React)
+            }
+            // Polls follow their post, and stay open when comments are locked
+            PostAction::Vote => self.allows(PostAction::React),
+            PostAction::Edit => own,
+            PostAction::Delete => own || moderator,
+            PostAction::Moderate => moderator,
+        }
+    }
+
+    /// `author_blocked` is whether the viewer and the comment's author are blocked
+    /// in either direction.
+    pub fn allows_comment(&self, action: CommentAction, comment: &Comment, viewer_id: &str, author_blocked: bool) -> bool {
+        match action {
+            // Anonymous comments ignore blocks so blocking can't unmask their authors
+            CommentAction::View => self.allows(PostAction::View) && (comment.is_anonymous || !author_blocked),
+            CommentAction::Delete => {
+                comment.user_id == viewer_id
+                    || self.relationship == Relationship::Own
+                    || self.allows(PostAction::Moderate)
+            }
+        }
+    }
+}
+
+/// Whether a viewer with `relationship` to the author may see a personal post
+/// with `visibility`. Public posts of private accounts are limited to friends and
+/// accepted followers, and `custom` posts to their audience.
+pub fn personal_visibility_allows(
+    visibility: &str,
+    relationship: Relationship,
+    author_requires_approval: bool,
+    audience: AudienceMatch,
+) -> bool {
+    match relationship {
+        Relationship::Own => true,
+        Relationship::Blocked => false,
+        _ if audience.excluded => false,
+        _ if visibility == "custom" => audience.included,
+        Relationship::Friend => visibility == "public" || visibility == "friends_only",
+        Relationship::Follower => visibility == "public",
+        Relationship::Stranger => visibility == "public" && !author_requires_approval,
+    }
+}
+
+pub fn group_allows(action: GroupAction, group: &Group, membership: Membership) -> bool {
+    let member = membership >= Membership::Member;
+
+    match action {
+        GroupAction::ViewDetails => !group.is_private || member,
+        GroupAction::ViewPosts => member,
+        GroupAction::Post => member && (group.posting_policy != "admins" || membership == Membership::Admin),
+        GroupAction::PostAnonymously => group_allows(GroupAction::Post, group, membership) && group.allow_anonymous,
+        // An invitation skips the approval queue, so private groups only let moderators invite
+        GroupAction::Invite => member && (!group.is_private || membership.is_moderator()),
+        GroupAction::Moderate => membership.is_moderator(),
+        GroupAction::Administer => membership == Membership::Admin,
+    }
+}
+
+/// Whether `actor` may act against someone with `target` standing (kick, ban).
+/// Only moderators and admins can, and only on lower ranks.
+pub fn outranks(actor: Membership, target: Membership) -> bool {
+    actor.is_moderator() && actor > target
+}
+
+/// Whether a viewer falls within a privacy audience: everyone, friends_of_friends,
+/// friends or nobody. Owners are always in their own audience.
+pub fn audience_permits(audience: &str, closeness: Closeness) -> bool {
+    match closeness {
+        Closeness::Own => true,
+        Closeness::Friend => ["everyone", "friends_of_friends", "friends"].contains(&audience),
+        Closeness::FriendOfFriend => ["everyone", "friends_of_friends"].contains(&audience),
+        Closeness::Other => audience == "everyone",
+    }
+}
+
+// Loading facts
+
+pub async fn load_relationship(state: &web::Data<AppState>, author_id: &str, viewer_id: &str) -> Relationship {
+    if author_id == viewer_id {
+        Relationship::Own
+    } else if is_blocked(state, author_id, viewer_id).await {
+        Relationship::Blocked
+    } else if are_friends(state, author_id, viewer_id).await {
+        Relationship::Friend
+    } else if is_following(state, viewer_id, author_id).await {
+        Relationship::Follower
+    } else {
+        Relationship::Stranger
+    }
+}
+
+pub async fn load_closeness(state: &web::Data<AppState>, owner_id: &str, viewer_id: &str) -> Closeness {
+    if owner_id == viewer_id {
+        return Closeness::Own;
+    }
+
+    if are_friends(state, owner_id, viewer_id).await {
+        return Closeness::Friend;
+    }
+
+    let mutual_friends: i32 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM friend_edges WHERE user_id = ? AND friend_id IN (SELECT friend_id FROM friend_edges WHERE user_id = ?)"
+    )
+    .bind(owner_id)
+    .bind(viewer_id)
+    .fetch_one(&state.db)
+    .await
+    .unwrap_or(0);
+
+    if mutual_friends > 0 {
+        Closeness::FriendOfFriend
+    } else {
+        Closeness::Other
+    }
+}
+
+/// Whether the user has a private account (follows need approval).
+pub async fn load_requires_approval(state: &web::Data<AppState>, user_id: &str) -> bool {
+    sqlx::query_scalar("SELECT requires_follow_approval FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(&state.db)
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or(false)
+}
+
+/// Banned users count as outsiders, including anonymously banned authors who
+/// are still on the member list.
+pub async fn load_membership(state: &web::Data<AppState>, group_id: &str, user_id: &str) -> Membership {
+    if is_banned(state, group_id, user_id).await {
+        return Membership::None;
+    }
+    Membership::from_role(get_member_role(state, group_id, user_id).await.as_deref())
+}
+
+pub async fn load_post_context<'a>(state: &web::Data<AppState>, post: &'a Post, viewer_id: &str) -> PostContext<'a> {
+    let (group, membership) = match post.group_id {
+        Some(ref group_id) => {
+            let group = sqlx::query_as::<_, Group>("SELECT * FROM groups WHERE id = ?")
+                .bind(group_id)
+                .fetch_optional(&state.db)
+                .await
+                .ok()
+                .flatten();
+            (group, load_membership(state, group_id, viewer_id).await)
+        }
+        None => (None, Membership::None),
+    };
+
+    // Blocks never apply to anonymous content, so they can't be used to unmask its author
+    let mut relationship = load_relationship(state, &post.user_id, viewer_id).await;
+    if post.is_anonymous && relationship == Relationship::Blocked {
+        relationship = Relationship::Stranger;
+    }
+
+    let author_requires_approval = load_requires_approval(state, &post.user_id).await;
+
+    let audience = if post.group_id.is_none() {
+        load_audience_match(state, &post.id, viewer_id).await
+    } else {
+        AudienceMatch::default()
+    };
+
+    PostContext {
+        post,
+        group,
+        relationship,
+        membership,
+        author_requires_approval,
+        audience,
+    }
+}
+
+pub async fn load_audience_match(state: &web::Data<AppState>, post_id: &str, viewer_id: &str) -> AudienceMatch {
+    let rows: Vec<(String, String)> = sqlx::query_as(
+        r#"
+        SELECT kind, target_id FROM post_audience
+        WHERE post_id = ? AND (
+            (kind IN ('user', 'except') AND target_id = ?)
+            OR (kind = 'list' AND target_id IN (SELECT list_id FROM friend_list_members WHERE user_id = ?))
+        )
+        "#
+    )
+    .bind(post_id)
+    .bind(viewer_id)
+    .bind(viewer_id)
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+
+    AudienceMatch {
+        included: rows.iter().any(|(kind, _)| kind == "user" || kind == "list"),
+        excluded: rows.iter().any(|(kind, _)| kind == "except"),
+    }
+}
+
+/// The visibility levels of `author_id`'s personal posts that `viewer_id` may see,
+/// for filtering post lists in SQL. `custom` is included whenever the viewer could
+/// be in a custom audience, so queries must still check `post_audience` per post.
+pub async fn visible_visibilities(state: &web::Data<AppState>, author_id: &str, viewer_id: &str) -> Vec<&'static str> {
+    let relationship = load_relationship(state, author_id, viewer_id).await;
+    let author_requires_approval = load_requires_approval(state, author_id).await;
+
+    let audience = AudienceMatch { included: true, excluded: false };
+
+    ["public", "friends_only", "private", "custom"]
+        .into_iter()
+        .filter(|visibility| personal_visibility_allows(visibility, relationship, author_requires_approval, audience))
+        .collect()
+}
+
+/// Stories follow the visibility rules of personal posts, without custom audiences.
+pub async fn can_view_story(state: &web::Data<AppState>, story: &Story, viewer_id: &str) -> bool {
+    let relationship = load_relationship(state, &story.user_id, viewer_id).await;
+    let author_requires_approval = load_requires_approval(state, &story.user_id).await;
+
+    personal_visibility_allows(&story.visibility, relationship, author_requires_approval, AudienceMatch::default())
+}
+
+/// Returns true if the user is an admin or moderator of the group.
+pub async fn is_group_moderator(state: &web::Data<AppState>, group_id: &str, user_id: &str) -> bool {
+    load_membership(state, group_id, user_id).await.is_moderator()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use Membership as M;
+    use Relationship as R;
+
+    const RELATIONSHIPS: [Relationship; 5] = [R::Own, R::Friend, R::Follower, R::Stranger, R::Blocked];
+    const MEMBERSHIPS: [Membership; 4] = [M::None, M::Member, M::Moderator, M::Admin];
+
+    fn post(visibility: &str, group_id: Option<&str>) -> Post {
+        Post {
+            id: "post-1".to_string(),
+            user_id: "author".to_string(),
+            content: "hello".to_string(),
+            visibility: visibility.to_string(),
+            group_id: group_id.map(str::to_string),
+            is_anonymous: false,
+            created_at: "2024-01-01T00:00:00+00:00".to_string(),
+            updated_at: "2024-01-01T00:00:00+00:00".to_string(),
+            status: "published".to_string(),
+            is_pinned: false,
+            comments_locked: false,
+            publish_at: None,
+            repost_of_id: None,
+            is_plain_repost: false,
+            link_url: None,
+        }
+    }
+
+    fn group(is_private: bool, posting_policy: &str, allow_anonymous: bool) -> Group {
+        Group {
+            id: "group-1".to_string(),
+            name: "Group".to_string(),
+            description: None,
+            cover_image: None,
+            creator_id: "owner".to_string(),
+            is_private,
+            created_at: "2024-01-01T00:00:00+00:00".to_string(),
+            category: None,
+            posting_policy: posting_policy.to_string(),
+            require_post_approval: false,
+            allow_anonymous,
+        }
+    }
+
+    fn comment(user_id: &str, is_anonymous: bool) -> Comment {
+        Comment {
+            id: "comment-1".to_string(),
+            post_id: "post-1".to_string(),
+            user_id: user_id.to_string(),
+            content: "hi".to_string(),
+            is_anonymous,
+            created_at: "2024-01-01T00:00:00+00:00".to_string(),
+        }
+    }
+
+    fn context<'a>(post: &'a Post, group: Option<Group>, relationship: Relationship, membership: Membership, private_account: bool) -> PostContext<'a> {
+        PostContext {
+            post,
+            group,
+            relationship,
+            membership,
+            author_requires_approval: private_account,
+            audience: AudienceMatch::default(),
+        }
+    }
+
+    #[test]
+    fn personal_post_visibility() {
+        // (visibility, relationship, private account, can view)
+        let cases = [
+            ("public", R::Own, false, true),
+            ("public", R::Friend, false, true),
+            ("public", R::Follower, false, true),
+            ("public", R::Stranger, false, true),
+            ("public", R::Blocked, false, false),
+            ("public", R::Own, true, true),
+            ("public", R::Friend, true, true),
+            ("public", R::Follower, true, true),
+            ("public", R::Stranger, true, false),
+            ("public", R::Blocked, true, false),
+            ("friends_only", R::Own, false, true),
+            ("friends_only", R::Friend, false, true),
+            ("friends_only", R::Follower, false, false),
+            ("friends_only", R::Stranger, false, false),
+            ("friends_only", R::Blocked, false, false),
+            ("friends_only", R::Own, true, true),
+            ("friends_only", R::Friend, true, true),
+            ("friends_only", R::Follower, true, false),
+            ("friends_only", R::Stranger, true, false),
+            ("friends_only", R::Blocked, true, false),
+            ("private", R::Own, false, true),
+            ("private", R::Friend, false, false),
+            ("private", R::Follower, false, false),
+            ("private", R::Stranger, false, false),
+            ("private", R::Blocked, false, false),
+            ("private", R::Own, true, true),
+            ("private", R::Friend, true, false),
+            ("private", R::Follower, true, false),
+            ("private", R::Stranger, true, false),
+            ("private", R::Blocked, true, false),
+        ];
+
+        for (visibility, relationship, private_account, expected) in cases {
+            let post = post(visibility, None);
+
+            // Group membership never matters for personal posts
+            for membership in MEMBERSHIPS {
+                let ctx = context(&post, None, relationship, membership, private_account);
+                assert_eq!(
+                    ctx.allows(PostAction::View),
+                    expected,
+                    "view {} post as {:?} (private account: {}, membership: {:?})",
+                    visibility, relationship, private_account, membership
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn custom_audiences_and_exclusions() {
+        // (visibility, relationship, included, excluded, can view)
+        let cases = [
+            ("custom", R::Own, false, false, true),
+            ("custom", R::Friend, true, false, true),
+            ("custom", R::Friend, false, false, false),
+            ("custom", R::Friend, true, true, false),
+            ("custom", R::Follower, true, false, true),
+            ("custom", R::Stranger, true, false, true),
+            ("custom", R::Stranger, false, false, false),
+            ("custom", R::Blocked, true, false, false),
+            ("friends_only", R::Own, false, true, true),
+            ("friends_only", R::Friend, false, false, true),
+            ("friends_only", R::Friend, false, true, false),
+            ("friends_only", R::Stranger, true, false, false),
+            ("public", R::Stranger, true, false, true),
+            ("private", R::Friend, true, false, false),
+        ];
+
+        for (visibility, relationship, included, excluded, expected) in cases {
+            for private_account in [false, true] {
+                let post = post(visibility, None);
+                let mut ctx = context(&post, None, relationship, M::None, private_account);
+                ctx.audience = AudienceMatch { included, excluded };
+
+                // Public posts of private accounts stay hidden from strangers whatever the audience says
+                let expected = expected && !(visibility == "public" && private_account && relationship == R::Stranger);
+                assert_eq!(
+                    ctx.allows(PostAction::View),
+                    expected,
+                    "view {} post as {:?} (included: {}, excluded: {}, private account: {})",
+                    visibility, relationship, included, excluded, private_account
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn group_post_visibility() {
+        // (relationship, membership, can view)
+        let cases = [
+            (R::Own, M::None, true),
+            (R::Own, M::Member, true),
+            (R::Own, M::Moderator, true),
+            (R::Own, M::Admin, true),
+            (R::Friend, M::None, false),
+            (R::Friend, M::Member, true),
+            (R::Friend, M::Moderator, true),
+            (R::Friend, M::Admin, true),
+            (R::Follower, M::None, false),
+            (R::Follower, M::Member, true),
+            (R::Follower, M::Moderator, true),
+            (R::Follower, M::Admin, true),
+            (R::Stranger, M::None, false),
+            (R::Stranger, M::Member, true),
+            (R::Stranger, M::Moderator, true),
+            (R::Stranger, M::Admin, true),
+            (R::Blocked, M::None, false),
+            (R::Blocked, M::Member, false),
+            (R::Blocked, M::Moderator, true),
+            (R::Blocked, M::Admin, true),
+        ];
+
+        for (relationship, membership, expected) in cases {
+            // Group privacy only affects the group's details, not its posts
+            for is_private in [false, true] {
+                let post = post("group", Some("group-1"));
+                let ctx = context(&post, Some(group(is_private, "everyone", true)), relationship, membership, false);
+                assert_eq!(
+                    ctx.allows(PostAction::View),
+                    expected,
+                    "view group post as {:?} with {:?} (private group: {})",
+                    relationship, membership, is_private
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn drafts_and_scheduled_posts_are_private_to_the_author() {
+        for status in ["draft", "scheduled"] {
+            for group_id in [None, Some("group-1")] {
+                let mut post = post(if group_id.is_some() { "group" } else { "public" }, group_id);
+                post.status = status.to_string();
+
+                for relationship in RELATIONSHIPS {
+                    for membership in MEMBERSHIPS {
+                        let group = group_id.map(|_| group(false, "everyone", true));
+                        let ctx = context(&post, group, relationship, membership, false);
+                        let own = relationship == R::Own;
+                        assert_eq!(ctx.allows(PostAction::View), own, "view {} post as {:?} {:?}", status, relationship, membership);
+                        assert_eq!(ctx.allows(PostAction::Edit), own);
+                        assert!(!ctx.allows(PostAction::React));
+                        assert!(!ctx.allows(PostAction::Comment));
+                        assert!(!ctx.allows(PostAction::Vote));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pending_posts_are_visible_to_author_and_moderators_only() {
+        let mut pending = post("group", Some("group-1"));
+        pending.status = "pending".to_string();
+
+        for relationship in RELATIONSHIPS {
+            for membership in MEMBERSHIPS {
+                let ctx = context(&pending, Some(group(false, "everyone", true)), relationship, membership, false);
+                let expected = relationship == R::Own || membership.is_moderator();
+                assert_eq!(ctx.allows(PostAction::View), expected, "{:?} {:?}", relationship, membership);
+                assert!(!ctx.allows(PostAction::React), "react to pending post as {:?} {:?}", relationship, membership);
+                assert!(!ctx.allows(PostAction::Comment), "comment on pending post as {:?} {:?}", relationship, membership);
+                assert!(!ctx.allows(PostAction::Vote), "vote on pending post as {:?} {:?}", relationship, membership);
+            }
+        }
+    }
+
+    #[test]
+    fn reacting_commenting_and_voting_require_viewing() {
+        for visibility in ["public", "friends_only", "private"] {
+            for relationship in RELATIONSHIPS {
+                for private_account in [false, true] {
+                    let post = post(visibility, None);
+                    let ctx = context(&post, None, relationship, M::None, private_account);
+                    let can_view = ctx.allows(PostAction::View);
+                    assert_eq!(ctx.allows(PostAction::React), can_view);
+                    assert_eq!(ctx.allows(PostAction::Comment), can_view);
+                    assert_eq!(ctx.allows(PostAction::Vote), can_view);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reposting() {
+        // (visibility, relationship, can repost)
+        let cases = [
+            ("public", R::Stranger, true),
+            ("public", R::Own, true),
+            ("public", R::Blocked, false),
+            ("friends_only", R::Friend, true),
+            ("friends_only", R::Follower, false),
+            ("friends_only", R::Stranger, false),
+            ("custom", R::Own, false),
+            ("private", R::Own, false),
+        ];
+
+        for (visibility, relationship, expected) in cases {
+            let post = post(visibility, None);
+            let ctx = context(&post, None, relationship, M::None, false);
+            assert_eq!(ctx.allows(PostAction::Repost), expected, "{} {:?}", visibility, relationship);
+        }
+
+        let group_post = post("group", Some("group-1"));
+        let ctx = context(&group_post, Some(group(false, "everyone", true)), R::Own, M::Admin, false);
+        assert!(!ctx.allows(PostAction::Repost));
+
+        let mut draft = post("public", None);
+        draft.status = "draft".to_string();
+        assert!(!context(&draft, None, R::Own, M::None, false).allows(PostAction::Repost));
+    }
+
+    #[test]
+    fn locked_comments() {
+        let mut locked = post("group", Some("group-1"));
+        locked.comments_locked = true;
+
+        // (relationship, membership, can comment)
+        let cases = [
+            (R::Own, M::Member, false),
+            (R::Stranger, M::Member, false),
+            (R::Stranger, M::Moderator, true),
+            (R::Stranger, M::Admin, true),
+        ];
+
+        for (relationship, membership, expected) in cases {
+            let ctx = context(&locked, Some(group(false, "everyone", true)), relationship, membership, false);
+            assert_eq!(ctx.allows(PostAction::Comment), expected, "{:?} {:?}", relationship, membership);
+            assert!(ctx.allows(PostAction::React));
+            assert!(ctx.allows(PostAction::Vote));
+        }
+    }
+
+    #[test]
+    fn anonymous_comments() {
+        // (visibility, group allows anonymity, relationship, membership, can comment anonymously)
+        let cases = [
+            ("friends_only", None, R::Friend, M::None, false),
+            ("friends_only", None, R::Own, M::None, false),
+            ("public", None, R::Stranger, M::None, false),
+            ("public", None, R::Own, M::None, false),
+            ("group", Some(true), R::Stranger, M::Member, true),
+            ("group", Some(true), R::Own, M::Member, true),
+            ("group", Some(true), R::Stranger, M::None, false),
+            ("group", Some(false), R::Stranger, M::Member, false),
+            ("group", Some(false), R::Stranger, M::Admin, false),
+        ];
+
+        for (visibility, allow_anonymous, relationship, membership, expected) in cases {
+            let group_id = allow_anonymous.map(|_| "group-1");
+            let post = post(visibility, group_id);
+            let group = allow_anonymous.map(|allow| group(false, "everyone", allow));
+            let ctx = context(&post, group, relationship, membership, false);
+            assert_eq!(
+                ctx.allows(PostAction::CommentAnonymously),
+                expected,
+                "{} post, group allows anonymity: {:?}, {:?} {:?}",
+                visibility, allow_anonymous, relationship, membership
+            );
+        }
+    }
+
+    #[test]
+    fn anonymous_comment_rejected_when_group_does_not_match_post() {
+        let post = post("group", Some("group-2"));
+        let ctx = context(&post, Some(group(false, "everyone", true)), R::Own, M::Member, false);
+        assert!(!ctx.allows(PostAction::CommentAnonymously));
+    }
+
+    #[test]
+    fn delete_and_moderate_posts() {
+        // (group post, relationship, membership, delete, moderate)
+        let cases = [
+            (false, R::Own, M::None, true, false),
+            (false, R::Friend, M::None, false, false),
+            (false, R::Friend, M::Admin, false, false),
+            (true, R::Own, M::Member, true, false),
+            (true, R::Stranger, M::Member, false, false),
+            (true, R::Stranger, M::Moderator, true, true),
+            (true, R::Blocked, M::Admin, true, true),
+        ];
+
+        for (is_group_post, relationship, membership, delete, moderate) in cases {
+            let post = if is_group_post { post("group", Some("group-1")) } else { post("public", None) };
+            let group = is_group_post.then(|| group(false, "everyone", true));
+            let ctx = context(&post, group, relationship, membership, false);
+            assert_eq!(ctx.allows(PostAction::Delete), delete, "delete as {:?} {:?}", relationship, membership);
+            assert_eq!(ctx.allows(PostAction::Moderate), moderate, "moderate as {:?} {:?}", relationship, membership);
+        }
+    }
+
+    #[test]
+    fn comment_decisions() {
+        let group_post = post("group", Some("group-1"));
+
+        // (relationship to post author, membership, comment by viewer, anonymous comment, blocked with comment author, view, delete)
+        let cases = [
+            (R::Stranger, M::Member, false, false, false, true, false),
+            (R::Stranger, M::Member, false, false, true, false, false),
+            (R::Stranger, M::Member, false, true, true, true, false),
+            (R::Stranger, M::Member, true, false, false, true, true),
+            (R::Own, M::Member, false, false, false, true, true),
+            (R::Stranger, M::Moderator, false, false, false, true, true),
+            (R::Stranger, M::None, false, false, false, false, false),
+        ];
+
+        for (relationship, membership, by_viewer, anonymous, author_blocked, view, delete) in cases {
+            let ctx = context(&group_post, Some(group(false, "everyone", true)), relationship, membership, false);
+            let comment = comment(if by_viewer { "viewer" } else { "someone" }, anonymous);
+            assert_eq!(ctx.allows_comment(CommentAction::View, &comment, "viewer", author_blocked), view);
+            assert_eq!(ctx.allows_comment(CommentAction::Delete, &comment, "viewer", author_blocked), delete);
+        }
+    }
+
+    #[test]
+    fn group_decisions() {
+        // (private, posting policy, allow anonymous, membership,
+        //  view details, view posts, post, post anonymously, invite, moderate, administer)
+        let cases = [
+            (false, "everyone", true, M::None, true, false, false, false, false, false, false),
+            (false, "everyone", true, M::Member, true, true, true, true, true, false, false),
+            (false, "everyone", false, M::Member, true, true, true, false, true, false, false),
+            (false, "admins", true, M::Member, true, true, false, false, true, false, false),
+            (false, "admins", true, M::Moderator, true, true, false, false, true, true, false),
+            (false, "admins", true, M::Admin, true, true, true, true, true, true, true),
+            (true, "everyone", true, M::None, false, false, false, false, false, false, false),
+            (true, "everyone", true, M::Member, true, true, true, true, false, false, false),
+            (true, "everyone", true, M::Moderator, true, true, true, true, true, true, false),
+            (true, "everyone", true, M::Admin, true, true, true, true, true, true, true),
+        ];
+
+        for (is_private, policy, allow_anonymous, membership, view_details, view_posts, can_post, post_anonymously, invite, moderate, administer) in cases {
+            let group = group(is_private, policy, allow_anonymous);
+            let label = format!("private: {}, policy: {}, anonymous: {}, {:?}", is_private, policy, allow_anonymous, membership);
+            assert_eq!(group_allows(GroupAction::ViewDetails, &group, membership), view_details, "view details, {}", label);
+            assert_eq!(group_allows(GroupAction::ViewPosts, &group, membership), view_posts, "view posts, {}", label);
+            assert_eq!(group_allows(GroupAction::Post, &group, membership), can_post, "post, {}", label);
+            assert_eq!(group_allows(GroupAction::PostAnonymously, &group, membership), post_anonymously, "post anonymously, {}", label);
+            assert_eq!(group_allows(GroupAction::Invite, &group, membership), invite, "invite, {}", label);
+            assert_eq!(group_allows(GroupAction::Moderate, &group, membership), moderate, "moderate, {}", label);
+            assert_eq!(group_allows(GroupAction::Administer, &group, membership), administer, "administer, {}", label);
+        }
+    }
+
+    #[test]
+    fn rank_rules() {
+        for actor in MEMBERSHIPS {
+            for target in MEMBERSHIPS {
+                let expected = actor >= M::Moderator && actor > target;
+                assert_eq!(outranks(actor, target), expected, "{:?} over {:?}", actor, target);
+            }
+        }
+    }
+
+    #[test]
+    fn audiences() {
+        // (audience, own, friend, friend of friend, other)
+        let cases = [
+            ("everyone", true, true, true, true),
+            ("friends_of_friends", true, true, true, false),
+            ("friends", true, true, false, false),
+            ("nobody", true, false, false, false),
+        ];
+
+        for (audience, own, friend, friend_of_friend, other) in cases {
+            assert_eq!(audience_permits(audience, Closeness::Own), own, "{} / own", audience);
+            assert_eq!(audience_permits(audience, Closeness::Friend), friend, "{} / friend", audience);
+            assert_eq!(audience_permits(audience, Closeness::FriendOfFriend), friend_of_friend, "{} / friend of friend", audience);
+            assert_eq!(audience_permits(audience, Closeness::Other), other, "{} / other", audience);
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+
+use actix_web::web;
+use chrono::{DateTime, Utc};
+
+use crate::handlers::posts::get_friend_ids;
+use crate::models::Post;
+use crate::AppState;
+
+/// Only posts newer than this are considered for the ranked feed.
+pub const RANKED_WINDOW_DAYS: i64 = 7;
+
+/// How many recent posts are scored before the best ones are picked.
+pub const RANKED_CANDIDATES: i64 = 300;
+
+/// A post's recency weight halves every this many hours.
+const RECENCY_HALF_LIFE_HOURS: f64 = 12.0;
+
+/// Keeps one prolific author from taking over the ranked feed.
+const MAX_POSTS_PER_AUTHOR: usize = 3;
+
+/// Only interactions newer than this count towards closeness.
+const INTERACTION_WINDOW_DAYS: i64 = 30;
+
+/// How close the viewer is to a post's author.
+#[derive(Default, Clone, Copy)]
+pub struct Affinity {
+    pub is_friend: bool,
+    /// Likes and comments between the two, in either direction.
+    pub interactions: i64,
+    pub shared_groups: i64,
+}
+
+/// Scores a post: recency decays exponentially, while engagement and closeness
+/// grow logarithmically so that neither can outweigh recency for long.
+pub fn score(age_hours: f64, likes: i64, comments: i64, affinity: Affinity) -> f64 {
+    let recency = 0.5_f64.powf(age_hours.max(0.0) / RECENCY_HALF_LIFE_HOURS);
+    let engagement = 1.0 + ((1 + likes + 2 * comments) as f64).ln();
+    let closeness = 1.0
+        + if affinity.is_friend { 1.0 } else { 0.0 }
+        + 0.5 * ((1 + affinity.interactions) as f64).ln()
+        + 0.1 * affinity.shared_groups.min(5) as f64;
+
+    recency * engagement * closeness
+}
+
+/// Orders candidate posts by score, keeping at most `MAX_POSTS_PER_AUTHOR` per
+/// author, and returns the top `limit`.
+///
+/// Anonymous posts are scored without closeness and capped on their own, so the
+/// ranking can't hint at who wrote them.
+pub async fn rank_posts(state: &web::Data<AppState>, viewer_id: &str, posts: Vec<Post>, limit: usize) -> Vec<Post> {
+    let now = Utc::now();
+    let engagement = load_engagement(state, &posts).await;
+    let affinities = load_affinities(state, viewer_id, &posts).await;
+
+    let mut scored: Vec<(f64, Post)> = posts
+        .into_iter()
+        .map(|post| {
+            let age_hours = DateTime::parse_from_rfc3339(&post.created_at)
+                .map(|created| (now - created.with_timezone(&Utc)).num_minutes() as f64 / 60.0)
+                .unwrap_or(f64::MAX);
+            let (likes, comments) = engagement.get(&post.id).copied().unwrap_or_default();
+            let affinity = if post.is_anonymous {
+                Affinity::default()
+            } else {
+                affinities.get(&post.user_id).copied().unwrap_or_default()
+            };
+
+            (score(age_hours, likes, comments, affinity), post)
+        })
+        .collect();
+
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut per_author: HashMap<String, usize> = HashMap::new();
+    let mut ranked: Vec<Post> = Vec::new();
+
+    for (_, post) in scored {
+        let author_key = if post.is_anonymous { post.id.clone() } else { post.user_id.clone() };
+        let count = per_author.entry(author_key).or_insert(0);
+        if *count >= MAX_POSTS_PER_AUTHOR {
+            continue;
+        }
+        *count += 1;
+
+        ranked.push(post);
+        if ranked.len() >= limit {
+            break;
+        }
+    }
+
+    ranked
+}
+
+/// Likes and comments per post.
+async fn load_engagement(state: &web::Data<AppState>, posts: &[Post]) -> HashMap<String, (i64, i64)> {
+    let ids: Vec<&str> = posts.iter().map(|p| p.id.as_str()).collect();
+    let ids_json = serde_json::to_string(&ids).unwrap_or_else(|_| "[]".to_string());
+
+    let rows: Vec<(String, i64, i64)> = sqlx::query_as(
+        r#"
+        SELECT
+            p.value,
+            (SELECT COUNT(*) FROM likes WHERE post_id = p.value),
+            (SELECT COUNT(*) FROM comments WHERE post_id = p.value)
+        FROM json_each(?) p
+        "#
+    )
+    .bind(&ids_json)
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+
+    rows.into_iter().map(|(id, likes, comments)| (id, (likes, comments))).collect()
+}
+
+/// Closeness to the authors of named posts, counting only named likes and
+/// comments like friend suggestions do.
+async fn load_affinities(state: &web::Data<AppState>, viewer_id: &str, posts: &[Post]) -> HashMap<String, Affinity> {
+    let mut author_ids: Vec<&str> = posts
+        .iter()
+        .filter(|p| !p.is_anonymous)
+        .map(|p| p.user_id.as_str())
+        .collect();
+    author_ids.sort_unstable();
+    author_ids.dedup();
+
+    let author_ids_json = serde_json::to_string(&author_ids).unwrap_or_else(|_| "[]".to_string());
+    let since = (Utc::now() - chrono::Duration::days(INTERACTION_WINDOW_DAYS)).to_rfc3339();
+
+    let rows: Vec<(String, i64, i64)> = sqlx::query_as(
+        r#"
+        WITH
+        authors AS (
+            SELECT value AS author_id FROM json_each(?2)
+        ),
+        interactions AS (
+            SELECT author_id, COUNT(*) AS n FROM (
+                SELECT p.user_id AS author_id FROM likes l
+                INNER JOIN posts p ON p.id = l.post_id
+                WHERE l.user_id = ?1 AND p.is_anonymous = 0 AND l.created_at > ?3
+                UNION ALL
+                SELECT l.user_id FROM likes l
+                INNER JOIN posts p ON p.id = l.post_id
+                WHERE p.user_id = ?1 AND p.is_anonymous = 0 AND l.created_at > ?3
+                UNION ALL
+                SELECT p.user_id FROM comments c
+                INNER JOIN posts p ON p.id = c.post_id
+                WHERE c.user_id = ?1 AND c.is_anonymous = 0 AND p.is_anonymous = 0 AND c.created_at > ?3
+                UNION ALL
+                SELECT c.user_id FROM comments c
+                INNER JOIN posts p ON p.id = c.post_id
+                WHERE p.user_id = ?1 AND c.is_anonymous = 0 AND p.is_anonymous = 0 AND c.created_at > ?3
+            )
+            GROUP BY author_id
+        ),
+        shared_groups AS (
+            SELECT other.user_id AS author_id, COUNT(*) AS n
+            FROM group_members mine
+            INNER JOIN group_members other ON other.group_id = mine.group_id
+            WHERE mine.user_id = ?1
+            GROUP BY other.user_id
+        )
+        SELECT a.author_id, COALESCE(i.n, 0), COALESCE(g.n, 0)
+        FROM authors a
+        LEFT JOIN interactions i ON i.author_id = a.author_id
+        LEFT JOIN shared_groups g ON g.author_id = a.author_id
+        "#
+    )
+    .bind(viewer_id)
+    .bind(&author_ids_json)
+    .bind(&since)
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+
+    let friend_ids = get_friend_ids(state, viewer_id).await;
+
+    rows.into_iter()
+        .map(|(author_id, interactions, shared_groups)| {
+            // Your own posts rank like a close friend's
+            let is_friend = author_id == viewer_id || friend_ids.contains(&author_id);
+            (author_id, Affinity { is_friend, interactions, shared_groups })
+        })
+        .collect()
+}
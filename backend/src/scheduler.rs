@@ -0,0 +1,227 @@
+use actix_web::web;
+use chrono::Utc;
+
+use crate::models::{Group, Post};
+use crate::notifications::{deliver, notify_group_members, notify_group_moderators, Notification};
+use crate::policy::{group_allows, load_membership, GroupAction};
+use crate::tagging::notify_post_mentions;
+use crate::AppState;
+
+/// How often due scheduled posts are looked for.
+pub const SCHEDULER_INTERVAL_SECONDS: u64 = 30;
+
+pub enum ReleaseOutcome {
+    Published,
+    /// Sent to the group's approval queue.
+    Pending,
+    /// The author can no longer post this in its group.
+    NotAllowed,
+    /// Someone else released or deleted it first.
+    AlreadyReleased,
+}
+
+/// Releases a draft or scheduled post as if it were created now. Personal posts go
+/// live; group posts go through the group's posting rules and approval queue like
+/// new posts do, with the same notifications.
+pub async fn release_post(state: &web::Data<AppState>, post: &Post) -> Result<ReleaseOutcome, sqlx::Error> {
+    let mut needs_approval = false;
+
+    if let Some(ref group_id) = post.group_id {
+        let group = sqlx::query_as::<_, Group>("SELECT * FROM groups WHERE id = ?")
+            .bind(group_id)
+            .fetch_optional(&state.db)
+            .await?;
+        let membership = load_membership(state, group_id, &post.user_id).await;
+
+        let group = match group {
+            Some(group) if group_allows(GroupAction::Post, &group, membership) => group,
+            _ => return Ok(ReleaseOutcome::NotAllowed),
+        };
+
+        if post.is_anonymous && !group_allows(GroupAction::PostAnonymously, &group, membership) {
+            return Ok(ReleaseOutcome::NotAllowed);
+        }
+
+        // Moderators skip the approval queue
+        needs_approval = group.require_post_approval && !group_allows(GroupAction::Moderate, &group, membership);
+    }
+
+    let status = if needs_approval { "pending" } else { "published" };
+    let now = Utc::now().to_rfc3339();
+
+    let result = sqlx::query(
+        "UPDATE posts SET status = ?, created_at = ?, updated_at = ? WHERE id = ? AND status IN ('draft', 'scheduled')"
+    )
+    .bind(status)
+    .bind(&now)
+    .bind(&now)
+    .bind(&post.id)
+    .execute(&state.db)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Ok(ReleaseOutcome::AlreadyReleased);
+    }
+
+    if !needs_approval {
+        let mut released = post.clone();
+        released.status = status.to_string();
+        notify_post_mentions(state, &released).await;
+    }
+
+    if let Some(ref group_id) = post.group_id {
+        let notification = Notification {
+            kind: if needs_approval { "group_post_pending" } else { "group_post" },
+            actor_id: post.user_id.clone(),
+            post_id: Some(post.id.clone()),
+            group_id: Some(group_id.clone()),
+            is_anonymous: post.is_anonymous,
+        };
+        if needs_approval {
+            notify_group_moderators(state, group_id, &notification).await;
+        } else {
+            notify_group_members(state, group_id, &notification).await;
+        }
+    }
+
+    Ok(if needs_approval { ReleaseOutcome::Pending } else { ReleaseOutcome::Published })
+}
+
+/// Periodically releases scheduled posts whose time has come.
+pub async fn run_scheduler(state: web::Data<AppState>) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(SCHEDULER_INTERVAL_SECONDS));
+
+    loop {
+        interval.tick().await;
+        release_due_posts(&state).await;
+    }
+}
+
+/// Releases every scheduled post that is due. Posts that can no longer be
+/// released go back to the author's drafts.
+pub async fn release_due_posts(state: &web::Data<AppState>) {
+    let due = sqlx::query_as::<_, Post>(
+        "SELECT * FROM posts WHERE status = 'scheduled' AND publish_at <= ? ORDER BY publish_at ASC"
+    )
+    .bind(Utc::now().to_rfc3339())
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+
+    for post in due {
+        let kind = match release_post(state, &post).await {
+            Ok(ReleaseOutcome::Published) | Ok(ReleaseOutcome::Pending) => "scheduled_post_published",
+            Ok(ReleaseOutcome::NotAllowed) => {
+                let _ = sqlx::query("UPDATE posts SET status = 'draft', publish_at = NULL WHERE id = ? AND status = 'scheduled'")
+                    .bind(&post.id)
+                    .execute(&state.db)
+                    .await;
+                "scheduled_post_failed"
+            }
+            Ok(ReleaseOutcome::AlreadyReleased) => continue,
+            Err(e) => {
+                eprintln!("Failed to publish scheduled post {}: {}", post.id, e);
+                continue;
+            }
+        };
+
+        deliver(state, &post.user_id, &Notification {
+            kind,
+            actor_id: post.user_id.clone(),
+            post_id: Some(post.id.clone()),
+            group_id: post.group_id.clone(),
+            is_anonymous: false,
+        }).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::groups::leave_group;
+    use crate::handlers::posts::{create_post, get_feed, get_post, update_post};
+    use crate::test_support::{auth_request, befriend, create_group, create_user, json, post_in_group, response_json, test_state};
+
+    async fn feed_contents(state: &web::Data<AppState>, viewer: &str) -> Vec<String> {
+        let response = get_feed(auth_request(viewer), state.clone(), web::Query::from_query("include_groups=true").unwrap()).await;
+        let feed = response_json(response).await;
+        feed.as_array().unwrap().iter().map(|p| p["content"].as_str().unwrap().to_string()).collect()
+    }
+
+    async fn post_status(state: &web::Data<AppState>, post_id: &str) -> String {
+        sqlx::query_scalar("SELECT status FROM posts WHERE id = ?").bind(post_id).fetch_one(&state.db).await.unwrap()
+    }
+
+    async fn make_due(state: &web::Data<AppState>, post_id: &str) {
+        sqlx::query("UPDATE posts SET publish_at = ? WHERE id = ?")
+            .bind((Utc::now() - chrono::Duration::minutes(1)).to_rfc3339())
+            .bind(post_id)
+            .execute(&state.db)
+            .await
+            .unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn due_posts_are_released_and_drafts_stay_hidden() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+        befriend(&state, &alice, &bob).await;
+        let publish_at = (Utc::now() + chrono::Duration::hours(1)).to_rfc3339();
+
+        let mut ids = Vec::new();
+        for body in [
+            serde_json::json!({"content": "Later", "publish_at": publish_at}),
+            serde_json::json!({"content": "Someday", "draft": true}),
+        ] {
+            let response = create_post(auth_request(&alice), state.clone(), json(body)).await;
+            assert_eq!(response.status().as_u16(), 201);
+            ids.push(response_json(response).await["id"].as_str().unwrap().to_string());
+        }
+        let (scheduled, draft) = (ids[0].clone(), ids[1].clone());
+
+        assert!(feed_contents(&state, &bob).await.is_empty());
+        assert!(feed_contents(&state, &alice).await.is_empty());
+        let response = get_post(auth_request(&bob), state.clone(), web::Path::from(scheduled.clone())).await;
+        assert_eq!(response.status().as_u16(), 403);
+
+        // Nothing is released before it's due
+        release_due_posts(&state).await;
+        assert_eq!(post_status(&state, &scheduled).await, "scheduled");
+
+        make_due(&state, &scheduled).await;
+        release_due_posts(&state).await;
+        assert_eq!(post_status(&state, &scheduled).await, "published");
+        assert_eq!(post_status(&state, &draft).await, "draft");
+        assert_eq!(feed_contents(&state, &bob).await, vec!["Later".to_string()]);
+
+        // Released posts can no longer be edited as drafts
+        let response = update_post(
+            auth_request(&alice),
+            state.clone(),
+            web::Path::from(scheduled),
+            json(serde_json::json!({"content": "Edited"})),
+        )
+        .await;
+        assert_eq!(response.status().as_u16(), 400);
+    }
+
+    #[actix_rt::test]
+    async fn scheduled_group_posts_fall_back_to_drafts_when_the_author_left() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+        let group_id = create_group(&state, &alice, &[&bob]).await;
+        let publish_at = (Utc::now() + chrono::Duration::hours(1)).to_rfc3339();
+
+        let post = post_in_group(&state, &bob, &group_id, serde_json::json!({"content": "Soon", "publish_at": publish_at})).await;
+        let post_id = post["id"].as_str().unwrap().to_string();
+        let response = leave_group(auth_request(&bob), state.clone(), web::Path::from(group_id)).await;
+        assert!(response.status().is_success());
+
+        make_due(&state, &post_id).await;
+        release_due_posts(&state).await;
+        assert_eq!(post_status(&state, &post_id).await, "draft");
+        assert!(feed_contents(&state, &alice).await.is_empty());
+    }
+}
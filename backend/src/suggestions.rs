@@ -0,0 +1,120 @@
+use chrono::{Duration, Utc};
+use sqlx::SqlitePool;
+
+/// How long cached suggestions are served before being recomputed on request.
+pub const SUGGESTIONS_TTL_MINUTES: i64 = 60;
+
+/// Only interactions newer than this count towards a suggestion's score.
+const INTERACTION_WINDOW_DAYS: i64 = 30;
+
+/// Recomputes the cached friend suggestions for one user.
+///
+/// Candidates are friends of friends, people sharing a group, and people the user
+/// recently liked or commented on (or who did so on the user's posts). Anonymous
+/// posts and comments are ignored so suggestions can't unmask their authors.
+/// Blocks and pending requests are filtered when suggestions are read, so they
+/// take effect without waiting for a refresh.
+pub async fn refresh_for_user(pool: &SqlitePool, user_id: &str) -> Result<(), sqlx::Error> {
+    let now = Utc::now();
+    let since = (now - Duration::days(INTERACTION_WINDOW_DAYS)).to_rfc3339();
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM friend_suggestions WHERE user_id = ?")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO friend_suggestions (
+            user_id, suggested_id, mutual_friends_count, shared_groups_count, interactions_count, score, computed_at
+        )
+        WITH
+        my_friends AS (
+            SELECT friend_id FROM friend_edges WHERE user_id = ?1
+        ),
+        mutual AS (
+            SELECT friend_id AS candidate_id, COUNT(*) AS n
+            FROM friend_edges
+            WHERE user_id IN (SELECT friend_id FROM my_friends)
+            GROUP BY friend_id
+        ),
+        shared_groups AS (
+            SELECT other.user_id AS candidate_id, COUNT(*) AS n
+            FROM group_members mine
+            INNER JOIN group_members other ON other.group_id = mine.group_id
+            INNER JOIN groups g ON g.id = mine.group_id
+            WHERE mine.user_id = ?1 AND g.is_private = 0
+            GROUP BY other.user_id
+        ),
+        interactions AS (
+            SELECT candidate_id, COUNT(*) AS n FROM (
+                SELECT p.user_id AS candidate_id FROM likes l
+                INNER JOIN posts p ON p.id = l.post_id
+                WHERE l.user_id = ?1 AND p.is_anonymous = 0 AND l.created_at > ?2
+                UNION ALL
+                SELECT l.user_id FROM likes l
+                INNER JOIN posts p ON p.id = l.post_id
+                WHERE p.user_id = ?1 AND p.is_anonymous = 0 AND l.created_at > ?2
+                UNION ALL
+                SELECT p.user_id FROM comments c
+                INNER JOIN posts p ON p.id = c.post_id
+                WHERE c.user_id = ?1 AND c.is_anonymous = 0 AND p.is_anonymous = 0 AND c.created_at > ?2
+                UNION ALL
+                SELECT c.user_id FROM comments c
+                INNER JOIN posts p ON p.id = c.post_id
+                WHERE p.user_id = ?1 AND c.is_anonymous = 0 AND p.is_anonymous = 0 AND c.created_at > ?2
+            )
+            GROUP BY candidate_id
+        ),
+        candidates AS (
+            SELECT candidate_id FROM mutual
+            UNION SELECT candidate_id FROM shared_groups
+            UNION SELECT candidate_id FROM interactions
+        )
+        SELECT
+            ?1,
+            c.candidate_id,
+            COALESCE(m.n, 0),
+            COALESCE(g.n, 0),
+            COALESCE(i.n, 0),
+            COALESCE(m.n, 0) * 3.0 + COALESCE(g.n, 0) * 2.0 + COALESCE(i.n, 0),
+            ?3
+        FROM candidates c
+        LEFT JOIN mutual m ON m.candidate_id = c.candidate_id
+        LEFT JOIN shared_groups g ON g.candidate_id = c.candidate_id
+        LEFT JOIN interactions i ON i.candidate_id = c.candidate_id
+        WHERE c.candidate_id != ?1
+          AND c.candidate_id NOT IN (SELECT friend_id FROM my_friends)
+        "#
+    )
+    .bind(user_id)
+    .bind(&since)
+    .bind(now.to_rfc3339())
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await
+}
+
+/// Periodically recomputes suggestions for every user so requests on large
+/// graphs are served from the cache.
+pub async fn run_refresher(pool: SqlitePool) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(SUGGESTIONS_TTL_MINUTES as u64 * 60));
+
+    loop {
+        interval.tick().await;
+
+        let user_ids: Vec<String> = sqlx::query_scalar("SELECT id FROM users")
+            .fetch_all(&pool)
+            .await
+            .unwrap_or_default();
+
+        for user_id in user_ids {
+            if let Err(e) = refresh_for_user(&pool, &user_id).await {
+                eprintln!("Failed to refresh friend suggestions for {}: {}", user_id, e);
+            }
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use chrono::Utc;
+use sqlx::SqlitePool;
+
+use crate::handlers::stories::delete_stories;
+
+/// How often expired stories are looked for.
+pub const SWEEPER_INTERVAL_SECONDS: u64 = 60;
+
+/// Periodically hard-deletes expired stories along with their views. Media is only
+/// referenced by URL, so it goes with the row.
+pub async fn run_story_sweeper(pool: SqlitePool) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(SWEEPER_INTERVAL_SECONDS));
+
+    loop {
+        interval.tick().await;
+
+        let expired: Vec<String> = sqlx::query_scalar("SELECT id FROM stories WHERE expires_at <= ?")
+            .bind(Utc::now().to_rfc3339())
+            .fetch_all(&pool)
+            .await
+            .unwrap_or_default();
+
+        if expired.is_empty() {
+            continue;
+        }
+
+        if let Err(e) = delete_stories(&pool, &expired).await {
+            eprintln!("Failed to delete expired stories: {}", e);
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use actix_web::web;
+
+use crate::handlers::blocks::is_blocked;
+use crate::models::{Comment, Mention, Post};
+use crate::notifications::{notify, Notification};
+use crate::policy::{load_post_context, CommentAction, PostAction};
+use crate::AppState;
+
+/// Longest hashtag that gets indexed.
+const MAX_HASHTAG_LENGTH: usize = 64;
+
+/// `@username` mentions in `content`, without the `@`, in order of first use.
+pub fn extract_mentions(content: &str) -> Vec<String> {
+    extract_prefixed(content, '@', |c| c.is_alphanumeric() || matches!(c, '_' | '.' | '-'))
+        .into_iter()
+        .map(|name| name.trim_end_matches(['.', '-']).to_string())
+        .filter(|name| !name.is_empty())
+        .fold(Vec::new(), dedup)
+}
+
+/// `#hashtags` in `content`, lowercased and without the `#`. Tags need at least one
+/// letter so that things like "#1" aren't indexed.
+pub fn extract_hashtags(content: &str) -> Vec<String> {
+    extract_prefixed(content, '#', |c| c.is_alphanumeric() || c == '_')
+        .into_iter()
+        .filter(|tag| tag.chars().any(char::is_alphabetic) && tag.chars().count() <= MAX_HASHTAG_LENGTH)
+        .map(|tag| tag.to_lowercase())
+        .fold(Vec::new(), dedup)
+}
+
+/// Normalises a tag from a URL or query the same way tags are indexed.
+pub fn normalize_hashtag(tag: &str) -> String {
+    tag.trim().trim_start_matches('#').to_lowercase()
+}
+
+/// Re-indexes a post's mentions and hashtags from its current content.
+pub async fn sync_post_tags(state: &web::Data<AppState>, post: &Post) -> Result<(), sqlx::Error> {
+    let user_ids = resolve_usernames(state, &extract_mentions(&post.content)).await;
+    let mut tx = state.db.begin().await?;
+
+    sqlx::query("DELETE FROM mentions WHERE post_id = ? AND comment_id IS NULL")
+        .bind(&post.id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM post_hashtags WHERE post_id = ?")
+        .bind(&post.id)
+        .execute(&mut *tx)
+        .await?;
+
+    for user_id in &user_ids {
+        sqlx::query("INSERT INTO mentions (post_id, comment_id, user_id) VALUES (?, NULL, ?)")
+            .bind(&post.id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    for tag in extract_hashtags(&post.content) {
+        sqlx::query("INSERT OR IGNORE INTO post_hashtags (post_id, tag) VALUES (?, ?)")
+            .bind(&post.id)
+            .bind(&tag)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await
+}
+
+/// Indexes the mentions in a new comment.
+pub async fn record_comment_mentions(state: &web::Data<AppState>, comment: &Comment) -> Result<(), sqlx::Error> {
+    for user_id in resolve_usernames(state, &extract_mentions(&comment.content)).await {
+        sqlx::query("INSERT INTO mentions (post_id, comment_id, user_id) VALUES (?, ?, ?)")
+            .bind(&comment.post_id)
+            .bind(&comment.id)
+            .bind(&user_id)
+            .execute(&state.db)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Tells the users mentioned in a post about it, once it's published, if they can
+/// see it.
+pub async fn notify_post_mentions(state: &web::Data<AppState>, post: &Post) {
+    for user_id in mentioned_user_ids(state, &post.id, None).await {
+        if !load_post_context(state, post, &user_id).await.allows(PostAction::View) {
+            continue;
+        }
+
+        notify(state, &user_id, &Notification {
+            kind: "mentioned",
+            actor_id: post.user_id.clone(),
+            post_id: Some(post.id.clone()),
+            group_id: post.group_id.clone(),
+            is_anonymous: post.is_anonymous,
+        }).await;
+    }
+}
+
+/// Tells the users mentioned in a comment about it, if they can see the comment.
+pub async fn notify_comment_mentions(state: &web::Data<AppState>, post: &Post, comment: &Comment) {
+    for user_id in mentioned_user_ids(state, &post.id, Some(&comment.id)).await {
+        let author_blocked = is_blocked(state, &user_id, &comment.user_id).await;
+        let ctx = load_post_context(state, post, &user_id).await;
+        if !ctx.allows_comment(CommentAction::View, comment, &user_id, author_blocked) {
+            continue;
+        }
+
+        notify(state, &user_id, &Notification {
+            kind: "mentioned_in_comment",
+            actor_id: comment.user_id.clone(),
+            post_id: Some(post.id.clone()),
+            group_id: post.group_id.clone(),
+            is_anonymous: comment.is_anonymous,
+        }).await;
+    }
+}
+
+/// The users mentioned in a post (`comment_id` None) or in one of its comments.
+pub async fn load_mentions(state: &web::Data<AppState>, post_id: &str, comment_id: Option<&str>) -> Vec<Mention> {
+    sqlx::query_as::<_, Mention>(
+        r#"
+        SELECT u.id AS user_id, u.username FROM mentions m
+        INNER JOIN users u ON u.id = m.user_id
+        WHERE m.post_id = ? AND m.comment_id IS ?
+        ORDER BY u.username
+        "#
+    )
+    .bind(post_id)
+    .bind(comment_id)
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default()
+}
+
+// Helper functions
+
+/// Tokens that follow `prefix` at the start of a word, so emails and anchors such as
+/// "a@b.com" or "page#top" are skipped.
+fn extract_prefixed(content: &str, prefix: char, is_token_char: impl Fn(char) -> bool) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut previous: Option<char> = None;
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let at_word_start = previous.is_none_or(|p| !(p.is_alphanumeric() || p == '_'));
+
+        if c == prefix && at_word_start {
+            let mut token = String::new();
+            while let Some(&next) = chars.peek() {
+                if !is_token_char(next) {
+                    break;
+                }
+                token.push(next);
+                chars.next();
+            }
+
+            previous = token.chars().last().or(Some(c));
+            if !token.is_empty() {
+                tokens.push(token);
+            }
+            continue;
+        }
+
+        previous = Some(c);
+    }
+
+    tokens
+}
+
+fn dedup(mut items: Vec<String>, item: String) -> Vec<String> {
+    if !items.contains(&item) {
+        items.push(item);
+    }
+    items
+}
+
+async fn resolve_usernames(state: &web::Data<AppState>, usernames: &[String]) -> Vec<String> {
+    let mut user_ids = Vec::new();
+
+    for username in usernames {
+        if let Ok(Some(user_id)) = sqlx::query_scalar::<_, String>("SELECT id FROM users WHERE username = ?")
+            .bind(username)
+            .fetch_optional(&state.db)
+            .await
+        {
+            if !user_ids.contains(&user_id) {
+                user_ids.push(user_id);
+            }
+        }
+    }
+
+    user_ids
+}
+
+async fn mentioned_user_ids(state: &web::Data<AppState>, post_id: &str, comment_id: Option<&str>) -> Vec<String> {
+    sqlx::query_scalar("SELECT user_id FROM mentions WHERE post_id = ? AND comment_id IS ?")
+        .bind(post_id)
+        .bind(comment_id)
+        .fetch_all(&state.db)
+        .await
+        .unwrap_or_default()
+}
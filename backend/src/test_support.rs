@@ -0,0 +1,168 @@
+use actix_web::{test::TestRequest, web, HttpRequest, HttpResponse};
+use chrono::Utc;
+use serde::de::DeserializeOwned;
+use sqlx::sqlite::SqlitePoolOptions;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::auth::create_token;
+use crate::db::create_schema;
+use crate::handlers::{groups, posts};
+use crate::AppState;
+
+const TEST_JWT_SECRET: &str = "test-secret";
+
+/// A fresh app state backed by its own in-memory database.
+pub async fn test_state() -> web::Data<AppState> {
+    // One connection, kept open, so every query sees the same in-memory database
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect("sqlite::memory:")
+        .await
+        .expect("in-memory database");
+
+    web::Data::new(AppState {
+        db: create_schema(pool).await.expect("schema"),
+        jwt_secret: TEST_JWT_SECRET.to_string(),
+        ws_connections: Arc::new(RwLock::new(HashMap::new())),
+    })
+}
+
+/// Signs up a user and returns their id.
+pub async fn create_user(state: &web::Data<AppState>, username: &str) -> String {
+    let user_id = Uuid::new_v4().to_string();
+
+    sqlx::query(
+        "INSERT INTO users (id, email, password_hash, username, display_name, created_at) VALUES (?, ?, '', ?, ?, ?)"
+    )
+    .bind(&user_id)
+    .bind(format!("{}@example.com", username))
+    .bind(username)
+    .bind(username)
+    .bind(Utc::now().to_rfc3339())
+    .execute(&state.db)
+    .await
+    .expect("insert user");
+
+    user_id
+}
+
+/// A request authenticated as `user_id`.
+pub fn auth_request(user_id: &str) -> HttpRequest {
+    let token = create_token(user_id, TEST_JWT_SECRET).expect("token");
+    TestRequest::default()
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_http_request()
+}
+
+/// Creates a public group run by `admin_id` with `member_ids` as members.
+pub async fn create_group(state: &web::Data<AppState>, admin_id: &str, member_ids: &[&str]) -> String {
+    let group = response_json(
+        groups::create_group(auth_request(admin_id), state.clone(), json(serde_json::json!({"name": "Test group"}))).await,
+    )
+    .await;
+    let group_id = group["id"].as_str().expect("group id").to_string();
+
+    for member_id in member_ids {
+        let response = groups::join_group(auth_request(member_id), state.clone(), web::Path::from(group_id.clone())).await;
+        assert!(response.status().is_success(), "join group");
+    }
+
+    group_id
+}
+
+/// Posts to a group as `user_id` and returns the created post.
+pub async fn post_in_group(state: &web::Data<AppState>, user_id: &str, group_id: &str, body: serde_json::Value) -> serde_json::Value {
+    let response = groups::create_group_post(auth_request(user_id), state.clone(), web::Path::from(group_id.to_string()), json(body)).await;
+    assert_eq!(response.status().as_u16(), 201, "create group post");
+    response_json(response).await
+}
+
+/// Makes two users friends.
+pub async fn befriend(state: &web::Data<AppState>, user_a: &str, user_b: &str) {
+    let now = Utc::now().to_rfc3339();
+    sqlx::query("INSERT INTO friendships (id, user_id, friend_id, status, created_at, responded_at) VALUES (?, ?, ?, 'accepted', ?, ?)")
+        .bind(Uuid::new_v4().to_string())
+        .bind(user_a)
+        .bind(user_b)
+        .bind(&now)
+        .bind(&now)
+        .execute(&state.db)
+        .await
+        .expect("insert friendship");
+}
+
+/// Has `blocker_id` block `blocked_id`.
+pub async fn block(state: &web::Data<AppState>, blocker_id: &str, blocked_id: &str) {
+    sqlx::query("INSERT INTO blocks (id, blocker_id, blocked_id, created_at) VALUES (?, ?, ?, ?)")
+        .bind(Uuid::new_v4().to_string())
+        .bind(blocker_id)
+        .bind(blocked_id)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&state.db)
+        .await
+        .expect("insert block");
+}
+
+/// Has a range of authors post about "zebras" to different audiences, and returns a
+/// viewer along with the personal and group posts that viewer should see.
+pub async fn visibility_fixture(state: &web::Data<AppState>) -> (String, Vec<&'static str>, Vec<&'static str>) {
+    let viewer = create_user(state, "viewer").await;
+    let friend = create_user(state, "friend").await;
+    let stranger = create_user(state, "stranger").await;
+    let private_account = create_user(state, "private_account").await;
+    let blocked = create_user(state, "blocked").await;
+    befriend(state, &viewer, &friend).await;
+    befriend(state, &viewer, &blocked).await;
+    sqlx::query("UPDATE users SET requires_follow_approval = 1 WHERE id = ?")
+        .bind(&private_account)
+        .execute(&state.db)
+        .await
+        .expect("private account");
+
+    let personal = [
+        (&friend, "friends_only", false, "zebras for friends"),
+        (&friend, "private", false, "zebras for nobody"),
+        (&friend, "public", true, "zebras in a draft"),
+        (&stranger, "public", false, "zebras for everyone"),
+        (&stranger, "friends_only", false, "zebras for the stranger's friends"),
+        (&private_account, "public", false, "zebras from a private account"),
+        (&blocked, "public", false, "zebras from a blocked user"),
+    ];
+    for (author, visibility, draft, content) in personal {
+        let response = posts::create_post(
+            auth_request(author),
+            state.clone(),
+            json(serde_json::json!({"content": content, "visibility": visibility, "draft": draft})),
+        )
+        .await;
+        assert_eq!(response.status().as_u16(), 201, "create post");
+    }
+
+    let group_id = create_group(state, &viewer, &[&blocked]).await;
+    sqlx::query("UPDATE groups SET allow_anonymous = 1").execute(&state.db).await.expect("allow anonymity");
+    post_in_group(state, &blocked, &group_id, serde_json::json!({"content": "zebras, anonymously", "is_anonymous": true})).await;
+    post_in_group(state, &blocked, &group_id, serde_json::json!({"content": "zebras in the group"})).await;
+
+    block(state, &viewer, &blocked).await;
+
+    (viewer, vec!["zebras for friends", "zebras for everyone"], vec!["zebras, anonymously"])
+}
+
+/// Builds a request body the way actix would deserialize it.
+pub fn json<T: DeserializeOwned>(value: serde_json::Value) -> web::Json<T> {
+    web::Json(serde_json::from_value(value).expect("request body"))
+}
+
+pub async fn response_json(response: HttpResponse) -> serde_json::Value {
+    let bytes = actix_web::body::to_bytes(response.into_body()).await.expect("response body");
+    serde_json::from_slice(&bytes).expect("JSON response")
+}
+
+pub async fn count(state: &web::Data<AppState>, sql: &str) -> i64 {
+    sqlx::query_scalar(sql).fetch_one(&state.db).await.expect("count")
+}
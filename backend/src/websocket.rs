@@ -6,6 +6,7 @@ use tokio::sync::mpsc;
 use uuid::Uuid;
 
 use crate::auth::verify_token;
+use crate::handlers::blocks::is_blocked;
 use crate::models::{MessageResponse, WsMessage};
 use crate::AppState;
 
@@ -60,6 +61,12 @@ pub async fn chat_ws(
                                         }
                                         Ok(WsMessage::ChatMessage { receiver_id, encrypted_content, iv }) => {
                                             if let Some(ref sender_id) = authenticated_user_id {
+                                                if is_blocked(&state_clone, sender_id, &receiver_id).await {
+                                                    let response = WsMessage::Error { message: "Cannot send messages to this user".to_string() };
+                                                    let _ = session.text(serde_json::to_string(&response).unwrap()).await;
+                                                    continue;
+                                                }
+
                                                 // Store message in database
                                                 let message_id = Uuid::new_v4().to_string();
                                                 let now = Utc::now().to_rfc3339();
@@ -115,6 +122,10 @@ pub async fn chat_ws(
                                         }
                                         Ok(WsMessage::Typing { receiver_id }) => {
                                             if let Some(ref sender_id) = authenticated_user_id {
+                                                if is_blocked(&state_clone, sender_id, &receiver_id).await {
+                                                    continue;
+                                                }
+
                                                 let connections = state_clone.ws_connections.read().await;
                                                 if let Some(receiver_channels) = connections.get(&receiver_id) {
                                                     let response = WsMessage::TypingIndicator { sender_id: sender_id.clone() };